@@ -0,0 +1,11 @@
+-- Adding the per file completion state of the audit run
+CREATE TABLE IF NOT EXISTS audit_file (
+    id INTEGER PRIMARY KEY,
+    audit_id INTEGER NOT NULL,
+    input_path TEXT NOT NULL,
+    input_hash TEXT NOT NULL,
+    config_hash TEXT NOT NULL,
+    output_path TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE INDEX IF NOT EXISTS audit_file_audit_id ON audit_file (audit_id);
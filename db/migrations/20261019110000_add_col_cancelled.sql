@@ -0,0 +1,2 @@
+-- Adding cancelled col
+ALTER TABLE audit ADD COLUMN cancelled BOOLEAN NOT NULL DEFAULT FALSE;
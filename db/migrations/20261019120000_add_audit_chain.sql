@@ -0,0 +1,6 @@
+-- Adding the hash chain of the sealed audit records
+ALTER TABLE audit ADD COLUMN chain_index INTEGER;
+ALTER TABLE audit ADD COLUMN prev_hash TEXT;
+ALTER TABLE audit ADD COLUMN row_hash TEXT;
+ALTER TABLE audit ADD COLUMN chain_algo TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS audit_chain_index ON audit (chain_index);
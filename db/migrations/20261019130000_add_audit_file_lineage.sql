@@ -0,0 +1,6 @@
+-- Adding the lineage of the input and output files of the audit run
+ALTER TABLE audit_file ADD COLUMN input_size INTEGER;
+ALTER TABLE audit_file ADD COLUMN output_hash TEXT;
+ALTER TABLE audit_file ADD COLUMN output_size INTEGER;
+ALTER TABLE audit_file ADD COLUMN total_records INTEGER;
+ALTER TABLE audit_file ADD COLUMN failed_records INTEGER;
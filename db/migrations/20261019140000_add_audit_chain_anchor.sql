@@ -0,0 +1,8 @@
+-- Adding the anchor of the hash chain, the last sealed audit record purged by the retention policy
+CREATE TABLE IF NOT EXISTS audit_chain_anchor (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    chain_index INTEGER NOT NULL,
+    row_hash TEXT NOT NULL,
+    purged_records INTEGER NOT NULL DEFAULT 0,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Adding the hash of the anchor, keyed as the chain, so the anchor can not be moved over purged records
+ALTER TABLE audit_chain_anchor ADD COLUMN chain_algo TEXT;
+ALTER TABLE audit_chain_anchor ADD COLUMN anchor_hash TEXT;
@@ -0,0 +1,12 @@
+-- The token vault, the values are encrypted and looked up by their keyed hash
+CREATE TABLE IF NOT EXISTS vault (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    key_check TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE TABLE IF NOT EXISTS token (
+    token TEXT PRIMARY KEY,
+    lookup TEXT NOT NULL UNIQUE,
+    value TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
@@ -1,13 +1,17 @@
+use crate::cli::audit::{audit_command, audit_db_arg, AuditCommand};
 use crate::cli::custom_validation::{dir_exist, worker_in_range};
-use clap::{arg, command, value_parser, ArgMatches};
-use med_core::models::enums::{FileType, Mode, Standard};
+use clap::{arg, command, value_parser, ArgAction, ArgGroup, ArgMatches};
+use med_core::models::enums::{FileType, Mode, OutputPolicy, Standard};
 use med_core::models::params::Params;
+use med_core::strategy::profile::Profile;
 use med_core::utils::error::MedError;
 use std::path::PathBuf;
 use tracing::log::info;
 
 pub struct Cli {
     pub params: Params,
+    /// the `med audit` subcommand, instead of processing files
+    pub audit: Option<AuditCommand>,
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -28,11 +32,24 @@ impl Cli {
         // Get the cli input params
         let matches = Self::get_params().await;
 
+        // query the audit records instead of processing files
+        if let Some(("audit", sub)) = matches.subcommand() {
+            let mut params = new_cli;
+            params.audit_db = sub.get_one::<String>("audit-db").cloned();
+            return Ok(Cli {
+                params,
+                audit: AuditCommand::from_matches(sub),
+            });
+        }
+
         // replace the default cli params by the cli input from the prompt
         let params = Self::fulfill_cli(matches, new_cli).await?;
 
         // return the fulfilled CLI Params
-        Ok(Cli { params })
+        Ok(Cli {
+            params,
+            audit: None,
+        })
     }
 
     /// Privite function fulfill the Cli Struct
@@ -44,7 +61,9 @@ impl Cli {
         {
             Mode::MASK => {
                 params.mode = Mode::MASK;
-                params.key = None;
+                if let Some(key) = matches.get_one::<String>("key") {
+                    params.key = Some(key.to_owned());
+                }
             }
             Mode::ENCRYPT => {
                 params.mode = Mode::ENCRYPT;
@@ -58,6 +77,22 @@ impl Cli {
                     params.key = Some(key.to_owned());
                 }
             }
+            Mode::TOKENIZE => {
+                params.mode = Mode::TOKENIZE;
+                if let Some(key) = matches.get_one::<String>("key") {
+                    params.key = Some(key.to_owned());
+                }
+            }
+            Mode::DETOKENIZE => {
+                params.mode = Mode::DETOKENIZE;
+                if let Some(key) = matches.get_one::<String>("key") {
+                    params.key = Some(key.to_owned());
+                }
+            }
+            Mode::KANONYMITY => {
+                params.mode = Mode::KANONYMITY;
+                params.key = None;
+            }
         }
 
         if let Some(path) = matches.get_one::<PathBuf>("config") {
@@ -89,6 +124,64 @@ impl Cli {
             params.worker = worker.to_owned();
         }
 
+        if matches.get_flag("no-clobber") {
+            params.output_policy = OutputPolicy::NoClobber;
+        } else if matches.get_flag("suffix") {
+            params.output_policy = OutputPolicy::Suffix;
+        } else {
+            params.output_policy = OutputPolicy::Overwrite;
+        }
+
+        params.allow_nested_output = matches.get_flag("allow-nested-output");
+
+        params.incremental = matches.get_flag("incremental");
+        params.prune = matches.get_flag("prune");
+
+        if let Some(chunk_size) = matches.get_one::<u64>("chunk-size") {
+            params.chunk_size = chunk_size.to_owned();
+        }
+
+        if let Some(chunk_threshold) = matches.get_one::<u64>("chunk-threshold") {
+            params.chunk_threshold = chunk_threshold.to_owned();
+        }
+
+        if let Some(path) = matches.get_one::<PathBuf>("report") {
+            info!("report location {:?} : ", path.display());
+            params.report_path = Some(path.display().to_string());
+        }
+
+        if let Some(audit_db) = matches.get_one::<String>("audit-db") {
+            params.audit_db = Some(audit_db.to_owned());
+        }
+
+        if let Some(sinks) = matches.get_many::<String>("audit-sink") {
+            params.audit_sinks = sinks.cloned().collect();
+        }
+
+        if let Some(max) = matches.get_one::<usize>("audit-max-failure-reasons") {
+            params.audit_max_failure_reasons = Some(max.to_owned());
+        }
+
+        if let Some(path) = matches.get_one::<PathBuf>("vault") {
+            params.vault_path = Some(path.display().to_string());
+        }
+
+        if let Some(resume) = matches.get_one::<i64>("resume") {
+            params.resume = Some(resume.to_owned());
+        }
+
+        if let Some(seed) = matches.get_one::<u64>("seed") {
+            params.seed = Some(seed.to_owned());
+        }
+
+        if let Some(path) = matches.get_one::<PathBuf>("key-map") {
+            params.key_map_path = Some(path.display().to_string());
+        }
+
+        if let Some(profile) = matches.get_one::<Profile>("profile") {
+            params.profile = Some(profile.to_owned());
+        }
+
         if let Some(standard) = matches.get_one::<Standard>("standard") {
             match standard {
                 Standard::AES128 => {
@@ -114,6 +207,9 @@ impl Cli {
         command!()
             .propagate_version(true)
             .arg_required_else_help(true)
+            .args_conflicts_with_subcommands(true)
+            .subcommand_negates_reqs(true)
+            .subcommand(audit_command())
             .arg(
                 arg!(<MODE>)
                     .required(true)
@@ -132,17 +228,30 @@ impl Cli {
                 arg!(
                     -k --key <KEY> "Sets a KEY to process file"
                 )
-                .help("Key for Encrypt and Decrypt the file.")
-                .required_if_eq_any([("MODE", "decrypt"), ("MODE", "encrypt")])
-                .requires("standard"),
+                .help("Key for Encrypt and Decrypt the file, of the token vault, or of the fakes and surrogates of the mask mode [env: MED_MASK_KEY] [default: med_mask.key in the config directory]")
+                .required_if_eq_any([
+                    ("MODE", "decrypt"),
+                    ("MODE", "encrypt"),
+                    ("MODE", "tokenize"),
+                    ("MODE", "detokenize"),
+                ]),
             )
             .arg(
                 arg!(
                     -s --standard <STANDARD> "Sets a Encrypt or Decrypt Standard"
                 )
                 .help("Set the Encrypt and Decrypt standard")
+                .required_if_eq_any([("MODE", "decrypt"), ("MODE", "encrypt")])
                 .value_parser(value_parser!(Standard)),
             )
+            .arg(
+                arg!(
+                    --vault <VAULT> "Sets the token vault path"
+                )
+                .required(false)
+                .help("Token vault file path for tokenize and detokenize [env: MED_VAULT] [default: med_vault.db in the config directory]")
+                .value_parser(value_parser!(PathBuf)),
+            )
             .arg(
                 arg!(
                     -f --file <FILE> "Sets a file/directory path"
@@ -181,6 +290,121 @@ impl Cli {
                 .required(false)
                 .value_parser(worker_in_range),
             )
+            .arg(
+                arg!(
+                    --overwrite "Sets overwrite flag"
+                )
+                .help("Overwrite the output files which already exist (default)"),
+            )
+            .arg(
+                arg!(
+                    --"no-clobber" "Sets no-clobber flag"
+                )
+                .help("Skip the input files whose output already exist"),
+            )
+            .arg(
+                arg!(
+                    --suffix "Sets suffix flag"
+                )
+                .help("Write to a suffixed file name (name_1.csv) when the output already exist"),
+            )
+            .group(
+                ArgGroup::new("policy")
+                    .args(["overwrite", "no-clobber", "suffix"])
+                    .multiple(false),
+            )
+            .arg(
+                arg!(
+                    --"allow-nested-output" "Sets allow nested output flag"
+                )
+                .help("Allow the output directory to be inside the input directory"),
+            )
+            .arg(
+                arg!(
+                    --report <REPORT> "Sets a json report file path"
+                )
+                .required(false)
+                .help("Write the run summary, with the metrics of every file, as json to the path")
+                .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(audit_db_arg())
+            .arg(
+                arg!(
+                    --"audit-sink" <SINK> "Sets an audit sink"
+                )
+                .required(false)
+                .action(ArgAction::Append)
+                .help("Also emit the audit record to jsonl:<path>, syslog[:<socket>] or webhook:<url>, repeatable [env: MED_AUDIT_SINKS]"),
+            )
+            .arg(
+                arg!(
+                    --"audit-max-failure-reasons" <MAX> "Sets the maximum number of failure reasons stored per run"
+                )
+                .required(false)
+                .help("Store at most MAX failure reasons per run in the audit database, the failed records count stays exact")
+                .value_parser(value_parser!(usize)),
+            )
+            .arg(
+                arg!(
+                    --resume <AUDIT_ID> "Sets the audit id to resume"
+                )
+                .required(false)
+                .help("Resume the run of the audit id, skip the files it already completed")
+                .value_parser(value_parser!(i64)),
+            )
+            .arg(
+                arg!(
+                    --seed <SEED> "Sets the seed of the masking strategies"
+                )
+                .required(false)
+                .help("Seed the masking strategies to reproduce a run, recorded in the audit [default: the job config seed, or random]")
+                .value_parser(value_parser!(u64)),
+            )
+            .arg(
+                arg!(
+                    --"key-map" <KEY_MAP> "Sets the key map file path"
+                )
+                .required(false)
+                .help("Keep the surrogates of the key fields between runs in the json file [default: the job config key_map, or the run only]")
+                .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                arg!(
+                    --profile <PROFILE> "Sets the built-in compliance profile"
+                )
+                .required(false)
+                .help("Mask the fields found by their name with the strategies of the built-in profile, merged with the job config, recorded in the audit [default: the job config profile]")
+                .value_parser(value_parser!(Profile)),
+            )
+            .arg(
+                arg!(
+                    --incremental "Sets incremental flag"
+                )
+                .help("Only process the new or changed files since the previous run to the output"),
+            )
+            .arg(
+                arg!(
+                    --prune "Sets prune flag"
+                )
+                .help("Delete the outputs whose input disappeared, with --incremental")
+                .requires("incremental"),
+            )
+            .arg(
+                arg!(
+                    --"chunk-size" <MB> "Sets the chunk size in MB"
+                )
+                .required(false)
+                .help("The size (MB) of the record aligned chunks of the large CSV files, parsed in parallel [default: 8]")
+                .value_parser(value_parser!(u64)),
+            )
+            .arg(
+                arg!(
+                    --"chunk-threshold" <MB> "Sets the chunk threshold in MB"
+                )
+                .required(false)
+                .help("CSV files larger than the chunk threshold (MB) are processed in parallel chunks, 0 to disable [default: 64]")
+                .value_parser(value_parser!(u64)),
+            )
             .get_matches()
     }
 }
@@ -0,0 +1,543 @@
+use crate::cli::custom_validation::date_format;
+use clap::{arg, value_parser, Arg, ArgGroup, ArgMatches, Command};
+use med_core::audit::chain::ChainReport;
+use med_core::audit::db::{AuditFile, AuditLocation, Database};
+use med_core::audit::lineage::{check_lineage, LineageStatus};
+use med_core::audit::query::{export_csv, export_json, AuditFilter, AuditRecord};
+use med_core::audit::retention::RetentionPolicy;
+use med_core::models::enums::Mode;
+use med_core::utils::error::{MedError, MedErrorType};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// The `med audit` subcommands
+#[derive(Debug, Clone)]
+pub enum AuditCommand {
+    List(AuditFilter),
+    Show(i64),
+    Export {
+        filter: AuditFilter,
+        format: String,
+        output: Option<PathBuf>,
+    },
+    Verify(Option<String>),
+    Lineage {
+        id: i64,
+        check: bool,
+    },
+    Purge {
+        policy: RetentionPolicy,
+        vacuum: bool,
+    },
+}
+
+/// Returns the clap definition of the `med audit` subcommand
+pub fn audit_command() -> Command {
+    Command::new("audit")
+        .about("Query the audit records of the previous runs")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(audit_db_arg())
+        .subcommand(filter_args(
+            Command::new("list").about("List the audit records, the latest first"),
+        ))
+        .subcommand(
+            Command::new("show")
+                .about("Show an audit record, with its completed files")
+                .arg(
+                    arg!(<AUDIT_ID> "The audit record id")
+                        .required(true)
+                        .value_parser(value_parser!(i64)),
+                ),
+        )
+        .subcommand(
+            filter_args(Command::new("export").about("Export the audit records to csv or json"))
+                .arg(
+                    arg!(--format <FORMAT> "Sets the export format [csv, json]")
+                        .required(false)
+                        .value_parser(["csv", "json"])
+                        .default_value("csv"),
+                )
+                .arg(
+                    arg!(-o --output <OUTPUT> "Sets the export file path, stdout if not set")
+                        .required(false)
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("lineage")
+                .about("Show the input and output files of an audit record")
+                .arg(
+                    arg!(<AUDIT_ID> "The audit record id")
+                        .required(true)
+                        .value_parser(value_parser!(i64)),
+                )
+                .arg(
+                    arg!(--check "Check the outputs were not altered since the run")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("purge")
+                .about("Purge the audit records, and their files, of the retention policy")
+                .arg(
+                    arg!(--"max-age" <DAYS> "Purge the records created more than DAYS days ago")
+                        .required(false)
+                        .value_parser(value_parser!(u32)),
+                )
+                .arg(
+                    arg!(--"max-rows" <ROWS> "Keep only the ROWS latest records")
+                        .required(false)
+                        .value_parser(value_parser!(u32)),
+                )
+                .arg(arg!(--vacuum "Reclaim the space of the purged records").required(false))
+                .group(
+                    ArgGroup::new("retention")
+                        .args(["max-age", "max-rows", "vacuum"])
+                        .multiple(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Verify the hash chain of the audit records, detecting modified, deleted or reordered records")
+                .arg(
+                    arg!(--key <KEY> "Sets the HMAC key of the hash chain")
+                        .required(false)
+                        .help("HMAC key of the hash chain [env: MED_AUDIT_KEY]"),
+                ),
+        )
+}
+
+/// Returns the `--audit-db` arg
+pub fn audit_db_arg() -> Arg {
+    arg!(--"audit-db" <AUDIT_DB> "Sets the audit database location")
+        .required(false)
+        .help("Audit database file path, :memory: or off [env: MED_AUDIT_DB] [default: med.db in the config directory]")
+}
+
+/// Privite function add the filter args to the command
+fn filter_args(command: Command) -> Command {
+    command
+        .arg(
+            arg!(--since <DATE> "Only the records created from the date (YYYY-MM-DD)")
+                .required(false)
+                .value_parser(date_format),
+        )
+        .arg(
+            arg!(--until <DATE> "Only the records created until the date (YYYY-MM-DD)")
+                .required(false)
+                .value_parser(date_format),
+        )
+        .arg(arg!(--user <USER> "Only the records of the user").required(false))
+        .arg(arg!(--hostname <HOSTNAME> "Only the records of the host").required(false))
+        .arg(
+            arg!(--mode <MODE> "Only the records of the mode")
+                .required(false)
+                .value_parser(value_parser!(Mode)),
+        )
+        .arg(
+            arg!(--successed <SUCCESSED> "Only the successed (true) or failed (false) records")
+                .required(false)
+                .value_parser(value_parser!(bool)),
+        )
+        .arg(
+            arg!(--limit <LIMIT> "Sets the maximum number of records")
+                .required(false)
+                .value_parser(value_parser!(u32)),
+        )
+}
+
+/// Privite function returns the filter from the args
+fn filter_from(matches: &ArgMatches) -> AuditFilter {
+    AuditFilter {
+        since: matches.get_one::<String>("since").cloned(),
+        until: matches.get_one::<String>("until").cloned(),
+        user: matches.get_one::<String>("user").cloned(),
+        hostname: matches.get_one::<String>("hostname").cloned(),
+        mode: matches.get_one::<Mode>("mode").copied(),
+        successed: matches.get_one::<bool>("successed").copied(),
+        limit: matches.get_one::<u32>("limit").copied(),
+    }
+}
+
+impl AuditCommand {
+    /// Returns the [AuditCommand] from the `med audit` args
+    pub fn from_matches(matches: &ArgMatches) -> Option<Self> {
+        match matches.subcommand() {
+            Some(("list", sub)) => Some(AuditCommand::List(filter_from(sub))),
+            Some(("show", sub)) => sub
+                .get_one::<i64>("AUDIT_ID")
+                .map(|id| AuditCommand::Show(*id)),
+            Some(("export", sub)) => Some(AuditCommand::Export {
+                filter: filter_from(sub),
+                format: sub
+                    .get_one::<String>("format")
+                    .cloned()
+                    .unwrap_or_else(|| "csv".to_string()),
+                output: sub.get_one::<PathBuf>("output").cloned(),
+            }),
+            Some(("lineage", sub)) => {
+                sub.get_one::<i64>("AUDIT_ID")
+                    .map(|id| AuditCommand::Lineage {
+                        id: *id,
+                        check: sub.get_flag("check"),
+                    })
+            }
+            Some(("purge", sub)) => Some(AuditCommand::Purge {
+                policy: RetentionPolicy {
+                    max_age_days: sub.get_one::<u32>("max-age").copied(),
+                    max_rows: sub.get_one::<u32>("max-rows").copied(),
+                },
+                vacuum: sub.get_flag("vacuum"),
+            }),
+            Some(("verify", sub)) => {
+                Some(AuditCommand::Verify(sub.get_one::<String>("key").cloned()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Run the audit query on the audit database of the location, and print the result to stdout
+    #[cfg(not(tarpaulin_include))]
+    pub async fn run(&self, location: &AuditLocation) -> Result<(), MedError> {
+        let db = Database::new(location).await?;
+        match self {
+            AuditCommand::List(filter) => {
+                let records = db.list(filter).await?;
+                print_records(&records, io::stdout().lock())?;
+            }
+            AuditCommand::Show(id) => {
+                let record = db.show(*id).await?;
+                let files = db.audit_files(*id).await?;
+                let mut stdout = io::stdout().lock();
+                serde_json::to_writer_pretty(&mut stdout, &record)?;
+                writeln!(stdout)?;
+                for file in files {
+                    writeln!(
+                        stdout,
+                        "{} -> {} ({})",
+                        file.input_path, file.output_path, file.input_hash
+                    )?;
+                }
+            }
+            AuditCommand::Export {
+                filter,
+                format,
+                output,
+            } => {
+                let records = db.list(filter).await?;
+                let writer: Box<dyn Write> = match output {
+                    Some(path) => Box::new(File::create(path)?),
+                    None => Box::new(io::stdout().lock()),
+                };
+                match format.as_str() {
+                    "json" => export_json(&records, writer)?,
+                    _ => export_csv(&records, writer)?,
+                }
+            }
+            AuditCommand::Lineage { id, check } => {
+                db.show(*id).await?;
+                let files = db.audit_files(*id).await?;
+                let mut stdout = io::stdout().lock();
+                if !check {
+                    for file in &files {
+                        print_lineage(file, None, &mut stdout)?;
+                    }
+                    return Ok(());
+                }
+                let checks = check_lineage(files);
+                for check in &checks {
+                    print_lineage(&check.file, Some(check.status), &mut stdout)?;
+                }
+                let altered = checks
+                    .iter()
+                    .filter(|check| {
+                        matches!(
+                            check.status,
+                            LineageStatus::Altered | LineageStatus::Missing
+                        )
+                    })
+                    .count();
+                if altered > 0 {
+                    return Err(MedError {
+                        message: Some(format!(
+                            "{} output(s) of audit record_id {} altered or missing",
+                            altered, id
+                        )),
+                        cause: Some("the outputs changed since the run".to_string()),
+                        error_type: MedErrorType::IoError,
+                    });
+                }
+            }
+            AuditCommand::Purge { policy, vacuum } => {
+                let mut stdout = io::stdout().lock();
+                if policy.max_age_days.is_some() || policy.max_rows.is_some() {
+                    let report = db.purge(policy).await?;
+                    writeln!(
+                        stdout,
+                        "purged records: {}, purged files: {}",
+                        report.purged_records, report.purged_files
+                    )?;
+                    if report.kept_records > 0 {
+                        writeln!(
+                            stdout,
+                            "kept records: {}, sealed after a record not purged",
+                            report.kept_records
+                        )?;
+                    }
+                    if let Some(anchor) = report.anchor_index {
+                        writeln!(stdout, "chain anchor: {}", anchor)?;
+                    }
+                }
+                if *vacuum {
+                    db.vacuum().await?;
+                    writeln!(stdout, "vacuumed")?;
+                }
+            }
+            AuditCommand::Verify(key) => {
+                let report = db.with_chain_key(key.clone()).verify().await?;
+                print_chain_report(&report, io::stdout().lock())?;
+                if !report.is_valid() {
+                    return Err(MedError {
+                        message: Some(format!(
+                            "the audit hash chain is broken, {} issue(s) found",
+                            report.issues.len()
+                        )),
+                        cause: Some(
+                            "the audit records were modified, deleted or reordered".to_string(),
+                        ),
+                        error_type: MedErrorType::DatabaseError,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Privite function print the records as a table
+fn print_records<W: Write>(records: &[AuditRecord], mut writer: W) -> Result<(), MedError> {
+    writeln!(
+        writer,
+        "{:>6}  {:<19}  {:<12}  {:<16}  {:<10}  {:<17}  {:>6}  {:>8}  {:>6}  STATUS",
+        "ID", "CREATED_AT", "USER", "HOSTNAME", "MODE", "PROFILE", "FILES", "RECORDS", "FAILED"
+    )?;
+    for record in records {
+        let status = match (record.successed, record.cancelled) {
+            (_, true) => "cancelled",
+            (true, false) => "successed",
+            (false, false) => "failed",
+        };
+        writeln!(
+            writer,
+            "{:>6}  {:<19}  {:<12}  {:<16}  {:<10}  {:<17}  {:>6}  {:>8}  {:>6}  {}",
+            record.id,
+            record.created_at,
+            record.user,
+            record.hostname,
+            record.mode.clone().unwrap_or_default(),
+            record.profile.clone().unwrap_or_default(),
+            record.total_files,
+            record.total_records,
+            record.failed_records,
+            status
+        )?;
+    }
+    Ok(())
+}
+
+/// Privite function print the lineage of the file, with the status of its output once checked
+fn print_lineage<W: Write>(
+    file: &AuditFile,
+    status: Option<LineageStatus>,
+    mut writer: W,
+) -> Result<(), MedError> {
+    writeln!(writer, "{} ({})", file.input_path, file.input_hash)?;
+    writeln!(
+        writer,
+        "  -> {} ({})",
+        file.output_path,
+        file.output_hash.as_deref().unwrap_or("unknown")
+    )?;
+    let mut details = vec![];
+    if let Some(total_records) = file.total_records {
+        details.push(format!("records: {}", total_records));
+    }
+    if let Some(failed_records) = file.failed_records {
+        details.push(format!("failed: {}", failed_records));
+    }
+    if let Some(status) = status {
+        details.push(format!("output: {:?}", status).to_lowercase());
+    }
+    if !details.is_empty() {
+        writeln!(writer, "  {}", details.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Privite function print the outcome of the hash chain verification
+fn print_chain_report<W: Write>(report: &ChainReport, mut writer: W) -> Result<(), MedError> {
+    writeln!(
+        writer,
+        "sealed: {}, unsealed: {}, unverified: {}",
+        report.sealed, report.unsealed, report.unverified
+    )?;
+    if let Some(anchor) = report.anchor_index {
+        writeln!(writer, "anchor: {}", anchor)?;
+    }
+    if let (Some(index), Some(hash)) = (report.head_index, &report.head_hash) {
+        writeln!(writer, "head: {} {}", index, hash)?;
+    }
+    for issue in &report.issues {
+        writeln!(
+            writer,
+            "record {} at chain index {}: {:?}",
+            issue.id, issue.chain_index, issue.kind
+        )?;
+    }
+    let status = match report.is_valid() {
+        true => "ok",
+        false => "broken",
+    };
+    writeln!(writer, "chain: {}", status)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{audit_command, print_chain_report, print_lineage, print_records, AuditCommand};
+    use med_core::audit::chain::{ChainIssue, ChainIssueKind, ChainReport};
+    use med_core::audit::db::AuditFile;
+    use med_core::audit::lineage::LineageStatus;
+    use med_core::audit::query::AuditRecord;
+    use med_core::models::enums::Mode;
+
+    #[test]
+    fn test_audit_command_list() {
+        let matches = audit_command().get_matches_from([
+            "audit",
+            "list",
+            "--user",
+            "med",
+            "--mode",
+            "mask",
+            "--since",
+            "2023-01-01",
+        ]);
+        match AuditCommand::from_matches(&matches) {
+            Some(AuditCommand::List(filter)) => {
+                assert_eq!(filter.user, Some("med".to_string()));
+                assert_eq!(filter.mode, Some(Mode::MASK));
+                assert_eq!(filter.since, Some("2023-01-01".to_string()));
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    #[test]
+    fn test_audit_command_export() {
+        let matches = audit_command().get_matches_from(["audit", "export", "--format", "json"]);
+        match AuditCommand::from_matches(&matches) {
+            Some(AuditCommand::Export { format, output, .. }) => {
+                assert_eq!(format, "json");
+                assert_eq!(output, None);
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    #[test]
+    fn test_print_records() {
+        let record = AuditRecord {
+            id: 1,
+            user: "med".to_string(),
+            cancelled: true,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_records(&[record], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 2);
+        assert!(out.lines().nth(1).unwrap().ends_with("cancelled"));
+    }
+
+    #[test]
+    fn test_audit_command_verify() {
+        let matches = audit_command().get_matches_from(["audit", "verify", "--key", "secret"]);
+        match AuditCommand::from_matches(&matches) {
+            Some(AuditCommand::Verify(key)) => assert_eq!(key, Some("secret".to_string())),
+            _ => unimplemented!(),
+        }
+    }
+
+    #[test]
+    fn test_print_chain_report() {
+        let report = ChainReport {
+            sealed: 2,
+            head_index: Some(2),
+            head_hash: Some("abc".to_string()),
+            issues: vec![ChainIssue {
+                id: 2,
+                chain_index: 2,
+                kind: ChainIssueKind::Modified,
+            }],
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_chain_report(&report, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("head: 2 abc"));
+        assert!(out.contains("record 2 at chain index 2: Modified"));
+        assert!(out.ends_with("chain: broken\n"));
+    }
+
+    #[test]
+    fn test_audit_command_lineage() {
+        let matches = audit_command().get_matches_from(["audit", "lineage", "42", "--check"]);
+        match AuditCommand::from_matches(&matches) {
+            Some(AuditCommand::Lineage { id, check }) => {
+                assert_eq!(id, 42);
+                assert!(check);
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    #[test]
+    fn test_print_lineage() {
+        let file = AuditFile {
+            input_path: "a.csv".to_string(),
+            input_hash: "in".to_string(),
+            output_path: "out/a.csv".to_string(),
+            total_records: Some(10),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_lineage(&file, Some(LineageStatus::Missing), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            "a.csv (in)\n  -> out/a.csv (unknown)\n  records: 10, output: missing\n"
+        );
+    }
+
+    #[test]
+    fn test_audit_command_purge() {
+        let matches =
+            audit_command().get_matches_from(["audit", "purge", "--max-age", "90", "--vacuum"]);
+        match AuditCommand::from_matches(&matches) {
+            Some(AuditCommand::Purge { policy, vacuum }) => {
+                assert_eq!(policy.max_age_days, Some(90));
+                assert_eq!(policy.max_rows, None);
+                assert!(vacuum);
+            }
+            _ => unimplemented!(),
+        }
+        // a limit or the vacuum is required
+        assert!(audit_command()
+            .try_get_matches_from(["audit", "purge"])
+            .is_err());
+    }
+}
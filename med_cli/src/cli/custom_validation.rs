@@ -52,6 +52,25 @@ pub fn dir_exist(s: &str) -> Result<PathBuf, String> {
     }
 }
 
+/// Check and Return if the date is in the YYYY-MM-DD format
+///
+/// # Examples
+/// ```
+/// assert_eq!(date_format("2023-06-01"), Ok("2023-06-01".to_string()));
+/// ```
+pub fn date_format(s: &str) -> Result<String, String> {
+    let parts: Vec<&str> = s.split('-').collect();
+    let valid = parts.len() == 3
+        && parts
+            .iter()
+            .zip([4, 2, 2])
+            .all(|(part, len)| part.len() == len && part.chars().all(|c| c.is_ascii_digit()));
+    match valid {
+        true => Ok(s.to_string()),
+        false => Err(format!("`{}` isn't a YYYY-MM-DD date", s)),
+    }
+}
+
 //************************************************************************************************
 // Unit Test
 //////////////////////////////// */
@@ -59,7 +78,7 @@ pub fn dir_exist(s: &str) -> Result<PathBuf, String> {
 mod tests {
     use std::path::PathBuf;
 
-    use super::{dir_exist, worker_in_range};
+    use super::{date_format, dir_exist, worker_in_range};
 
     #[test]
     fn test_worker_in_range() {
@@ -85,6 +104,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_date_format() {
+        assert_eq!(date_format("2023-06-01"), Ok("2023-06-01".to_string()));
+        assert_eq!(
+            date_format("2023-6-1"),
+            Err("`2023-6-1` isn't a YYYY-MM-DD date".to_string())
+        );
+    }
+
     #[test]
     fn test_dir_exist() {
         match dir_exist("./") {
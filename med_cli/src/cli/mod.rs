@@ -1,2 +1,4 @@
 pub mod app;
+pub mod audit;
 mod custom_validation;
+pub mod signal;
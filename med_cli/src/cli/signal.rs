@@ -0,0 +1,47 @@
+use colored::Colorize;
+use med_core::utils::cancel::CancellationToken;
+use tracing::info;
+
+/// exit code of a run stopped by SIGINT, as the shells report it
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+/// Cancel the token on the first Ctrl-C or SIGTERM, so the run stops gracefully and the audit is
+/// still recorded; a second signal exits immediately
+#[cfg(not(tarpaulin_include))]
+pub fn handle_signals(token: CancellationToken) {
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        info!(
+            "{}: cancelling, waiting for the files in progress, press Ctrl-C again to force exit",
+            "warning".bold().yellow()
+        );
+        token.cancel();
+
+        wait_for_signal().await;
+        info!("{}: forced exit", "warning".bold().yellow());
+        std::process::exit(CANCELLED_EXIT_CODE);
+    });
+}
+
+#[cfg(unix)]
+#[cfg(not(tarpaulin_include))]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {},
+                _ = terminate.recv() => {},
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+#[cfg(not(tarpaulin_include))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -4,29 +4,52 @@ use tracing::info;
 mod cli;
 
 use med_core::app::core::App;
+use med_core::audit::db::AuditLocation;
 use med_core::utils::error::MedError;
 
 use cli::app::Cli;
+use cli::signal::{handle_signals, CANCELLED_EXIT_CODE};
 
 #[tokio::main]
 #[cfg(not(tarpaulin_include))]
 async fn main() -> Result<(), MedError> {
     let now = Instant::now();
     let new_cli = Cli::new().await?;
+    if let Some(audit) = new_cli.audit {
+        let location = AuditLocation::resolve(new_cli.params.audit_db.as_deref(), None)?;
+        return audit.run(&location).await;
+    }
     let params = new_cli.params;
 
     let mut new_app = App::new(params).await?;
+    handle_signals(new_app.cancellation_token());
     let metrics = new_app.process().await?;
     let audit_id = new_app.update_audit(format!("{:?}", now.elapsed())).await?;
+    new_app.write_report(audit_id)?;
 
     info!(
-        "total processed {} files, {} records, with {} records failed, elapsed time {:?}, audit record_id {}",
+        "total processed {} files, skipped {} files, pruned {} files, {} records, with {} records failed, {} records dropped, elapsed time {:?}, audit record_id {}",
         metrics.total_files.to_string().bold().green(),
+        metrics.skipped_files.to_string().bold().green(),
+        metrics.pruned_files.to_string().bold().green(),
         metrics.metadata.total_records.to_string().bold().green(),
         metrics.metadata.failed_records.to_string().bold().green(),
+        metrics.metadata.dropped_records.to_string().bold().green(),
         now.elapsed(),
         audit_id
     );
 
+    if metrics.cancelled {
+        match new_app.is_resumable() {
+            true => info!(
+                "{}: run cancelled, resume it with --resume {}",
+                "warning".bold().yellow(),
+                audit_id
+            ),
+            false => info!("{}: run cancelled", "warning".bold().yellow()),
+        }
+        std::process::exit(CANCELLED_EXIT_CODE);
+    }
+
     Ok(())
 }
@@ -0,0 +1,5 @@
+// the audit and vault database migrations are embedded at build time
+fn main() {
+    println!("cargo:rerun-if-changed=../db/migrations");
+    println!("cargo:rerun-if-changed=../db/vault");
+}
@@ -1,12 +1,22 @@
+use crate::app::manifest::Manifest;
 use crate::app::processor::FileProcessor;
 use crate::audit::app::Audit;
+use crate::audit::checkpoint::Checkpoint;
+use crate::audit::db::AuditLocation;
+use crate::audit::sink::SinkConfig;
+use crate::models::enums::Mode;
+use crate::strategy::mask_key::mask_key;
+use crate::strategy::rule::Strategy;
+use crate::utils::cancel::CancellationToken;
+use crate::utils::error::MedErrorType;
 use crate::{utils::config::JobConfig, utils::error::MedError};
 use colored::Colorize;
 use std::path::Path;
 use tokio::time::Instant;
 use tracing::{debug, info};
 
-use crate::models::{metrics::Metrics, params::Params};
+use crate::models::{metrics::Metrics, params::Params, report::Report};
+use crate::utils::helpers::{commit_output, create_output_dir, discard_output, temp_output_path};
 use crate::utils::logger::logging;
 
 pub struct App {
@@ -15,6 +25,9 @@ pub struct App {
     pub hostname: String,
     pub audit: Audit,
     pub metrics: Metrics,
+    cancel: CancellationToken,
+    /// where the audit records are stored, only the persistent ones can be resumed
+    audit_location: AuditLocation,
 }
 
 impl App {
@@ -43,8 +56,35 @@ impl App {
         logging(params.debug).await;
 
         let user = whoami::username();
-        let hostname = whoami::hostname();
-        let audit = Audit::new().await?;
+        let hostname = whoami::fallible::hostname().unwrap_or_default();
+        // the job config errors are reported when the files are processed
+        let job_conf = JobConfig::new(Path::new(&params.conf_path)).await.ok();
+        let audit_location = Self::audit_location(&params, job_conf.as_ref())?;
+        if params.resume.is_some() && !audit_location.is_persistent() {
+            return Err(MedError {
+                message: Some(format!(
+                    "can not resume a run with the {:?} audit database",
+                    audit_location
+                )),
+                cause: Some("--resume needs a persistent audit database".to_string()),
+                error_type: MedErrorType::ConfigError,
+            });
+        }
+        let audit_sinks = SinkConfig::resolve(
+            &params.audit_sinks,
+            job_conf
+                .as_ref()
+                .map(|conf| conf.audit_sinks.as_slice())
+                .unwrap_or_default(),
+        )?;
+        let mut audit = Audit::new(&audit_location)
+            .await?
+            .with_sinks(audit_sinks.iter().map(SinkConfig::build).collect());
+        audit.db.max_failure_reasons = params.audit_max_failure_reasons.or_else(|| {
+            job_conf
+                .as_ref()
+                .and_then(|conf| conf.audit_max_failure_reasons)
+        });
         let metrics = Metrics::default();
 
         info!(
@@ -63,9 +103,82 @@ impl App {
             hostname,
             audit,
             metrics,
+            cancel: CancellationToken::new(),
+            audit_location,
         })
     }
 
+    /// Returns the [CancellationToken] of the App, cancel it to stop the [App::process] gracefully,
+    /// the files in progress are finished or rolled back, the remaining files are skipped and the
+    /// audit records the run as cancelled
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use med_core::app::core::App;
+    /// use med_core::utils::error::MedError;
+    /// use med_core::models::params::Params;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), MedError> {
+    ///     let params = Params::default();
+    ///     let app = App::new(params).await.unwrap();
+    ///     let token = app.cancellation_token();
+    ///     tokio::spawn(async move {
+    ///         tokio::signal::ctrl_c().await.unwrap();
+    ///         token.cancel();
+    ///     });
+    ///     Ok(())
+    /// }
+    ///
+    /// ```
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Returns true if the run can be resumed with `--resume`, its audit database is persistent
+    pub fn is_resumable(&self) -> bool {
+        self.audit_location.is_persistent()
+    }
+
+    /// Privite function Returns the audit database location of the params, or of the job config
+    fn audit_location(
+        params: &Params,
+        job_conf: Option<&JobConfig>,
+    ) -> Result<AuditLocation, MedError> {
+        let conf_audit_db = job_conf.and_then(|conf| conf.audit_db.as_deref());
+        AuditLocation::resolve(params.audit_db.as_deref(), conf_audit_db)
+    }
+
+    /// Privite function returns the seed of the masking strategies from, in order, the cli, the
+    /// run resumed, the job config, the manifest of the previous incremental run, or a random
+    /// seed, a resumed or incremental run keeps the seed of the files already written
+    async fn strategy_seed(&self, job_conf: &JobConfig) -> u64 {
+        if let Some(seed) = self.params.seed {
+            return seed;
+        }
+        if let Some(resume) = self.params.resume {
+            let resumed = self.audit.db.show(resume).await.ok().and_then(|record| {
+                serde_json::from_str::<serde_json::Value>(&record.runtime_conf)
+                    .ok()
+                    .and_then(|conf| conf["seed"].as_u64())
+            });
+            if let Some(seed) = resumed {
+                return seed;
+            }
+        }
+        if let Some(seed) = job_conf.seed {
+            return seed;
+        }
+        if self.params.incremental {
+            let manifest = Manifest::load(Path::new(&self.params.output_path));
+            if let Some(seed) = manifest.ok().and_then(|manifest| manifest.seed) {
+                return seed;
+            }
+        }
+        rand::random()
+    }
+
     /// Privite function Returns job config
     async fn load_job_config(&self) -> Result<JobConfig, MedError> {
         let conf = JobConfig::new(Path::new(&self.params.conf_path)).await?;
@@ -119,8 +232,41 @@ impl App {
             now.elapsed()
         );
 
+        // the seed is recorded in the audit, so the run can be reproduced
+        self.params.seed = Some(self.strategy_seed(&job_conf).await);
+        // the profile of the cli, or of the job config, is recorded by name
+        self.params.profile = self.params.profile.or(job_conf.profile);
+        // the keyed strategies need the mask key, the other masks never read or create it
+        if self.params.mode == Mode::MASK
+            && job_conf.any_strategy(self.params.profile, Strategy::is_keyed)
+        {
+            self.params.key = Some(mask_key(self.params.key.as_deref())?);
+        }
+
+        // record the audit at the start, so the completed files can be resumed
+        self.fill_audit_summary()?;
+        let audit_id = self.audit.begin().await?;
+        match self.audit_location.is_persistent() {
+            true => info!(
+                "audit record_id {}, resume an interrupted run with --resume {}",
+                audit_id.to_string().bold().green(),
+                audit_id
+            ),
+            false => info!("audit record_id {}", audit_id.to_string().bold().green()),
+        }
+
         let now = Instant::now();
         let mut processor = FileProcessor::new(self.params.clone(), job_conf).await;
+        processor.process_runtime.cancel = self.cancel.clone();
+        processor.checkpoint = Some(
+            Checkpoint::new(
+                self.audit.db.clone(),
+                audit_id,
+                processor.config_hash.clone(),
+                self.params.resume,
+            )
+            .await?,
+        );
         match processor.run().await {
             Ok(metrics) => {
                 self.metrics = metrics.clone();
@@ -132,7 +278,15 @@ impl App {
                         metrics.metadata.record_failed_reason
                     );
                 }
-                self.audit.summary.successed = true;
+                self.audit.summary.cancelled = metrics.cancelled;
+                self.audit.summary.successed = !metrics.cancelled;
+                if metrics.cancelled {
+                    info!(
+                        "{}: processing cancelled, {} files completed",
+                        "warning".bold().yellow(),
+                        metrics.total_files
+                    );
+                }
             }
             Err(err) => {
                 self.audit.summary.process_failure_reason = Some(serde_json::to_string(&err)?);
@@ -186,9 +340,7 @@ impl App {
         if self.params.key.is_some() {
             self.params.key = Some("****".to_owned());
         }
-        self.audit.summary.user = self.user.clone();
-        self.audit.summary.hostname = self.hostname.clone();
-        self.audit.summary.runtime_conf = serde_json::to_string(&self.params)?;
+        self.fill_audit_summary()?;
         self.audit.summary.elapsed_time = elapsed_time;
         debug!("audit summary : {:?}", self.audit.summary);
 
@@ -196,6 +348,93 @@ impl App {
         let id = self.audit.insert().await?;
         Ok(id)
     }
+
+    /// Write the run [Report] as json to the report path of the params, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `audit_id` - the audit record id of the run
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use med_core::app::core::App;
+    /// use med_core::utils::error::MedError;
+    /// use med_core::models::params::Params;
+    /// use med_core::models::enums::{FileType, Mode};
+    /// use tokio::time::Instant;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), MedError> {
+    ///     let now = Instant::now();
+    ///     let params = Params {
+    ///         conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
+    ///         file_path: "../demo/data/input/csv".to_owned(),
+    ///         output_path: "../demo/data/output/report/csv".to_owned(),
+    ///         file_type: FileType::CSV,
+    ///         mode: Mode::MASK,
+    ///         report_path: Some("../demo/data/output/report/doc_report.json".to_owned()),
+    ///         ..Default::default()
+    ///     };
+    ///     let mut app = App::new(params).await.unwrap();
+    ///     let metrics = app.process().await.unwrap();
+    ///     let audit_id = app.update_audit(format!("{:?}", now.elapsed())).await?;
+    ///     app.write_report(audit_id)?;
+    ///     Ok(())
+    /// }
+    ///
+    /// ```
+    pub fn write_report(&self, audit_id: i64) -> Result<(), MedError> {
+        let report_path = match &self.params.report_path {
+            Some(report_path) => Path::new(report_path),
+            None => return Ok(()),
+        };
+        let mut params = self.params.clone();
+        if params.key.is_some() {
+            params.key = Some("****".to_owned());
+        }
+        let report = Report {
+            audit_id,
+            user: self.audit.summary.user.clone(),
+            hostname: self.audit.summary.hostname.clone(),
+            successed: self.audit.summary.successed,
+            cancelled: self.audit.summary.cancelled,
+            process_failure_reason: self.audit.summary.process_failure_reason.clone(),
+            elapsed_time: self.audit.summary.elapsed_time.clone(),
+            params,
+            metrics: self.metrics.clone(),
+        };
+
+        create_output_dir(report_path)?;
+        let temp_path = temp_output_path(report_path);
+        let result = std::fs::File::create(&temp_path)
+            .map_err(MedError::from)
+            .and_then(|mut file| {
+                serde_json::to_writer_pretty(&mut file, &report)?;
+                commit_output(file, &temp_path, report_path)
+            });
+        if result.is_err() {
+            discard_output(&temp_path);
+        }
+        result?;
+        info!(
+            "report written to {}",
+            report_path.display().to_string().bold().green()
+        );
+        Ok(())
+    }
+
+    /// Privite function fill the audit summary with the runtime information, without the key
+    fn fill_audit_summary(&mut self) -> Result<(), MedError> {
+        let mut params = self.params.clone();
+        if params.key.is_some() {
+            params.key = Some("****".to_owned());
+        }
+        self.audit.summary.user = self.user.clone();
+        self.audit.summary.hostname = self.hostname.clone();
+        self.audit.summary.runtime_conf = serde_json::to_string(&params)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
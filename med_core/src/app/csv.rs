@@ -1,143 +1,768 @@
 use colored::Colorize;
 use csv::{StringRecord, Writer};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::Instant;
 use tracing::{debug, info, warn};
 
 use crate::{
-    models::{enums::Mode, metrics::Metadata},
+    models::{
+        enums::{Mode, RecordErrorKind},
+        metrics::{FileMetrics, Metadata, RecordError},
+    },
+    strategy::anonymity::k_anonymize,
+    strategy::rule::{CsvFields, RuleContext},
+    utils::digest::{HashingReader, HashingWriter},
     utils::error::{MedError, MedErrorType},
+    utils::helpers::{commit_output, discard_output, temp_output_path},
 };
 
 use crate::app::processor::ProcessRuntime;
 
 pub fn csv_processor(
-    tx_metadata: flume::Sender<Metadata>,
+    tx_metadata: flume::Sender<FileMetrics>,
     files_path: &str,
     output_path: &str,
     process_runtime: ProcessRuntime,
 ) -> Result<(), MedError> {
-    // prepare the reader and read the file
-    let mut reader = csv::Reader::from_path(files_path)?;
+    // the output is written to a temp file, and only renamed to the output once completed
+    let temp_path = temp_output_path(Path::new(output_path));
+    let now = Instant::now();
+
+    let processed = match process_runtime.mode {
+        Mode::KANONYMITY => {
+            csv_anonymity_file(files_path, output_path, &temp_path, process_runtime)
+        }
+        _ => csv_process_file(files_path, output_path, &temp_path, process_runtime),
+    };
+    match processed {
+        Ok(mut metadata) => {
+            metadata.duration_ms = now.elapsed().as_millis() as u64;
+            tx_metadata.send(metadata).unwrap();
+            Ok(())
+        }
+        Err(err) => {
+            discard_output(&temp_path);
+            Err(err)
+        }
+    }
+}
+
+/// The outcome of a record
+enum RecordOutcome {
+    /// the masked record, and the fields which failed
+    Masked(StringRecord, Vec<RecordError>),
+    /// left out by the subset
+    Dropped,
+    /// the record failed to be read
+    Failed(RecordError),
+}
+
+/// The records of a csv file, read one by one, or in record aligned chunks parsed in parallel
+enum CsvSource {
+    Records(csv::Reader<HashingReader<File>>),
+    Chunks(ChunkReader<HashingReader<File>>),
+}
+
+impl CsvSource {
+    fn headers(&mut self) -> csv::Result<StringRecord> {
+        match self {
+            CsvSource::Records(reader) => Ok(reader.headers()?.to_owned()),
+            CsvSource::Chunks(chunks) => chunks.headers(),
+        }
+    }
+
+    /// Returns the hex encoded sha256 of the file, once every record is read
+    fn finalize(self) -> String {
+        match self {
+            CsvSource::Records(reader) => reader.into_inner().finalize(),
+            CsvSource::Chunks(chunks) => chunks.inner.finalize(),
+        }
+    }
+}
+
+fn csv_process_file(
+    files_path: &str,
+    output_path: &str,
+    temp_path: &Path,
+    mut process_runtime: ProcessRuntime,
+) -> Result<FileMetrics, MedError> {
+    // prepare the reader and read the file, hashing the content on the fly
+    let file = File::open(files_path).map_err(csv::Error::from)?;
+    let file_size = file.metadata()?.len();
+
+    // large files are split in record aligned chunks, parsed and processed in parallel
+    let chunked =
+        process_runtime.chunk_threshold > 0 && file_size > process_runtime.chunk_threshold;
+    let mut source = match chunked {
+        true => {
+            debug!(
+                "split {:?} ({} bytes) in chunks of {} bytes",
+                files_path, file_size, process_runtime.chunk_size
+            );
+            CsvSource::Chunks(ChunkReader::new(
+                HashingReader::new(file),
+                process_runtime.chunk_size as usize,
+            ))
+        }
+        false => CsvSource::Records(csv::Reader::from_reader(HashingReader::new(file))),
+    };
+    let batch_size = rayon::current_num_threads() * 2;
 
     // get the header of the file
-    let headers = reader.headers()?.to_owned();
+    let headers = source.headers()?;
+    process_runtime.with_profile_fields(headers.iter());
 
     // prepare the metrics
     let mut failed_records: usize = 0;
-    let mut record_failed_reason: Vec<MedError> = Vec::new();
+    let mut record_failed_reason: Vec<RecordError> = Vec::new();
 
     let indexs = csv_fields_exist(headers.clone(), &process_runtime.fields);
+    // the other files of the run are still processed
+    if indexs.is_empty() {
+        warn!(
+            "no header of {:?} matches the fields to mask, its records are written unchanged",
+            files_path
+        );
+    }
     debug!("write to location : {:?}", output_path);
 
     let mut total_records = 0;
+    let mut dropped_records = 0;
+    let mut kept_records = 0;
+
+    // the columns kept in the output
+    let subset = &process_runtime.subset;
+    let columns: Vec<usize> = (0..headers.len())
+        .filter(|i| !subset.drop_fields.iter().any(|field| field == &headers[*i]))
+        .collect();
 
     // prepare the writer
-    let mut wtr = Writer::from_path(output_path)?;
+    let mut wtr = Writer::from_writer(HashingWriter::new(File::create(temp_path)?));
 
     // write the header
-    wtr.write_record(&headers)?;
+    wtr.write_record(columns.iter().map(|i| &headers[*i]))?;
 
-    reader.into_records().for_each(|record| {
-        total_records += 1;
-        match record {
-            Ok(records) => {
-                let mut masked_record: StringRecord = StringRecord::new();
-                records.iter().enumerate().for_each(|(i, item)| {
-                    match indexs.contains(&i) {
-                        true => {
-                            let mut masked: String = String::new();
-                            match process_runtime.mode {
-                                Mode::MASK => {
-                                    if let Some(symbols) = process_runtime.mask_symbols.clone() {
-                                        masked = symbols;
-                                    }
-                                }
-                                Mode::ENCRYPT => {
-                                    if let Some(cypher) = process_runtime.cypher.clone() {
-                                        if let Some(standard) = process_runtime.standard {
-                                            masked = cypher.encrypt(item, &standard).unwrap();
-                                        }
-                                    }
-                                }
-                                Mode::DECRYPT => {
-                                    if let Some(cypher) = process_runtime.cypher.clone() {
-                                        if let Some(standard) = process_runtime.standard {
-                                            match cypher.decrypt(item, &standard) {
-                                                Ok(m) => masked = m,
-                                                Err(err) => {
-                                                    let record_error = MedError {
-                                                        message: Some(format!(
-                                                            "please check {} {:?} format",
-                                                            files_path, process_runtime.mode
-                                                        )),
-                                                        cause: Some(err.to_string()),
-                                                        error_type: MedErrorType::CsvError,
-                                                    };
-                                                    let error_str =
-                                                        serde_json::to_string(&record_error)
-                                                            .unwrap();
-                                                    info!(
-                                                        "{}: {}",
-                                                        "warning".bold().yellow(),
-                                                        error_str
-                                                    );
-                                                    record_failed_reason.push(record_error);
-                                                    failed_records += 1;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            masked_record.push_field(&masked);
-                        }
-                        false => masked_record.push_field(item),
-                    };
-                });
-                wtr.write_record(&masked_record).unwrap();
+    loop {
+        // the partial output is rolled back once cancelled
+        if process_runtime.cancel.is_cancelled() {
+            return Err(MedError {
+                message: Some(format!("{} processing cancelled", files_path)),
+                cause: Some("cancellation requested".to_string()),
+                error_type: MedErrorType::CancelledError,
+            });
+        }
+
+        // process the next record, or the next batch of chunks, the order of the output is kept
+        let outcomes: Vec<RecordOutcome> = match &mut source {
+            CsvSource::Records(reader) => match reader.records().next() {
+                Some(Ok(record)) => vec![csv_process_row(
+                    &record,
+                    &headers,
+                    &indexs,
+                    files_path,
+                    &process_runtime,
+                )],
+                Some(Err(err)) => {
+                    let position = err.position().cloned();
+                    vec![RecordOutcome::Failed(
+                        csv_parse_error(files_path, &process_runtime, err.to_string())
+                            .with_position(position.as_ref()),
+                    )]
+                }
+                None => break,
+            },
+            CsvSource::Chunks(chunks) => {
+                let mut batch: Vec<Chunk> = Vec::with_capacity(batch_size);
+                while batch.len() < batch_size {
+                    match chunks.next_chunk()? {
+                        Some(chunk) => batch.push(chunk),
+                        None => break,
+                    }
+                }
+                if batch.is_empty() {
+                    break;
+                }
+                batch
+                    .into_par_iter()
+                    .map(|chunk| {
+                        csv_process_chunk(chunk, &headers, &indexs, files_path, &process_runtime)
+                    })
+                    .collect::<Vec<Vec<RecordOutcome>>>()
+                    .into_iter()
+                    .flatten()
+                    .collect()
             }
-            Err(err) => {
-                let record_error = MedError {
-                    message: Some(format!(
-                        "please check {} {:?} format",
-                        files_path, process_runtime.mode
-                    )),
-                    cause: Some(err.to_string()),
-                    error_type: MedErrorType::CsvError,
-                };
-                let error_str = serde_json::to_string(&record_error).unwrap();
-                info!("{}: {}", "warning".bold().yellow(), error_str);
+        };
+
+        for outcome in outcomes {
+            total_records += 1;
+            let errors = match outcome {
+                RecordOutcome::Masked(masked_record, errors)
+                    if !subset.limit_reached(kept_records) =>
+                {
+                    wtr.write_record(columns.iter().map(|i| &masked_record[*i]))?;
+                    kept_records += 1;
+                    errors
+                }
+                // the records after the limit are still read, so the input hash is complete
+                RecordOutcome::Masked(_, _) | RecordOutcome::Dropped => {
+                    dropped_records += 1;
+                    continue;
+                }
+                RecordOutcome::Failed(record_error) => vec![record_error],
+            };
+            for record_error in errors {
+                info!("{}: {}", "warning".bold().yellow(), record_error);
                 record_failed_reason.push(record_error);
                 failed_records += 1;
             }
-        };
-    });
+        }
+    }
+
     // clear the writer
     wtr.flush()?;
+    let (file, output_hash) = wtr
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .into_parts();
+    commit_output(file, temp_path, Path::new(output_path))?;
+    let bytes_written = std::fs::metadata(output_path)?.len();
 
-    tx_metadata
-        .send(Metadata {
+    Ok(FileMetrics {
+        input_path: files_path.to_string(),
+        output_path: output_path.to_string(),
+        input_hash: Some(source.finalize()),
+        output_hash: Some(output_hash),
+        completed: true,
+        bytes_read: file_size,
+        bytes_written,
+        duration_ms: 0,
+        fields_matched: indexs.iter().map(|i| headers[*i].to_string()).collect(),
+        fields_dropped: (0..headers.len())
+            .filter(|i| !columns.contains(i))
+            .map(|i| headers[i].to_string())
+            .collect(),
+        k_anonymity: None,
+        metadata: Metadata {
             total_records,
             failed_records,
+            dropped_records,
+            record_failed_reason,
+        },
+    })
+}
+
+/// Privite function processes the file in the k-anonymity mode, the whole file is read to find
+/// the classes of the quasi identifiers, the other columns are kept as they are
+fn csv_anonymity_file(
+    files_path: &str,
+    output_path: &str,
+    temp_path: &Path,
+    process_runtime: ProcessRuntime,
+) -> Result<FileMetrics, MedError> {
+    let config = process_runtime.k_anonymity.as_ref().ok_or(MedError {
+        message: Some("missing the k_anonymity of the job config".to_string()),
+        cause: Some("the k-anonymity mode needs the quasi identifiers and k".to_string()),
+        error_type: MedErrorType::ConfigError,
+    })?;
+
+    // prepare the reader and read the file, hashing the content on the fly
+    let file = File::open(files_path).map_err(csv::Error::from)?;
+    let file_size = file.metadata()?.len();
+    let mut reader = csv::Reader::from_reader(HashingReader::new(file));
+    let headers = reader.headers()?.to_owned();
+
+    // the columns of the quasi identifiers
+    let columns = config
+        .quasi_identifiers
+        .iter()
+        .map(|name| {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or(MedError {
+                    message: Some(format!(
+                        "the quasi identifier {:?} is not a column of {}",
+                        name, files_path
+                    )),
+                    cause: Some("please check the k_anonymity quasi_identifiers".to_string()),
+                    error_type: MedErrorType::ConfigError,
+                })
+        })
+        .collect::<Result<Vec<usize>, MedError>>()?;
+
+    // the classes are only known once every record is read
+    let mut total_records = 0;
+    let mut record_failed_reason: Vec<RecordError> = Vec::new();
+    let mut records: Vec<StringRecord> = Vec::new();
+    for record in reader.records() {
+        if process_runtime.cancel.is_cancelled() {
+            return Err(MedError {
+                message: Some(format!("{} processing cancelled", files_path)),
+                cause: Some("cancellation requested".to_string()),
+                error_type: MedErrorType::CancelledError,
+            });
+        }
+        total_records += 1;
+        match record {
+            Ok(record) => records.push(record),
+            Err(err) => {
+                let position = err.position().cloned();
+                let record_error = csv_parse_error(files_path, &process_runtime, err.to_string())
+                    .with_position(position.as_ref());
+                info!("{}: {}", "warning".bold().yellow(), record_error);
+                record_failed_reason.push(record_error);
+            }
+        }
+    }
+
+    let indexes: Vec<u64> = records
+        .iter()
+        .map(|record| record.position().map(|p| p.record()).unwrap_or_default())
+        .collect();
+    let quasi_identifiers = records
+        .iter()
+        .map(|record| columns.iter().map(|i| record[*i].to_string()).collect())
+        .collect();
+    let (generalized, report) = k_anonymize(
+        config,
+        &config.quasi_identifiers,
+        quasi_identifiers,
+        &indexes,
+    );
+    if report.input_k < report.k {
+        info!(
+            "{}: {} is {}-anonymous, {} records in classes smaller than {}, {}-anonymous once {:?}",
+            "warning".bold().yellow(),
+            files_path,
+            report.input_k,
+            report.at_risk_records.len(),
+            report.k,
+            report.output_k,
+            config.enforce
+        );
+    }
+
+    // write the records, the quasi identifiers replaced
+    let mut wtr = Writer::from_writer(HashingWriter::new(File::create(temp_path)?));
+    wtr.write_record(&headers)?;
+    for (record, values) in records.iter().zip(&generalized) {
+        wtr.write_record(record.iter().enumerate().map(|(i, item)| {
+            match columns.iter().position(|column| *column == i) {
+                Some(j) => values[j].as_str(),
+                None => item,
+            }
+        }))?;
+    }
+    wtr.flush()?;
+    let (file, output_hash) = wtr
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .into_parts();
+    commit_output(file, temp_path, Path::new(output_path))?;
+    let bytes_written = std::fs::metadata(output_path)?.len();
+
+    Ok(FileMetrics {
+        input_path: files_path.to_string(),
+        output_path: output_path.to_string(),
+        input_hash: Some(reader.into_inner().finalize()),
+        output_hash: Some(output_hash),
+        completed: true,
+        bytes_read: file_size,
+        bytes_written,
+        duration_ms: 0,
+        fields_matched: config.quasi_identifiers.clone(),
+        fields_dropped: Vec::new(),
+        k_anonymity: Some(report),
+        metadata: Metadata {
+            total_records,
+            failed_records: record_failed_reason.len(),
+            dropped_records: 0,
             record_failed_reason,
+        },
+    })
+}
+
+/// Returns the outcomes of the records of the chunk, parsed with their position in the file
+fn csv_process_chunk(
+    chunk: Chunk,
+    headers: &StringRecord,
+    indexs: &[usize],
+    files_path: &str,
+    process_runtime: &ProcessRuntime,
+) -> Vec<RecordOutcome> {
+    // the positions of the chunk reader start at the chunk, moved to the file
+    let rebase = |position: &csv::Position| {
+        let mut rebased = csv::Position::new();
+        rebased
+            .set_byte(chunk.byte + position.byte())
+            .set_line(chunk.line + position.line() - 1)
+            .set_record(chunk.record + position.record());
+        rebased
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(chunk.bytes.as_slice());
+    reader
+        .records()
+        .map(|record| match record {
+            Ok(mut record) => {
+                let position = record
+                    .position()
+                    .map(rebase)
+                    .unwrap_or_else(csv::Position::new);
+                // the number of fields is checked against the header, as the csv reader does
+                if record.len() != headers.len() {
+                    let cause = format!(
+                        "CSV error: record {} (line: {}, byte: {}): found record with {} fields, but the previous record has {} fields",
+                        position.record(),
+                        position.line(),
+                        position.byte(),
+                        record.len(),
+                        headers.len()
+                    );
+                    return RecordOutcome::Failed(
+                        csv_parse_error(files_path, process_runtime, cause)
+                            .with_position(Some(&position)),
+                    );
+                }
+                record.set_position(Some(position));
+                csv_process_row(&record, headers, indexs, files_path, process_runtime)
+            }
+            Err(err) => {
+                let position = err.position().map(rebase);
+                let cause = match (err.kind(), &position) {
+                    (csv::ErrorKind::Utf8 { err, .. }, Some(position)) => format!(
+                        "CSV parse error: record {} (line {}, field: {}, byte: {}): {}",
+                        position.record(),
+                        position.line(),
+                        err.field(),
+                        position.byte(),
+                        err
+                    ),
+                    _ => err.to_string(),
+                };
+                RecordOutcome::Failed(
+                    csv_parse_error(files_path, process_runtime, cause)
+                        .with_position(position.as_ref()),
+                )
+            }
         })
-        .unwrap();
+        .collect()
+}
+
+/// Returns the outcome of the record, left out by the subset or masked
+fn csv_process_row(
+    record: &StringRecord,
+    headers: &StringRecord,
+    indexs: &[usize],
+    files_path: &str,
+    process_runtime: &ProcessRuntime,
+) -> RecordOutcome {
+    // the subset filters the records on their values before they are masked
+    let index = record.position().map(|p| p.record()).unwrap_or_default();
+    let fields = CsvFields { headers, record };
+    if !process_runtime
+        .subset
+        .keeps(&fields, index, process_runtime.seed)
+    {
+        return RecordOutcome::Dropped;
+    }
+    let (masked_record, errors) =
+        csv_process_record(record, headers, indexs, files_path, process_runtime);
+    RecordOutcome::Masked(masked_record, errors)
+}
+
+/// Returns the [RecordError] of a csv record which failed to be read
+fn csv_parse_error(
+    files_path: &str,
+    process_runtime: &ProcessRuntime,
+    cause: String,
+) -> RecordError {
+    RecordError::new(
+        files_path,
+        RecordErrorKind::Parse,
+        csv_record_error(files_path, process_runtime, cause),
+    )
+}
 
-    Ok(())
+/// Returns the [MedError] of a failed csv record
+fn csv_record_error(files_path: &str, process_runtime: &ProcessRuntime, cause: String) -> MedError {
+    MedError {
+        message: Some(format!(
+            "please check {} {:?} format",
+            files_path, process_runtime.mode
+        )),
+        cause: Some(cause),
+        error_type: MedErrorType::CsvError,
+    }
+}
+
+fn csv_process_record(
+    records: &StringRecord,
+    headers: &StringRecord,
+    indexs: &[usize],
+    files_path: &str,
+    process_runtime: &ProcessRuntime,
+) -> (StringRecord, Vec<RecordError>) {
+    let mut record_errors: Vec<RecordError> = Vec::new();
+    // the error of the field i, located in the file
+    let field_error = |i: usize, kind: RecordErrorKind, err: MedError| {
+        RecordError::new(
+            files_path,
+            kind,
+            csv_record_error(files_path, process_runtime, err.to_string()),
+        )
+        .with_position(records.position())
+        .with_field(headers.get(i).unwrap_or_default())
+    };
+    let mut masked_record: StringRecord = StringRecord::new();
+    records.iter().enumerate().for_each(|(i, item)| {
+        match indexs.contains(&i) {
+            true => {
+                let mut masked: String = String::new();
+                match process_runtime.mode {
+                    Mode::MASK => {
+                        let symbols = process_runtime.mask_symbols.as_deref().unwrap_or_default();
+                        match process_runtime
+                            .rules
+                            .get(headers.get(i).unwrap_or_default())
+                        {
+                            Some(rule) => {
+                                let fields = CsvFields {
+                                    headers,
+                                    record: records,
+                                };
+                                // the field of the records not matching the rule is kept
+                                if !rule.applies(&fields) {
+                                    masked_record.push_field(item);
+                                    return;
+                                }
+                                let context = RuleContext {
+                                    mask_symbols: symbols,
+                                    seed: process_runtime.seed,
+                                    keys: &process_runtime.keys,
+                                    mask_key: &process_runtime.mask_key,
+                                    record: &fields,
+                                };
+                                match rule.strategy.apply(item, &context) {
+                                    Ok(m) => masked = m,
+                                    Err(err) => record_errors.push(field_error(
+                                        i,
+                                        RecordErrorKind::Mask,
+                                        err,
+                                    )),
+                                }
+                            }
+                            None => masked = symbols.to_string(),
+                        }
+                    }
+                    Mode::ENCRYPT => {
+                        if let Some(cypher) = process_runtime.cypher.clone() {
+                            if let Some(standard) = process_runtime.standard {
+                                match cypher.encrypt(item, &standard) {
+                                    Ok(m) => masked = m,
+                                    Err(err) => record_errors.push(field_error(
+                                        i,
+                                        RecordErrorKind::Encrypt,
+                                        err,
+                                    )),
+                                }
+                            }
+                        }
+                    }
+                    Mode::DECRYPT => {
+                        if let Some(cypher) = process_runtime.cypher.clone() {
+                            if let Some(standard) = process_runtime.standard {
+                                match cypher.decrypt(item, &standard) {
+                                    Ok(m) => masked = m,
+                                    Err(err) => record_errors.push(field_error(
+                                        i,
+                                        RecordErrorKind::Decrypt,
+                                        err,
+                                    )),
+                                }
+                            }
+                        }
+                    }
+                    Mode::TOKENIZE => {
+                        if let Some(vault) = &process_runtime.vault {
+                            match vault.tokenize(item) {
+                                Ok(m) => masked = m,
+                                Err(err) => record_errors.push(field_error(
+                                    i,
+                                    RecordErrorKind::Tokenize,
+                                    err,
+                                )),
+                            }
+                        }
+                    }
+                    Mode::DETOKENIZE => {
+                        if let Some(vault) = &process_runtime.vault {
+                            match vault.detokenize(item) {
+                                Ok(m) => masked = m,
+                                Err(err) => record_errors.push(field_error(
+                                    i,
+                                    RecordErrorKind::Detokenize,
+                                    err,
+                                )),
+                            }
+                        }
+                    }
+                    // the quasi identifiers are processed by the whole file
+                    Mode::KANONYMITY => masked = item.to_string(),
+                }
+                masked_record.push_field(&masked);
+            }
+            false => masked_record.push_field(item),
+        };
+    });
+    (masked_record, record_errors)
+}
+
+/// A record aligned chunk of a csv file, with the position of its first byte in the file
+struct Chunk {
+    bytes: Vec<u8>,
+    byte: u64,
+    line: u64,
+    record: u64,
+}
+
+/// Where the scan of a csv file is, within a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    QuoteInQuoted,
+}
+
+/// Reader splitting a csv file in record aligned chunks of at least the chunk size, the bytes
+/// are scanned as the csv reader parses them, so the new lines of the quoted fields do not
+/// split a record
+struct ChunkReader<R> {
+    inner: R,
+    chunk_size: usize,
+    state: ScanState,
+    /// the current record has data, the empty lines are not records
+    has_data: bool,
+    /// the bytes read past the end of the previous chunk
+    pending: Vec<u8>,
+    /// the position of the next chunk in the file
+    byte: u64,
+    line: u64,
+    record: u64,
+}
+
+impl<R: Read> ChunkReader<R> {
+    fn new(inner: R, chunk_size: usize) -> Self {
+        ChunkReader {
+            inner,
+            chunk_size: chunk_size.max(1),
+            state: ScanState::FieldStart,
+            has_data: false,
+            pending: Vec::new(),
+            byte: 0,
+            line: 1,
+            record: 0,
+        }
+    }
+
+    /// Returns the header, the first record of the file
+    fn headers(&mut self) -> csv::Result<StringRecord> {
+        let chunk = self.read_chunk(1)?.unwrap_or(Chunk {
+            bytes: Vec::new(),
+            byte: 0,
+            line: 1,
+            record: 0,
+        });
+        Ok(csv::Reader::from_reader(chunk.bytes.as_slice())
+            .headers()?
+            .to_owned())
+    }
+
+    fn next_chunk(&mut self) -> io::Result<Option<Chunk>> {
+        self.read_chunk(self.chunk_size)
+    }
+
+    /// Returns the bytes up to the end of the first record past the size, or the end of the file
+    fn read_chunk(&mut self, size: usize) -> io::Result<Option<Chunk>> {
+        let mut bytes = std::mem::take(&mut self.pending);
+        let mut buffer = vec![0; 64 * 1024];
+        let (mut scanned, mut lines, mut records) = (0, 0, 0);
+        let end = loop {
+            let mut end = None;
+            while end.is_none() && scanned < bytes.len() {
+                let byte = bytes[scanned];
+                scanned += 1;
+                lines += (byte == b'\n') as u64;
+                let (state, end_of_record) = scan(self.state, byte);
+                self.state = state;
+                match end_of_record {
+                    // the empty lines are skipped, as the csv reader does
+                    true if std::mem::take(&mut self.has_data) => {
+                        records += 1;
+                        if scanned >= size {
+                            end = Some(scanned);
+                        }
+                    }
+                    true => (),
+                    false => self.has_data = true,
+                }
+            }
+            if end.is_some() {
+                break end;
+            }
+            match self.inner.read(&mut buffer)? {
+                0 => break None,
+                n => bytes.extend_from_slice(&buffer[..n]),
+            }
+        };
+        if let Some(end) = end {
+            self.pending = bytes.split_off(end);
+        }
+        // the last record may not end with a new line
+        records += std::mem::take(&mut self.has_data) as u64;
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+        let chunk = Chunk {
+            byte: self.byte,
+            line: self.line,
+            record: self.record,
+            bytes,
+        };
+        self.byte += chunk.bytes.len() as u64;
+        self.line += lines;
+        self.record += records;
+        Ok(Some(chunk))
+    }
+}
+
+/// Returns the state after the byte of a csv record, and true if the byte ends the record
+fn scan(state: ScanState, byte: u8) -> (ScanState, bool) {
+    match (state, byte) {
+        (ScanState::Quoted, b'"') => (ScanState::QuoteInQuoted, false),
+        (ScanState::Quoted, _) => (ScanState::Quoted, false),
+        (ScanState::QuoteInQuoted, b'"') => (ScanState::Quoted, false),
+        (ScanState::FieldStart, b'"') => (ScanState::Quoted, false),
+        (_, b',') => (ScanState::FieldStart, false),
+        (_, b'\n' | b'\r') => (ScanState::FieldStart, true),
+        _ => (ScanState::Unquoted, false),
+    }
 }
 
 fn csv_fields_exist(headers: StringRecord, fields: &[String]) -> Vec<usize> {
-    let indexs = headers
+    headers
         .iter()
         .enumerate()
         .filter(|(_, item)| fields.contains(&item.to_string()))
         .map(|(i, _)| i)
-        .collect::<Vec<_>>();
-
-    if indexs.is_empty() {
-        warn!("Please check your csv file, there is no marched header found in the csv files");
-        std::process::exit(1);
-    }
-    indexs
+        .collect::<Vec<_>>()
 }
 
 #[cfg(test)]
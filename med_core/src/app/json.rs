@@ -1,192 +1,581 @@
-use serde_json::Value;
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeSet;
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
 
 use crate::{
-    models::{enums::Mode, metrics::Metadata},
+    models::{
+        enums::{Mode, RecordErrorKind},
+        metrics::{FileMetrics, Metadata, RecordError},
+    },
+    strategy::rule::{RecordFields, RuleContext},
+    utils::digest::sha256_hex,
     utils::error::{MedError, MedErrorType},
+    utils::helpers::{commit_output, discard_output, temp_output_path},
 };
 
 use crate::app::processor::ProcessRuntime;
 
 pub fn json_processor(
-    tx_metadata: flume::Sender<Metadata>,
+    tx_metadata: flume::Sender<FileMetrics>,
     files_path: &str,
     output_path: &str,
-    process_runtime: ProcessRuntime,
+    mut process_runtime: ProcessRuntime,
 ) -> Result<(), MedError> {
     // prepare the metrics
     let mut total_records: usize = 0;
     let mut failed_records: usize = 0;
-    let mut record_failed_reason: Vec<MedError> = Vec::new();
+    let mut record_failed_reason: Vec<RecordError> = Vec::new();
+    let mut input_hash: Option<String> = None;
+    let mut output_hash: Option<String> = None;
+    let mut bytes_read: u64 = 0;
+    let mut bytes_written: u64 = 0;
+    let mut fields_matched: Vec<String> = Vec::new();
+    let mut fields_dropped: Vec<String> = Vec::new();
+    let mut dropped_records: usize = 0;
+    let now = Instant::now();
 
     match std::fs::read_to_string(files_path) {
         Ok(text) => match serde_json::from_str::<Value>(&text) {
             Ok(data) => {
-                if data.is_array() {
+                bytes_read = text.len() as u64;
+                let mut keys = BTreeSet::new();
+                json_keys(&data, &mut keys);
+                process_runtime.with_profile_fields(keys.iter().map(String::as_str));
+                json_fields_matched(&data, &process_runtime.fields, &mut fields_matched);
+                fields_matched
+                    .sort_by_key(|field| process_runtime.fields.iter().position(|f| f == field));
+                // the items of an array are the records, otherwise the whole document
+                let record = if data.is_array() {
                     total_records = data.as_array().unwrap().len();
+                    None
                 } else {
                     total_records = 1;
-                }
+                    Some(0)
+                };
+                // the document is the record, otherwise the items of the array
+                let record_paths = match record {
+                    Some(_) => json_record_paths(&data, &process_runtime),
+                    None => Map::new(),
+                };
+                // the subset filters the records on their values, only the kept ones are masked
+                let kept = json_subset_records(&data, &process_runtime);
                 let mut json_data = data;
-                let new_json_data = json_med_core(&mut json_data, &process_runtime);
-                write_json(&new_json_data, output_path).unwrap();
+                json_med_core(
+                    &mut json_data,
+                    "$",
+                    record,
+                    &record_paths,
+                    kept.as_deref(),
+                    files_path,
+                    &process_runtime,
+                    &mut record_failed_reason,
+                );
+                if let (Some(kept), Value::Array(items)) = (&kept, &mut json_data) {
+                    let mut i = 0;
+                    items.retain(|_| {
+                        i += 1;
+                        kept[i - 1]
+                    });
+                    dropped_records = total_records - items.len();
+                }
+                json_drop_fields(
+                    &mut json_data,
+                    &process_runtime.subset.drop_fields,
+                    &mut fields_dropped,
+                );
+                failed_records += record_failed_reason.len();
+                match write_json(&json_data, output_path) {
+                    Ok(hash) => {
+                        input_hash = Some(sha256_hex(text.as_bytes()));
+                        output_hash = Some(hash);
+                        bytes_written = std::fs::metadata(output_path)?.len();
+                    }
+                    Err(err) => {
+                        record_failed_reason.push(RecordError::new(
+                            files_path,
+                            RecordErrorKind::Write,
+                            err,
+                        ));
+                        failed_records += 1;
+                    }
+                }
             }
             Err(err) => {
-                let record_error = MedError {
-                    message: Some(format!(
-                        "please check {} {:?} format",
-                        files_path, process_runtime.mode
-                    )),
-                    cause: Some(err.to_string()),
-                    error_type: MedErrorType::CsvError,
-                };
+                let mut record_error = RecordError::new(
+                    files_path,
+                    RecordErrorKind::Parse,
+                    json_record_error(files_path, &process_runtime, err.to_string()),
+                );
+                record_error.line = Some(err.line() as u64);
+                record_error.column = Some(err.column() as u64);
                 record_failed_reason.push(record_error);
                 failed_records += 1;
             }
         },
         Err(err) => {
-            let record_error = MedError {
-                message: Some(format!(
-                    "please check {} {:?} format",
-                    files_path, process_runtime.mode
-                )),
-                cause: Some(err.to_string()),
-                error_type: MedErrorType::CsvError,
-            };
-            record_failed_reason.push(record_error);
+            record_failed_reason.push(RecordError::new(
+                files_path,
+                RecordErrorKind::Read,
+                json_record_error(files_path, &process_runtime, err.to_string()),
+            ));
             failed_records += 1;
         }
     }
 
     tx_metadata
-        .send(Metadata {
-            total_records,
-            failed_records,
-            record_failed_reason,
+        .send(FileMetrics {
+            input_path: files_path.to_string(),
+            output_path: output_path.to_string(),
+            completed: input_hash.is_some(),
+            input_hash,
+            output_hash,
+            bytes_read,
+            bytes_written,
+            duration_ms: now.elapsed().as_millis() as u64,
+            fields_matched,
+            fields_dropped,
+            k_anonymity: None,
+            metadata: Metadata {
+                total_records,
+                failed_records,
+                dropped_records,
+                record_failed_reason,
+            },
         })
         .unwrap();
 
     Ok(())
 }
 
-fn json_med_core(value: &mut Value, process_runtime: &ProcessRuntime) -> Value {
+/// Returns the [MedError] of a failed json record
+fn json_record_error(
+    files_path: &str,
+    process_runtime: &ProcessRuntime,
+    cause: String,
+) -> MedError {
+    MedError {
+        message: Some(format!(
+            "please check {} {:?} format",
+            files_path, process_runtime.mode
+        )),
+        cause: Some(cause),
+        error_type: MedErrorType::CsvError,
+    }
+}
+
+/// Collect the keys of the json value at any depth
+fn json_keys(value: &Value, keys: &mut BTreeSet<String>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| json_keys(item, keys)),
+        Value::Object(obj) => obj.iter().for_each(|(key, val)| {
+            keys.insert(key.to_owned());
+            json_keys(val, keys);
+        }),
+        _ => (),
+    }
+}
+
+/// Collect the configured fields found in the json value
+fn json_fields_matched(value: &Value, fields: &[String], matched: &mut Vec<String>) {
+    match value {
+        Value::Array(arr) => arr
+            .iter()
+            .for_each(|item| json_fields_matched(item, fields, matched)),
+        Value::Object(obj) => obj.iter().for_each(|(key, val)| {
+            if fields.contains(key) && !matched.contains(key) {
+                matched.push(key.to_owned());
+            }
+            json_fields_matched(val, fields, matched);
+        }),
+        _ => {}
+    }
+}
+
+/// Mask, encrypt or decrypt the configured fields of the json value in place, the fields which
+/// failed are emptied and recorded with their json path
+///
+/// # Arguments
+///
+/// * `path` - the json path of the value, `$` for the document
+/// * `record` - the index of the record the value belongs to, None for the top level array
+/// * `record_paths` - the values of the json paths of the conditions in the record
+/// * `kept` - the records of the top level array kept by the subset, the others are not masked
+#[allow(clippy::too_many_arguments)]
+fn json_med_core(
+    value: &mut Value,
+    path: &str,
+    record: Option<u64>,
+    record_paths: &Map<String, Value>,
+    kept: Option<&[bool]>,
+    files_path: &str,
+    process_runtime: &ProcessRuntime,
+    errors: &mut Vec<RecordError>,
+) {
     match value {
         Value::Array(arr) => {
-            // debug!("[arr] {:?}", arr);
-            for item in arr {
+            for (i, item) in arr.iter_mut().enumerate() {
+                // the records dropped by the subset are never masked, tokenized or surrogated
+                if record.is_none() && kept.is_some_and(|kept| !kept[i]) {
+                    continue;
+                }
+                let item_path = format!("{}[{}]", path, i);
+                let item_record = record.or(Some(i as u64));
+                // the items of the top level array are the records
+                let item_paths;
+                let record_paths = match record {
+                    Some(_) => record_paths,
+                    None => {
+                        item_paths = json_record_paths(item, process_runtime);
+                        &item_paths
+                    }
+                };
                 if item.is_array() {
-                    json_med_core(item, process_runtime);
+                    json_med_core(
+                        item,
+                        &item_path,
+                        item_record,
+                        record_paths,
+                        None,
+                        files_path,
+                        process_runtime,
+                        errors,
+                    );
                 }
 
-                if item.is_object() {
-                    // info!("is obj {:?} ", val);
-                    item.as_object_mut()
-                        .unwrap()
-                        .into_iter()
-                        .for_each(|(key, val)| {
-                            //debug!("key: {:?}, val: {:?} ", key, val);
-                            //mask parent lvl
-                            if process_runtime.fields.contains(key) {
-                                if let Value::String(mut masked_val) = val.to_owned() {
-                                    match process_runtime.mode {
-                                        Mode::MASK => {
-                                            masked_val.clear();
-                                            let symbols =
-                                                process_runtime.to_owned().mask_symbols.unwrap();
-                                            masked_val.push_str(&symbols);
-                                        }
-                                        Mode::ENCRYPT => {
-                                            if let Some(cypher) = process_runtime.to_owned().cypher
-                                            {
-                                                if let Some(standard) = process_runtime.standard {
-                                                    let masked = cypher
-                                                        .encrypt(&masked_val, &standard)
-                                                        .unwrap();
-                                                    masked_val.clear();
-                                                    masked_val.push_str(&masked);
-                                                }
-                                            }
-                                        }
-                                        Mode::DECRYPT => {
-                                            if let Some(cypher) = process_runtime.to_owned().cypher
-                                            {
-                                                if let Some(standard) = process_runtime.standard {
-                                                    let masked = cypher
-                                                        .decrypt(&masked_val, &standard)
-                                                        .unwrap();
-                                                    masked_val.clear();
-                                                    masked_val.push_str(&masked);
-                                                }
-                                            }
-                                        }
-                                    }
-                                    *val = Value::String(masked_val);
-                                }
-                            }
-
-                            if val.is_array() {
-                                json_med_core(val, process_runtime);
-                            }
-
-                            if val.is_object() {
-                                json_med_core(val, process_runtime);
-                            }
-                        });
+                if let Value::Object(obj) = item {
+                    let siblings = json_siblings(obj, process_runtime);
+                    for (key, val) in obj.iter_mut() {
+                        //mask parent lvl
+                        if process_runtime.fields.contains(key) {
+                            let field = JsonField {
+                                name: key,
+                                path: format!("{}.{}", item_path, key),
+                                record: item_record,
+                                siblings: &siblings,
+                                record_paths,
+                            };
+                            json_med_field(val, &field, files_path, process_runtime, errors);
+                        }
+
+                        if val.is_array() || val.is_object() {
+                            json_med_core(
+                                val,
+                                &format!("{}.{}", item_path, key),
+                                item_record,
+                                record_paths,
+                                None,
+                                files_path,
+                                process_runtime,
+                                errors,
+                            );
+                        }
+                    }
                 }
             }
         }
         Value::Object(obj) => {
-            for (key, val) in obj {
-                // debug!("key : {:?}, val: {:?}", key, val);
+            let siblings = json_siblings(obj, process_runtime);
+            for (key, val) in obj.iter_mut() {
                 if val.is_array() {
-                    json_med_core(val, process_runtime);
+                    json_med_core(
+                        val,
+                        &format!("{}.{}", path, key),
+                        record,
+                        record_paths,
+                        None,
+                        files_path,
+                        process_runtime,
+                        errors,
+                    );
                 }
                 if process_runtime.fields.contains(key) {
-                    if let Value::String(mut masked_val) = val.to_owned() {
-                        match process_runtime.mode {
-                            Mode::MASK => {
-                                masked_val.clear();
-                                masked_val
-                                    .push_str(&process_runtime.to_owned().mask_symbols.unwrap());
-                            }
-                            Mode::ENCRYPT => {
-                                if let Some(cypher) = process_runtime.to_owned().cypher {
-                                    if let Some(standard) = process_runtime.standard {
-                                        let masked =
-                                            cypher.encrypt(&masked_val, &standard).unwrap();
-                                        masked_val.clear();
-                                        masked_val.push_str(&masked);
-                                    }
-                                }
-                            }
-                            Mode::DECRYPT => {
-                                if let Some(cypher) = process_runtime.to_owned().cypher {
-                                    if let Some(standard) = process_runtime.standard {
-                                        let masked =
-                                            cypher.decrypt(&masked_val, &standard).unwrap();
-                                        masked_val.clear();
-                                        masked_val.push_str(&masked);
-                                    }
-                                }
-                            }
+                    let field = JsonField {
+                        name: key,
+                        path: format!("{}.{}", path, key),
+                        record,
+                        siblings: &siblings,
+                        record_paths,
+                    };
+                    json_med_field(val, &field, files_path, process_runtime, errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A configured field of a json object
+struct JsonField<'a> {
+    name: &'a str,
+    /// the json path of the field
+    path: String,
+    /// the index of the record the field belongs to
+    record: Option<u64>,
+    /// the scalar keys of the object, before it is masked
+    siblings: &'a Map<String, Value>,
+    /// the values of the json paths of the conditions in the record, before it is masked
+    record_paths: &'a Map<String, Value>,
+}
+
+/// The sibling keys of the field, or the json paths from the record root, `$.customer.country`
+impl RecordFields for JsonField<'_> {
+    fn field(&self, name: &str) -> Option<String> {
+        match name.starts_with('$') {
+            true => self.record_paths.field(name),
+            false => self.siblings.field(name),
+        }
+    }
+}
+
+/// A json record, its keys, or the json paths from its root, `$.customer.country`
+struct JsonRecord<'a>(&'a Value);
+
+impl RecordFields for JsonRecord<'_> {
+    fn field(&self, name: &str) -> Option<String> {
+        let value = match name.starts_with('$') {
+            true => self.0.pointer(&json_pointer(name))?,
+            false => self.0.get(name)?,
+        };
+        match value {
+            Value::String(value) => Some(value.to_owned()),
+            Value::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+/// Returns whether the subset keeps each item of the top level array, None if the document is
+/// not an array or the subset keeps every record, the document is always kept
+fn json_subset_records(data: &Value, process_runtime: &ProcessRuntime) -> Option<Vec<bool>> {
+    let subset = &process_runtime.subset;
+    match data {
+        Value::Array(items) if subset.filters_records() => {
+            let mut kept_records = 0;
+            Some(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        let keep = !subset.limit_reached(kept_records)
+                            && subset.keeps(&JsonRecord(item), i as u64, process_runtime.seed);
+                        if keep {
+                            kept_records += 1;
                         }
-                        *val = Value::String(masked_val);
-                    }
+                        keep
+                    })
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+/// Remove the keys of the fields at any depth, collect the fields found
+fn json_drop_fields(value: &mut Value, fields: &[String], dropped: &mut Vec<String>) {
+    if fields.is_empty() {
+        return;
+    }
+    match value {
+        Value::Array(arr) => arr
+            .iter_mut()
+            .for_each(|item| json_drop_fields(item, fields, dropped)),
+        Value::Object(obj) => {
+            for field in fields {
+                if obj.remove(field).is_some() && !dropped.contains(field) {
+                    dropped.push(field.to_owned());
                 }
             }
+            obj.values_mut()
+                .for_each(|val| json_drop_fields(val, fields, dropped));
         }
         _ => {}
     }
-    value.clone()
 }
 
-pub fn write_json(masked_data: &Value, output_file: &str) -> Result<(), MedError> {
-    let mut json_file = File::create(output_file)?;
+/// Returns the values of the json paths of the rule conditions in the record
+fn json_record_paths(record: &Value, process_runtime: &ProcessRuntime) -> Map<String, Value> {
+    process_runtime
+        .rules
+        .values()
+        .filter_map(|rule| rule.when.as_ref())
+        .flat_map(|condition| condition.fields())
+        .filter(|field| field.starts_with('$'))
+        .filter_map(|path| {
+            record
+                .pointer(&json_pointer(path))
+                .map(|value| (path.to_string(), value.clone()))
+        })
+        .collect()
+}
+
+/// Returns the json pointer of the json path, `/customer/0/country` of `$.customer[0].country`
+fn json_pointer(path: &str) -> String {
+    path.trim_start_matches('$')
+        .replace('[', ".")
+        .replace(']', "")
+        .replace('.', "/")
+}
+
+/// Returns the scalar keys of the object, before it is masked, for the rules of its fields
+fn json_siblings(obj: &Map<String, Value>, process_runtime: &ProcessRuntime) -> Map<String, Value> {
+    match process_runtime.rules.is_empty() {
+        true => Map::new(),
+        false => obj
+            .iter()
+            .filter(|(_, val)| !val.is_array() && !val.is_object())
+            .map(|(key, val)| (key.to_owned(), val.clone()))
+            .collect(),
+    }
+}
+
+/// Mask, encrypt, decrypt, tokenize or detokenize a single string field, or mask a number by
+/// the rule of its field
+fn json_med_field(
+    value: &mut Value,
+    field: &JsonField,
+    files_path: &str,
+    process_runtime: &ProcessRuntime,
+    errors: &mut Vec<RecordError>,
+) {
+    // the numbers are only masked by the rule of their field, and kept numbers if possible
+    if let Value::Number(number) = value {
+        if let (Mode::MASK, Some(rule)) =
+            (process_runtime.mode, process_runtime.rules.get(field.name))
+        {
+            if !rule.applies(field) {
+                return;
+            }
+            let context = RuleContext {
+                mask_symbols: process_runtime.mask_symbols.as_deref().unwrap_or_default(),
+                seed: process_runtime.seed,
+                keys: &process_runtime.keys,
+                mask_key: &process_runtime.mask_key,
+                record: field,
+            };
+            *value = match rule.strategy.apply(&number.to_string(), &context) {
+                Ok(masked) => serde_json::from_str::<Number>(&masked)
+                    .map(Value::Number)
+                    .unwrap_or(Value::String(masked)),
+                Err(err) => {
+                    errors.push(json_field_error(
+                        field,
+                        files_path,
+                        process_runtime,
+                        RecordErrorKind::Mask,
+                        err,
+                    ));
+                    Value::Null
+                }
+            };
+        }
+        return;
+    }
+    if let Value::String(item) = value {
+        let (masked, kind) = match process_runtime.mode {
+            Mode::MASK => {
+                let symbols = process_runtime.mask_symbols.as_deref().unwrap_or_default();
+                match process_runtime.rules.get(field.name) {
+                    // the field of the records not matching the rule is kept
+                    Some(rule) if !rule.applies(field) => return,
+                    Some(rule) => {
+                        let context = RuleContext {
+                            mask_symbols: symbols,
+                            seed: process_runtime.seed,
+                            keys: &process_runtime.keys,
+                            mask_key: &process_runtime.mask_key,
+                            record: field,
+                        };
+                        (rule.strategy.apply(item, &context), RecordErrorKind::Mask)
+                    }
+                    None => {
+                        *item = symbols.to_string();
+                        return;
+                    }
+                }
+            }
+            Mode::ENCRYPT | Mode::DECRYPT => {
+                match (&process_runtime.cypher, process_runtime.standard) {
+                    (Some(cypher), Some(standard)) if process_runtime.mode == Mode::ENCRYPT => {
+                        (cypher.encrypt(item, &standard), RecordErrorKind::Encrypt)
+                    }
+                    (Some(cypher), Some(standard)) => {
+                        (cypher.decrypt(item, &standard), RecordErrorKind::Decrypt)
+                    }
+                    _ => return,
+                }
+            }
+            Mode::TOKENIZE | Mode::DETOKENIZE => match &process_runtime.vault {
+                Some(vault) if process_runtime.mode == Mode::TOKENIZE => {
+                    (vault.tokenize(item), RecordErrorKind::Tokenize)
+                }
+                Some(vault) => (vault.detokenize(item), RecordErrorKind::Detokenize),
+                None => return,
+            },
+            // the json files are refused in the k-anonymity mode
+            Mode::KANONYMITY => return,
+        };
+        match masked {
+            Ok(masked) => *item = masked,
+            Err(err) => {
+                item.clear();
+                errors.push(json_field_error(
+                    field,
+                    files_path,
+                    process_runtime,
+                    kind,
+                    err,
+                ));
+            }
+        }
+    }
+}
+
+/// Returns the [RecordError] of a failed json field
+fn json_field_error(
+    field: &JsonField,
+    files_path: &str,
+    process_runtime: &ProcessRuntime,
+    kind: RecordErrorKind,
+    err: MedError,
+) -> RecordError {
+    let mut record_error = RecordError::new(
+        files_path,
+        kind,
+        json_record_error(files_path, process_runtime, err.to_string()),
+    )
+    .with_field(&field.path);
+    record_error.record = field.record;
+    record_error
+}
+
+/// Write the masked data to the output file, returns the sha256 of the written output
+pub fn write_json(masked_data: &Value, output_file: &str) -> Result<String, MedError> {
+    // the output is written to a temp file, and only renamed to the output once completed
+    let output_file = Path::new(output_file);
+    let temp_file = temp_output_path(output_file);
+
+    match write_json_file(masked_data, &temp_file, output_file) {
+        Ok(hash) => Ok(hash),
+        Err(err) => {
+            discard_output(&temp_file);
+            Err(err)
+        }
+    }
+}
+
+fn write_json_file(
+    masked_data: &Value,
+    temp_file: &Path,
+    output_file: &Path,
+) -> Result<String, MedError> {
+    let mut json_file = File::create(temp_file)?;
     let data = serde_json::to_string(masked_data)?;
     json_file.write_all(data.as_bytes())?;
-    json_file.sync_data()?;
-    Ok(())
+    commit_output(json_file, temp_file, output_file)?;
+    Ok(sha256_hex(data.as_bytes()))
 }
 
 #[cfg(test)]
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::debug;
+
+use crate::utils::digest::sha256_file;
+use crate::utils::error::MedError;
+use crate::utils::helpers::{commit_output, discard_output, temp_output_path};
+
+/// The state of an input file when its output was written, the output path is relative to the
+/// output root
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub size: u64,
+    pub modified: u64,
+    pub input_hash: String,
+    pub config_hash: String,
+    pub output_path: String,
+}
+
+/// The manifest of the output directory, used by the incremental mode to skip the unchanged files
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(skip)]
+    path: PathBuf,
+    /// the output root, the output paths are relative to
+    #[serde(skip)]
+    root: PathBuf,
+    /// the seed of the run which wrote the outputs, reused by the next incremental run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    pub files: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    pub const FILE_NAME: &'static str = ".med-manifest.json";
+
+    /// Returns the manifest of the output directory, empty if there is none yet
+    ///
+    /// # Arguments
+    ///
+    /// * `output_dir` - the output root directory
+    pub fn load(output_dir: &Path) -> Result<Self, MedError> {
+        let path = output_dir.join(Self::FILE_NAME);
+        let mut manifest = match path.exists() {
+            true => serde_json::from_str::<Manifest>(&fs::read_to_string(&path)?)?,
+            false => Manifest::default(),
+        };
+        debug!(
+            "manifest {} loaded {} files",
+            path.display(),
+            manifest.files.len()
+        );
+        manifest.path = path;
+        manifest.root = output_dir.to_path_buf();
+        Ok(manifest)
+    }
+
+    /// Write the manifest to the output directory
+    pub fn save(&self) -> Result<(), MedError> {
+        let temp_path = temp_output_path(&self.path);
+        let result = File::create(&temp_path)
+            .map_err(MedError::from)
+            .and_then(|mut file| {
+                file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+                commit_output(file, &temp_path, &self.path)
+            });
+        if result.is_err() {
+            discard_output(&temp_path);
+        }
+        result
+    }
+
+    /// Returns true if the input file is unchanged since its output was written with the same
+    /// configuration, the size and modified time are checked first, then the content hash
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the input path relative to the input root
+    /// * `input_path` - the input path
+    /// * `config_hash` - the hash of the configuration of the current run
+    pub fn is_unchanged(&mut self, key: &str, input_path: &Path, config_hash: &str) -> bool {
+        let entry = match self.files.get_mut(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        if entry.config_hash != config_hash || !self.root.join(&entry.output_path).exists() {
+            return false;
+        }
+        let (size, modified) = match file_stat(input_path) {
+            Ok(stat) => stat,
+            Err(_) => return false,
+        };
+        if entry.size == size && entry.modified == modified {
+            return true;
+        }
+        // the file was touched, compare the content
+        match sha256_file(input_path) {
+            Ok(hash) if hash == entry.input_hash => {
+                entry.size = size;
+                entry.modified = modified;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record the output written for the input file
+    pub fn record(
+        &mut self,
+        key: &str,
+        input_path: &Path,
+        input_hash: &str,
+        config_hash: &str,
+        output_path: &str,
+    ) -> Result<(), MedError> {
+        let (size, modified) = file_stat(input_path)?;
+        let output_path = Path::new(output_path);
+        let output_path = output_path.strip_prefix(&self.root).unwrap_or(output_path);
+        self.files.insert(
+            key.to_string(),
+            ManifestEntry {
+                size,
+                modified,
+                input_hash: input_hash.to_string(),
+                config_hash: config_hash.to_string(),
+                output_path: output_path.display().to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Delete the outputs of the input files which disappeared, returns the number of files pruned
+    ///
+    /// # Arguments
+    ///
+    /// * `seen` - the input paths, relative to the input root, found by the current run
+    pub fn prune(&mut self, seen: &HashSet<String>) -> Result<usize, MedError> {
+        let removed: Vec<String> = self
+            .files
+            .keys()
+            .filter(|key| !seen.contains(*key))
+            .cloned()
+            .collect();
+        for key in removed.iter() {
+            if let Some(entry) = self.files.remove(key) {
+                let output = self.root.join(&entry.output_path);
+                if output.exists() {
+                    fs::remove_file(output)?;
+                }
+                debug!("prune {:?}, input disappeared", entry.output_path);
+            }
+        }
+        Ok(removed.len())
+    }
+}
+
+/// Returns the size and the modified time, in nanoseconds since epoch, of the file
+fn file_stat(path: &Path) -> Result<(u64, u64), MedError> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+    Ok((metadata.len(), modified))
+}
+
+#[cfg(test)]
+#[path = "../tests/manifest_test.rs"]
+mod manifest_test;
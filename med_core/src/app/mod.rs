@@ -1,5 +1,6 @@
 pub mod core;
 pub mod csv;
 pub mod json;
+pub mod manifest;
 pub mod processor;
 pub mod worker;
@@ -1,49 +1,164 @@
-use tracing::debug;
-use walkdir::WalkDir;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use walkdir::{DirEntry, WalkDir};
 
 use crate::app::csv::csv_processor;
 use crate::app::json::json_processor;
+use crate::app::manifest::Manifest;
 use crate::app::worker::Worker;
-use crate::models::enums::{FileType, Mode, Standard};
-use crate::models::metrics::Metrics;
+use crate::audit::checkpoint::Checkpoint;
+use crate::models::enums::{FileType, Mode, RecordErrorKind, Standard};
+use crate::models::metrics::{FileMetrics, Metadata, Metrics, RecordError};
+use crate::strategy::anonymity::KAnonymityConfig;
+use crate::strategy::key::KeyMap;
+use crate::strategy::mask_key::{config_secret, MaskKey};
+use crate::strategy::profile::Profile;
+use crate::strategy::rule::{FieldRule, Strategy};
+use crate::strategy::subset::Subset;
+use crate::utils::cancel::CancellationToken;
 use crate::utils::config::JobConfig;
 use crate::utils::crypto::Cypher;
+use crate::utils::digest::{hmac_sha256_hex, sha256_hex};
 use crate::utils::error::MedErrorType;
-use crate::utils::helpers::{create_output_dir, is_not_hidden};
+use crate::utils::helpers::{
+    absolute_path, create_output_dir, ensure_output_outside_input, is_nested_output, is_not_hidden,
+    output_file_path, relative_path, resolve_output_path,
+};
 use crate::utils::progress_bar::get_progress_bar;
+use crate::vault::token::{vault_path, TokenVault};
 use crate::{models::params::Params, utils::error::MedError};
 
 #[derive(Debug, Clone, Default)]
 pub struct FileProcessor {
     metrics: Metrics,
     runtime_params: Params,
+    pub config_hash: String,
     pub process_runtime: ProcessRuntime,
+    pub checkpoint: Option<Checkpoint>,
+    /// the token vault path of the job config
+    vault_conf: Option<String>,
+    /// the key map path of the job config
+    key_map_conf: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct ProcessRuntime {
     pub fields: Vec<String>,
     pub mask_symbols: Option<String>,
+    /// the masking rule of the fields in the mask mode, by field name
+    pub rules: BTreeMap<String, FieldRule>,
+    /// the seed of the strategies, the same within the run
+    pub seed: u64,
+    /// the records and fields kept in the output
+    pub subset: Subset,
+    /// the quasi identifiers of the k-anonymity mode
+    pub k_anonymity: Option<KAnonymityConfig>,
     pub cypher: Option<Cypher>,
     pub standard: Option<Standard>,
     pub mode: Mode,
+    /// the size, in bytes, of the record aligned chunks of the large csv files
+    pub chunk_size: u64,
+    /// csv files larger than the threshold, in bytes, are processed in parallel chunks, 0 disable
+    pub chunk_threshold: u64,
+    pub cancel: CancellationToken,
+    pub vault: Option<TokenVault>,
+    /// the surrogates of the key fields, shared by the workers
+    pub keys: KeyMap,
+    /// the secret key of the fakes and the surrogates, of `--key` in the mask mode
+    pub mask_key: MaskKey,
+    /// the built-in profile of the fields found by their name
+    pub profile: Option<Profile>,
 }
 
 impl FileProcessor {
-    pub async fn new(runtime_params: Params, job_conf: JobConfig) -> Self {
+    pub async fn new(mut runtime_params: Params, job_conf: JobConfig) -> Self {
         let mode = runtime_params.mode;
+        let chunk_size = runtime_params.chunk_size * 1024 * 1024;
+        let chunk_threshold = runtime_params.chunk_threshold * 1024 * 1024;
+        let seed = runtime_params
+            .seed
+            .or(job_conf.seed)
+            .unwrap_or_else(rand::random);
+        runtime_params.seed = Some(seed);
+        let profile = runtime_params.profile.or(job_conf.profile);
+        runtime_params.profile = profile;
+        let mask_key = match (mode, &runtime_params.key) {
+            (Mode::MASK, Some(key)) => MaskKey::new(key.as_bytes()),
+            _ => MaskKey::default(),
+        };
+        let config_hash = Self::hash_config(&runtime_params, &job_conf);
+        let vault_conf = job_conf.vault.clone();
+        let key_map_conf = job_conf.key_map.clone();
         FileProcessor {
             metrics: Metrics::default(),
             runtime_params,
+            config_hash,
             process_runtime: ProcessRuntime {
-                fields: job_conf.fields,
+                fields: job_conf.masked_fields(),
                 mask_symbols: Some(job_conf.mask_symbols),
+                rules: job_conf.rules,
+                seed,
+                subset: job_conf.subset,
+                k_anonymity: job_conf.k_anonymity,
                 cypher: None,
                 standard: None,
                 mode,
+                chunk_size,
+                chunk_threshold,
+                cancel: CancellationToken::new(),
+                vault: None,
+                keys: KeyMap::default(),
+                mask_key,
+                profile,
             },
+            checkpoint: None,
+            vault_conf,
+            key_map_conf,
         }
     }
+
+    /// Returns the hash of everything which changes the output of a file
+    ///
+    /// The key is in the hash by its HMAC keyed with the local secret of the config hash, the
+    /// hash written to the manifest and the audit is not an oracle to guess the key, without a
+    /// local secret the key is left out of the hash.
+    pub fn hash_config(runtime_params: &Params, job_conf: &JobConfig) -> String {
+        let key_hash = match runtime_params.mode {
+            Mode::ENCRYPT | Mode::DECRYPT | Mode::TOKENIZE | Mode::DETOKENIZE | Mode::MASK => {
+                match runtime_params.key.as_deref() {
+                    Some(key) if !key.is_empty() => match config_secret() {
+                        Some(secret) => Some(hmac_sha256_hex(secret.as_bytes(), key.as_bytes())),
+                        None => {
+                            warn!("no local secret for the config hash, the key is left out");
+                            None
+                        }
+                    },
+                    _ => None,
+                }
+            }
+            Mode::KANONYMITY => None,
+        };
+        // the seed changes the output of the seeded strategies and the sample only
+        let profile = runtime_params.profile.or(job_conf.profile);
+        let seeded =
+            job_conf.subset.sample.is_some() || job_conf.any_strategy(profile, Strategy::is_seeded);
+        let seed = match seeded {
+            true => runtime_params.seed,
+            false => None,
+        };
+        let conf = json!({
+            "job_conf": job_conf,
+            "file_type": runtime_params.file_type,
+            "mode": runtime_params.mode,
+            "standard": runtime_params.standard,
+            "key": key_hash,
+            "seed": seed,
+            "profile": profile,
+        });
+        sha256_hex(conf.to_string().as_bytes())
+    }
     pub async fn run(&mut self) -> Result<Metrics, MedError> {
         match self.runtime_params.mode {
             Mode::ENCRYPT | Mode::DECRYPT => match &self.runtime_params.key {
@@ -61,7 +176,51 @@ impl FileProcessor {
                     })
                 }
             },
-            Mode::MASK => (),
+            Mode::TOKENIZE | Mode::DETOKENIZE => match &self.runtime_params.key {
+                Some(key) => {
+                    let path = vault_path(
+                        self.runtime_params.vault_path.as_deref(),
+                        self.vault_conf.as_deref(),
+                    )?;
+                    self.process_runtime.vault = Some(TokenVault::open(&path, key).await?);
+                }
+                None => {
+                    return Err(MedError {
+                        message: Some(
+                            "Missing key for Tokenization and Detokenization input!".to_string(),
+                        ),
+                        cause: Some("missing -k or --key".to_string()),
+                        error_type: MedErrorType::ConfigError,
+                    })
+                }
+            },
+            Mode::MASK => {
+                // the surrogates are kept between runs in the key map file
+                if let Some(path) = self
+                    .runtime_params
+                    .key_map_path
+                    .as_deref()
+                    .or(self.key_map_conf.as_deref())
+                {
+                    self.process_runtime.keys = KeyMap::load(Path::new(path))?;
+                }
+            }
+            Mode::KANONYMITY => {
+                if self.process_runtime.k_anonymity.is_none() {
+                    return Err(MedError {
+                        message: Some("Missing k_anonymity in the job config!".to_string()),
+                        cause: Some("set the k_anonymity quasi_identifiers and k".to_string()),
+                        error_type: MedErrorType::ConfigError,
+                    });
+                }
+                if self.runtime_params.file_type != FileType::CSV {
+                    return Err(MedError {
+                        message: Some("The k-anonymity mode only supports csv files!".to_string()),
+                        cause: Some("use -t csv or --type csv".to_string()),
+                        error_type: MedErrorType::ConfigError,
+                    });
+                }
+            }
         }
         self.metrics = self.load().await?;
 
@@ -75,29 +234,114 @@ impl FileProcessor {
         // inital worker based on the input
         let new_worker = Worker::new(self.runtime_params.worker).await?;
 
-        // init the files number as 0
-        let mut files_number: u64 = 0;
+        // the input path relative to the input root of the loaded files, used for the checkpoint
+        let mut keys: HashMap<String, String> = HashMap::new();
 
-        // create outpu dir
-        create_output_dir(
-            &self.runtime_params.output_path,
+        // refuse to write the output inside the input tree unless explicitly allowed
+        if !self.runtime_params.allow_nested_output {
+            ensure_output_outside_input(
+                &self.runtime_params.file_path,
+                &self.runtime_params.output_path,
+            )?;
+        }
+
+        // when the output is nested, the output tree must not be picked up as input
+        let nested_output = match is_nested_output(
             &self.runtime_params.file_path,
-        )
-        .await?;
+            &self.runtime_params.output_path,
+        )? {
+            true => Some(absolute_path(Path::new(&self.runtime_params.output_path))?),
+            false => None,
+        };
+
+        // the manifest of the output directory for the incremental mode
+        let mut manifest = match self.runtime_params.incremental {
+            true => Some(Manifest::load(Path::new(&self.runtime_params.output_path))?),
+            false => None,
+        };
+
+        // the input path relative to the input root of all the files found
+        let mut seen: HashSet<String> = HashSet::new();
+
+        // the length grows as the files are queued
+        let bar = get_progress_bar(
+            0,
+            &format!("processing {:?} files", self.runtime_params.file_type),
+        );
+
+        // the entries which could not be read, their files are not seen
+        let mut walk_errors = 0;
 
         // loop over the files path
         for entry in WalkDir::new(&self.runtime_params.file_path)
             .follow_links(true)
             .into_iter()
-            .filter_entry(is_not_hidden)
-            .filter_map(|e| e.ok())
+            .filter_entry(|e| is_not_hidden(e) && !is_output_entry(e, &nested_output))
+            .filter_map(|e| match e {
+                Ok(entry) => Some(entry),
+                Err(err) => {
+                    warn!("skip {:?}, {}", err.path().unwrap_or(Path::new("")), err);
+                    walk_errors += 1;
+                    None
+                }
+            })
             .filter(|e| !e.path().is_dir())
         {
+            // stop queuing the remaining files once cancelled
+            if self.process_runtime.cancel.is_cancelled() {
+                self.metrics.cancelled = true;
+                break;
+            }
+
+            // skip the files completed by the resumed run
+            let key = relative_path(Path::new(&self.runtime_params.file_path), entry.path());
+            seen.insert(key.clone());
+            let output_file = output_file_path(
+                Path::new(&self.runtime_params.file_path),
+                entry.path(),
+                Path::new(&self.runtime_params.output_path),
+            );
+            if let Some(checkpoint) = &self.checkpoint {
+                if let Some(completed) = checkpoint.completed(&key, entry.path(), &output_file) {
+                    debug!("skip {:?}, completed by the resumed run", key);
+                    checkpoint.complete(&completed).await?;
+                    self.metrics.skipped_files += 1;
+                    continue;
+                }
+            }
+
+            // skip the files unchanged since the previous incremental run
+            if let Some(manifest) = manifest.as_mut() {
+                if manifest.is_unchanged(&key, entry.path(), &self.config_hash) {
+                    debug!("skip {:?}, unchanged since the previous run", key);
+                    self.metrics.skipped_files += 1;
+                    continue;
+                }
+            }
+            keys.insert(entry.path().display().to_string(), key);
+
+            let output_file =
+                match resolve_output_path(output_file, self.runtime_params.output_policy) {
+                    Some(output_file) => output_file,
+                    None => {
+                        debug!(
+                            "skip {:?}, output already exist",
+                            entry.path().display().to_string()
+                        );
+                        self.metrics.skipped_files += 1;
+                        continue;
+                    }
+                };
+
+            // create output dir
+            create_output_dir(&output_file)?;
+
             // prepare the worker processing
             let tx_metadata = tx_metadata.clone();
             let files_path = entry.path().display().to_string();
-            let output_dir = format!("{}/{}", self.runtime_params.output_path, files_path);
+            let output_dir = output_file.display().to_string();
             let process_runtime = self.process_runtime.clone();
+            let file_type = self.runtime_params.file_type.clone();
 
             // debug ensure the files have been process
             debug!(
@@ -107,46 +351,163 @@ impl FileProcessor {
             );
 
             // increase file number
-            files_number += 1;
-
-            match self.runtime_params.file_type {
-                FileType::CSV => {
-                    // worker execution
-                    new_worker.pool.execute(move || {
-                        csv_processor(tx_metadata, &files_path, &output_dir, process_runtime)
-                            .unwrap();
-                    });
-                }
-                FileType::JSON => {
-                    new_worker.pool.execute(move || {
-                        json_processor(tx_metadata, &files_path, &output_dir, process_runtime)
-                            .unwrap();
-                    });
-                }
+            bar.inc_length(1);
+
+            // worker execution, waits while the queue is full
+            new_worker
+                .execute(move || {
+                    // the queued files are skipped once cancelled
+                    if process_runtime.cancel.is_cancelled() {
+                        return;
+                    }
+                    let result = match file_type {
+                        FileType::CSV => csv_processor(
+                            tx_metadata.clone(),
+                            &files_path,
+                            &output_dir,
+                            process_runtime,
+                        ),
+                        FileType::JSON => json_processor(
+                            tx_metadata.clone(),
+                            &files_path,
+                            &output_dir,
+                            process_runtime,
+                        ),
+                    };
+                    // the cancelled file is rolled back, it is not reported
+                    match result {
+                        Err(err) if err.error_type != MedErrorType::CancelledError => {
+                            // the output failed to be written, or the input to be read
+                            let kind = match err.error_type {
+                                MedErrorType::IoError => RecordErrorKind::Write,
+                                _ => RecordErrorKind::Read,
+                            };
+                            let record_error = RecordError::new(&files_path, kind, err);
+                            let _ = tx_metadata.send(FileMetrics {
+                                input_path: files_path,
+                                output_path: output_dir,
+                                metadata: Metadata {
+                                    total_records: 0,
+                                    failed_records: 1,
+                                    record_failed_reason: vec![record_error],
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            });
+                        }
+                        _ => (),
+                    }
+                })
+                .await;
+
+            // collect the files already completed while queuing
+            while let Ok(item) = rx_metadata.try_recv() {
+                bar.inc(1);
+                self.collect(item, &keys, &mut manifest).await?;
             }
         }
 
         // drop the channel once it done.
         drop(tx_metadata);
 
-        let bar = get_progress_bar(
-            files_number,
-            &format!("processing {:?} files", self.runtime_params.file_type),
-        );
-        rx_metadata.iter().for_each(|item| {
+        while let Ok(item) = rx_metadata.recv_async().await {
             bar.inc(1);
-            self.metrics.total_files = files_number as usize;
-            self.metrics.metadata.total_records += item.total_records;
-            self.metrics.metadata.failed_records += item.failed_records;
-            self.metrics
-                .metadata
-                .record_failed_reason
-                .extend(item.record_failed_reason);
-        });
+            self.collect(item, &keys, &mut manifest).await?;
+        }
         bar.finish_and_clear();
+        if self.process_runtime.cancel.is_cancelled() {
+            self.metrics.cancelled = true;
+        }
+
+        if let Some(manifest) = manifest.as_mut() {
+            // the input tree was not fully walked when cancelled, or when an entry failed
+            match (
+                self.runtime_params.prune,
+                self.metrics.cancelled,
+                walk_errors,
+            ) {
+                (true, false, 0) => self.metrics.pruned_files = manifest.prune(&seen)?,
+                (true, _, errors) if errors > 0 => warn!(
+                    "prune skipped, {} entries of the input could not be read",
+                    errors
+                ),
+                _ => (),
+            }
+            manifest.seed = Some(self.process_runtime.seed);
+            manifest.save()?;
+        }
+
+        // the surrogates written to the outputs, even when cancelled
+        self.process_runtime.keys.save()?;
 
         debug!("metrics {:?}", self.metrics);
 
         Ok(self.metrics.clone())
     }
+
+    /// Privite function aggregate the processed file in the metrics, checkpoint and manifest
+    async fn collect(
+        &mut self,
+        item: FileMetrics,
+        keys: &HashMap<String, String>,
+        manifest: &mut Option<Manifest>,
+    ) -> Result<(), MedError> {
+        if let (Some(checkpoint), Some(key)) = (&self.checkpoint, keys.get(&item.input_path)) {
+            checkpoint.complete_metrics(key, &item).await?;
+        }
+        if let (Some(manifest), Some(key), Some(input_hash)) = (
+            manifest.as_mut(),
+            keys.get(&item.input_path),
+            &item.input_hash,
+        ) {
+            if item.completed {
+                manifest.record(
+                    key,
+                    Path::new(&item.input_path),
+                    input_hash,
+                    &self.config_hash,
+                    &item.output_path,
+                )?;
+            }
+        }
+        self.metrics.total_files += 1;
+        self.metrics.metadata.total_records += item.metadata.total_records;
+        self.metrics.metadata.failed_records += item.metadata.failed_records;
+        self.metrics.metadata.dropped_records += item.metadata.dropped_records;
+        self.metrics
+            .metadata
+            .record_failed_reason
+            .extend(item.metadata.record_failed_reason.clone());
+        self.metrics.files.push(item);
+        Ok(())
+    }
+}
+
+impl ProcessRuntime {
+    /// Adds the fields of the file matching the profile, and their rule unless the job config
+    /// has one, the fields and rules of the job config take precedence
+    pub fn with_profile_fields<'a>(&mut self, names: impl Iterator<Item = &'a str>) {
+        let Some(profile) = self.profile else {
+            return;
+        };
+        for name in names {
+            if let Some(rule) = profile.rule(name) {
+                if !self.fields.iter().any(|field| field == name) {
+                    self.fields.push(name.to_owned());
+                }
+                self.rules.entry(name.to_owned()).or_insert(rule);
+            }
+        }
+    }
+}
+
+/// Returns true if the directory entry is the nested output directory
+fn is_output_entry(entry: &DirEntry, nested_output: &Option<PathBuf>) -> bool {
+    match nested_output {
+        Some(output) if entry.file_type().is_dir() => match absolute_path(entry.path()) {
+            Ok(path) => path.starts_with(output),
+            Err(_) => false,
+        },
+        _ => false,
+    }
 }
@@ -1,15 +1,28 @@
 use crate::utils::error::MedError;
-use std::sync::Once;
-use threadpool::ThreadPool;
+use tracing::error;
+
+/// The number of jobs queued per worker before the producer has to wait
+const QUEUE_PER_WORKER: usize = 2;
 
 #[derive(Debug)]
 pub struct Worker {
     pub cpu_num: u16,
-    pub pool: ThreadPool,
+    pub pool: rayon::ThreadPool,
+    queue: (flume::Sender<()>, flume::Receiver<()>),
+}
+
+/// Release the queue slot of the job once it is done, even if it panicked
+struct QueueSlot(flume::Receiver<()>);
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        let _ = self.0.try_recv();
+    }
 }
 
 impl Worker {
-    /// Returns a Worker instant
+    /// Returns a Worker instant, owning its own thread pool so several Worker can run in the
+    /// same process with a different size
     ///
     /// # Arguments
     ///
@@ -24,21 +37,54 @@ impl Worker {
     /// #[tokio::main]
     /// async fn main() -> Result<(), MedError> {
     ///     let worker = Worker::new(4).await?;
+    ///     assert_eq!(worker.pool.current_num_threads(), 4);
     ///     Ok(())
     /// }
     ///
     /// ```
     pub async fn new(cpu_num: u16) -> Result<Self, MedError> {
-        let pool = ThreadPool::new(cpu_num as usize);
-        static START: Once = Once::new();
-
-        START.call_once(|| {
-            rayon::ThreadPoolBuilder::new()
-                .num_threads(cpu_num as usize)
-                .build_global()
-                .unwrap();
-        });
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(cpu_num as usize)
+            .thread_name(|i| format!("med-worker-{}", i))
+            .panic_handler(|err| {
+                let reason = err
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| err.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                error!("worker panicked: {}", reason)
+            })
+            .build()?;
+
+        // bounded queue, the producer waits for a free slot instead of queuing every file
+        let queue = flume::bounded(pool.current_num_threads() * QUEUE_PER_WORKER);
 
-        Ok(Worker { cpu_num, pool })
+        Ok(Worker {
+            cpu_num,
+            pool,
+            queue,
+        })
+    }
+
+    /// Queue the job on the pool, waits while the queue is full
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - the closure to execute on the pool
+    pub async fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        // the receiver is owned by the worker, the send can not fail
+        let _ = self.queue.0.send_async(()).await;
+        let slot = QueueSlot(self.queue.1.clone());
+        self.pool.spawn(move || {
+            let _slot = slot;
+            job();
+        });
     }
 }
+
+#[cfg(test)]
+#[path = "../tests/worker_test.rs"]
+mod worker_test;
@@ -1,4 +1,9 @@
-use crate::{audit::db::Database, models::metrics::Metrics, utils::error::MedError};
+use crate::{
+    audit::db::{AuditLocation, Database},
+    audit::sink::{emit_all, AuditEvent, AuditSink},
+    models::metrics::Metrics,
+    utils::error::MedError,
+};
 
 #[derive(Debug, Default, Clone)]
 pub struct Summary {
@@ -9,23 +14,57 @@ pub struct Summary {
     pub process_failure_reason: Option<String>,
     pub successed: bool,
     pub elapsed_time: String,
+    pub cancelled: bool,
 }
 
 pub struct Audit {
     pub db: Database,
+    pub id: Option<i64>,
     pub summary: Summary,
+    /// the sinks the completed run is emitted to, in addition to the database
+    pub sinks: Vec<Box<dyn AuditSink>>,
 }
 
 impl Audit {
-    pub async fn new() -> Result<Self, MedError> {
-        let db = Database::new().await?;
+    pub async fn new(location: &AuditLocation) -> Result<Self, MedError> {
+        let db = Database::new(location).await?;
         let summary = Summary::default();
-        Ok(Audit { db, summary })
+        Ok(Audit {
+            db,
+            id: None,
+            summary,
+            sinks: Vec::new(),
+        })
     }
 
+    /// Returns the Audit emitting the completed run to the sinks
+    pub fn with_sinks(mut self, sinks: Vec<Box<dyn AuditSink>>) -> Self {
+        self.sinks = sinks;
+        self
+    }
+
+    /// Insert the audit record at the start of the run, so the files completed can be
+    /// checkpointed against the audit id, returns the audit id
     #[cfg(not(tarpaulin_include))]
-    pub async fn insert(&mut self) -> Result<i64, MedError> {
+    pub async fn begin(&mut self) -> Result<i64, MedError> {
         let id = self.db.insert(&self.summary).await?;
+        self.id = Some(id);
+        Ok(id)
+    }
+
+    /// Record the completed run, and emit it to the sinks, returns the audit id
+    #[cfg(not(tarpaulin_include))]
+    pub async fn insert(&mut self) -> Result<i64, MedError> {
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                let id = self.db.insert(&self.summary).await?;
+                self.id = Some(id);
+                id
+            }
+        };
+        self.db.update(id, &self.summary).await?;
+        emit_all(&self.sinks, &AuditEvent::new(id, &self.summary)).await;
         Ok(id)
     }
 }
@@ -0,0 +1,293 @@
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{sqlite::SqliteRow, Row, SqliteConnection};
+
+use crate::audit::db::Database;
+use crate::audit::lineage::lineage_digest;
+use crate::utils::digest::{hmac_sha256_hex, sha256_hex};
+use crate::utils::error::MedError;
+
+/// The environment variable of the key of the HMAC keyed hash chain
+pub const AUDIT_KEY_ENV: &str = "MED_AUDIT_KEY";
+
+/// The previous hash of the first sealed record
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const SHA256: &str = "sha256";
+const HMAC_SHA256: &str = "hmac-sha256";
+
+const CHAIN_COLUMNS: &str =
+    "SELECT id, user, hostname, total_files, total_records, failed_records, \
+    record_failed_reason, runtime_conf, process_failure_reason, successed, \
+    strftime('%Y-%m-%d %H:%M:%S', created_at) AS created_at, elapsed_time, cancelled, chain_index, \
+    prev_hash, chain_algo, row_hash FROM audit";
+
+/// Why a sealed record failed the verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainIssueKind {
+    /// the content of the record, or its file lineage, does not match its hash
+    Modified,
+    /// the records before it in the chain were deleted, not by the retention policy
+    Missing,
+    /// the record does not follow the previous record of the chain
+    Reordered,
+    /// the anchor of the purged records does not match its hash, the id is 0
+    Anchor,
+}
+
+/// A sealed record which failed the verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainIssue {
+    pub id: i64,
+    pub chain_index: i64,
+    pub kind: ChainIssueKind,
+}
+
+/// The outcome of the verification of the audit hash chain
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ChainReport {
+    /// the records sealed in the chain
+    pub sealed: usize,
+    /// the records not sealed yet, the runs in progress or interrupted
+    pub unsealed: usize,
+    /// the HMAC keyed records whose content could not be checked without the key
+    pub unverified: usize,
+    /// the index of the last record purged by the retention policy, the chain is verified from it
+    pub anchor_index: Option<i64>,
+    /// the index and hash of the last sealed record, to be kept aside to detect the deletion of
+    /// the latest records
+    pub head_index: Option<i64>,
+    pub head_hash: Option<String>,
+    pub issues: Vec<ChainIssue>,
+}
+
+impl ChainReport {
+    /// Returns true if no sealed record was modified, deleted, or reordered
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Privite function returns the hash of the record content and the digest of its lineage,
+/// including its link to the previous record
+fn row_hash(row: &SqliteRow, lineage: &str, key: Option<&str>) -> String {
+    let content = json!([
+        row.get::<i64, &str>("id"),
+        row.get::<String, &str>("user"),
+        row.get::<String, &str>("hostname"),
+        row.get::<i64, &str>("total_files"),
+        row.get::<i64, &str>("total_records"),
+        row.get::<i64, &str>("failed_records"),
+        row.get::<Option<String>, &str>("record_failed_reason"),
+        row.get::<String, &str>("runtime_conf"),
+        row.get::<Option<String>, &str>("process_failure_reason"),
+        row.get::<bool, &str>("successed"),
+        row.get::<String, &str>("created_at"),
+        row.get::<Option<String>, &str>("elapsed_time"),
+        row.get::<bool, &str>("cancelled"),
+        row.get::<Option<i64>, &str>("chain_index"),
+        row.get::<Option<String>, &str>("prev_hash"),
+        row.get::<Option<String>, &str>("chain_algo"),
+        lineage,
+    ])
+    .to_string();
+    match key {
+        Some(key) => hmac_sha256_hex(key.as_bytes(), content.as_bytes()),
+        None => sha256_hex(content.as_bytes()),
+    }
+}
+
+/// Returns the hash of the anchor, keyed as the chain, the anchor can not be moved over records
+/// deleted outside of the retention policy without the key
+pub(crate) fn anchor_hash(
+    chain_index: i64,
+    row_hash: &str,
+    purged_records: i64,
+    key: Option<&str>,
+) -> String {
+    let content = json!([chain_index, row_hash, purged_records]).to_string();
+    match key {
+        Some(key) => hmac_sha256_hex(key.as_bytes(), content.as_bytes()),
+        None => sha256_hex(content.as_bytes()),
+    }
+}
+
+/// Returns the algorithm of the chain with the key
+pub(crate) fn chain_algo(key: Option<&str>) -> &'static str {
+    match key {
+        Some(_) => HMAC_SHA256,
+        None => SHA256,
+    }
+}
+
+/// Seal the audit record at the end of the chain, the connection must hold the write lock so
+/// the chain is not appended concurrently
+pub(crate) async fn seal(
+    conn: &mut SqliteConnection,
+    id: i64,
+    key: Option<&str>,
+) -> Result<(), MedError> {
+    let head = sqlx::query(
+        "SELECT chain_index, row_hash FROM audit WHERE chain_index IS NOT NULL ORDER BY chain_index DESC LIMIT 1;",
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    // the chain continues from the anchor once every sealed record was purged
+    let head = match head {
+        Some(head) => Some(head),
+        None => {
+            sqlx::query("SELECT chain_index, row_hash FROM audit_chain_anchor WHERE id = 1;")
+                .fetch_optional(&mut *conn)
+                .await?
+        }
+    };
+    let (chain_index, prev_hash) = match head {
+        Some(head) => (
+            head.get::<i64, &str>("chain_index") + 1,
+            head.get::<String, &str>("row_hash"),
+        ),
+        None => (1, GENESIS_HASH.to_string()),
+    };
+    let chain_algo = chain_algo(key);
+
+    sqlx::query(
+        "UPDATE audit SET chain_index = ?1, prev_hash = ?2, chain_algo = ?3 WHERE id = ?4;",
+    )
+    .bind(chain_index)
+    .bind(&prev_hash)
+    .bind(chain_algo)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    let row = sqlx::query(&format!("{} WHERE id = ?1", CHAIN_COLUMNS))
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let lineage = lineage_digest(&mut *conn, id).await?;
+    sqlx::query("UPDATE audit SET row_hash = ?1 WHERE id = ?2;")
+        .bind(row_hash(&row, &lineage, key))
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+impl Database {
+    /// Returns the [ChainReport] of the audit hash chain, checked with the key of the database
+    /// for the HMAC keyed records
+    pub async fn verify(&self) -> Result<ChainReport, MedError> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE chain_index IS NOT NULL ORDER BY chain_index",
+            CHAIN_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        let unsealed =
+            sqlx::query("SELECT count(*) AS count FROM audit WHERE chain_index IS NULL;")
+                .fetch_one(&self.pool)
+                .await?;
+
+        let mut report = ChainReport {
+            sealed: rows.len(),
+            unsealed: unsealed.get::<i64, &str>("count") as usize,
+            ..Default::default()
+        };
+        let anchor = sqlx::query(
+            "SELECT chain_index, row_hash, purged_records, chain_algo, anchor_hash FROM audit_chain_anchor WHERE id = 1;",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(anchor) = &anchor {
+            let chain_index: i64 = anchor.get("chain_index");
+            let computed = |key| {
+                anchor_hash(
+                    chain_index,
+                    &anchor.get::<String, &str>("row_hash"),
+                    anchor.get("purged_records"),
+                    key,
+                )
+            };
+            let computed = match (
+                anchor.get::<Option<String>, &str>("chain_algo").as_deref(),
+                self.chain_key.as_deref(),
+            ) {
+                (Some(HMAC_SHA256), Some(key)) => Some(computed(Some(key))),
+                (Some(HMAC_SHA256), None) => None,
+                _ => Some(computed(None)),
+            };
+            match computed {
+                Some(computed)
+                    if Some(&computed)
+                        != anchor.get::<Option<String>, &str>("anchor_hash").as_ref() =>
+                {
+                    report.issues.push(ChainIssue {
+                        id: 0,
+                        chain_index,
+                        kind: ChainIssueKind::Anchor,
+                    })
+                }
+                Some(_) => (),
+                None => report.unverified += 1,
+            }
+        }
+        let (mut expected_index, mut expected_prev) = match &anchor {
+            Some(anchor) => (
+                anchor.get::<i64, &str>("chain_index") + 1,
+                anchor.get::<String, &str>("row_hash"),
+            ),
+            None => (1, GENESIS_HASH.to_string()),
+        };
+        report.anchor_index = anchor.map(|anchor| anchor.get::<i64, &str>("chain_index"));
+        for row in rows.iter() {
+            let id: i64 = row.get("id");
+            let chain_index: i64 = row.get("chain_index");
+            let prev_hash: Option<String> = row.get("prev_hash");
+            let stored_hash: Option<String> = row.get("row_hash");
+            let chain_algo: Option<String> = row.get("chain_algo");
+
+            if chain_index != expected_index {
+                report.issues.push(ChainIssue {
+                    id,
+                    chain_index,
+                    kind: ChainIssueKind::Missing,
+                });
+            } else if prev_hash.as_deref() != Some(expected_prev.as_str()) {
+                report.issues.push(ChainIssue {
+                    id,
+                    chain_index,
+                    kind: ChainIssueKind::Reordered,
+                });
+            }
+
+            let lineage = lineage_digest(&self.pool, id).await?;
+            let computed = match (chain_algo.as_deref(), self.chain_key.as_deref()) {
+                (Some(HMAC_SHA256), Some(key)) => Some(row_hash(row, &lineage, Some(key))),
+                (Some(HMAC_SHA256), None) => None,
+                _ => Some(row_hash(row, &lineage, None)),
+            };
+            match computed {
+                Some(computed) if Some(&computed) != stored_hash.as_ref() => {
+                    report.issues.push(ChainIssue {
+                        id,
+                        chain_index,
+                        kind: ChainIssueKind::Modified,
+                    })
+                }
+                Some(_) => (),
+                None => report.unverified += 1,
+            }
+
+            expected_index = chain_index + 1;
+            expected_prev = stored_hash.clone().unwrap_or_default();
+            report.head_index = Some(chain_index);
+            report.head_hash = stored_hash;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/chain_test.rs"]
+mod chain_test;
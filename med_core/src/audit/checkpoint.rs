@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use tracing::debug;
+
+use crate::audit::db::{AuditFile, Database};
+use crate::models::metrics::FileMetrics;
+use crate::utils::digest::sha256_file;
+use crate::utils::error::MedError;
+use crate::utils::helpers::absolute_path;
+
+/// Per file completion state of an audit run, used to resume a previous run
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    db: Database,
+    audit_id: i64,
+    config_hash: String,
+    completed: HashMap<String, AuditFile>,
+}
+
+impl Checkpoint {
+    /// Returns a Checkpoint recording the completed files against the `audit_id`
+    ///
+    /// # Arguments
+    ///
+    /// * `db` [Database] - the audit database
+    /// * `audit_id` [i64] - the audit id of the current run
+    /// * `config_hash` - the hash of the configuration of the current run
+    /// * `resume` - the audit id of the run to resume, if any
+    pub async fn new(
+        db: Database,
+        audit_id: i64,
+        config_hash: String,
+        resume: Option<i64>,
+    ) -> Result<Self, MedError> {
+        let completed = match resume {
+            Some(resume_id) => db
+                .completed_files(resume_id, &config_hash)
+                .await?
+                .into_iter()
+                .map(|file| (file.input_path.clone(), file))
+                .collect(),
+            None => HashMap::new(),
+        };
+        debug!("checkpoint loaded {} completed files", completed.len());
+
+        Ok(Checkpoint {
+            db,
+            audit_id,
+            config_hash,
+            completed,
+        })
+    }
+
+    /// Returns the completed file if the `input_path` was completed with identical content and
+    /// its output still exist, unaltered, in the output directory of the current run
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - the input path relative to the input root
+    /// * `input_path` - the input path
+    /// * `output_file` - the output path of the input in the current run, before the output
+    ///   policy renames it
+    pub fn completed(&self, key: &str, input_path: &Path, output_file: &Path) -> Option<AuditFile> {
+        let file = self.completed.get(key)?;
+        let output_path = Path::new(&file.output_path);
+        if !output_path.exists() {
+            return None;
+        }
+        // the output written by the resumed run to another output directory is not this run's
+        let output_dir = |path: &Path| {
+            absolute_path(path)
+                .ok()
+                .and_then(|path| path.parent().map(Path::to_path_buf))
+        };
+        match (output_dir(output_path), output_dir(output_file)) {
+            (Some(recorded), Some(current)) if recorded == current => (),
+            _ => return None,
+        }
+        if let Some(output_hash) = &file.output_hash {
+            match sha256_file(output_path) {
+                Ok(hash) if &hash == output_hash => (),
+                _ => return None,
+            }
+        }
+        match sha256_file(input_path) {
+            Ok(hash) if hash == file.input_hash => Some(file.clone()),
+            _ => None,
+        }
+    }
+
+    /// Record the file as completed for the current run
+    pub async fn complete(&self, file: &AuditFile) -> Result<(), MedError> {
+        self.db
+            .insert_file(self.audit_id, &self.config_hash, file)
+            .await
+    }
+
+    /// Record the processed file as completed for the current run, if it was written
+    pub async fn complete_metrics(&self, key: &str, file: &FileMetrics) -> Result<(), MedError> {
+        match (&file.input_hash, file.completed) {
+            (Some(input_hash), true) => {
+                self.complete(&AuditFile {
+                    input_path: key.to_string(),
+                    input_hash: input_hash.to_owned(),
+                    input_size: Some(file.bytes_read as i64),
+                    output_path: file.output_path.to_owned(),
+                    output_hash: file.output_hash.to_owned(),
+                    output_size: Some(file.bytes_written as i64),
+                    total_records: Some(file.metadata.total_records as i64),
+                    failed_records: Some(file.metadata.failed_records as i64),
+                })
+                .await
+            }
+            _ => Ok(()),
+        }
+    }
+}
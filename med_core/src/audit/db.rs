@@ -1,19 +1,123 @@
 use std::{path::PathBuf, str::FromStr, time::Duration};
 
 use colored::Colorize;
+use serde::Serialize;
 use sqlx::{
-    migrate::MigrateDatabase,
-    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    sqlite::{
+        SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous,
+    },
     Pool, Row, Sqlite,
 };
 use tracing::{debug, info};
 
-use crate::utils::error::MedError;
+use crate::audit::app::Summary;
+use crate::audit::chain::{seal, AUDIT_KEY_ENV};
+use crate::audit::migration::migrate;
+use crate::utils::error::{MedError, MedErrorType};
 
-use super::app::Summary;
+/// The environment variable of the audit database location
+pub const AUDIT_DB_ENV: &str = "MED_AUDIT_DB";
 
+/// Where the audit records are stored
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AuditLocation {
+    /// `med.db` in the config directory of the user
+    #[default]
+    ConfigDir,
+    /// a database file, e.g. shared by a team
+    File(PathBuf),
+    /// in memory, the records are lost when the process exits
+    Memory,
+    /// nothing is recorded, the run is only audited in memory
+    Disabled,
+}
+
+impl FromStr for AuditLocation {
+    type Err = String;
+
+    /// `:memory:` for [AuditLocation::Memory], `off` for [AuditLocation::Disabled], otherwise
+    /// the path of the database file
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err("the audit database location is empty".to_string()),
+            ":memory:" | "memory" => Ok(AuditLocation::Memory),
+            "off" | "disabled" | "none" => Ok(AuditLocation::Disabled),
+            path => Ok(AuditLocation::File(PathBuf::from(path))),
+        }
+    }
+}
+
+impl AuditLocation {
+    /// Returns the location from, in order, the cli, the `MED_AUDIT_DB` environment variable,
+    /// the job configuration, or the config directory by default
+    pub fn resolve(cli: Option<&str>, conf: Option<&str>) -> Result<Self, MedError> {
+        let env = std::env::var(AUDIT_DB_ENV).ok();
+        match cli.or(env.as_deref()).or(conf) {
+            Some(location) => AuditLocation::from_str(location).map_err(|err| MedError {
+                message: Some(err),
+                cause: Some(format!("check --audit-db or {}", AUDIT_DB_ENV)),
+                error_type: MedErrorType::ConfigError,
+            }),
+            None => Ok(AuditLocation::ConfigDir),
+        }
+    }
+
+    /// Returns true if the records outlive the process, e.g. to resume a run
+    pub fn is_persistent(&self) -> bool {
+        matches!(self, AuditLocation::ConfigDir | AuditLocation::File(_))
+    }
+}
+
+#[derive(Clone)]
 pub struct Database {
     pub pool: sqlx::Pool<sqlx::Sqlite>,
+    /// the HMAC key of the hash chain, from `MED_AUDIT_KEY`, plain SHA-256 if not set
+    pub(crate) chain_key: Option<String>,
+    /// the maximum number of failure reasons stored per run, all of them if not set
+    pub max_failure_reasons: Option<usize>,
+}
+
+/// The chain key is a secret, never printed
+impl std::fmt::Debug for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("pool", &self.pool)
+            .field("chain_key", &self.chain_key.as_ref().map(|_| "***"))
+            .field("max_failure_reasons", &self.max_failure_reasons)
+            .finish()
+    }
+}
+
+/// A file completed by an audit run, and the lineage of its output, unknown for the files
+/// recorded by the previous releases
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct AuditFile {
+    pub input_path: String,
+    pub input_hash: String,
+    pub input_size: Option<i64>,
+    pub output_path: String,
+    pub output_hash: Option<String>,
+    pub output_size: Option<i64>,
+    pub total_records: Option<i64>,
+    pub failed_records: Option<i64>,
+}
+
+pub(crate) const AUDIT_FILE_COLUMNS: &str =
+    "SELECT input_path, input_hash, input_size, output_path, output_hash, output_size, \
+    total_records, failed_records FROM audit_file";
+
+/// Returns the [AuditFile] of the row
+pub(crate) fn audit_file(row: &SqliteRow) -> AuditFile {
+    AuditFile {
+        input_path: row.get("input_path"),
+        input_hash: row.get("input_hash"),
+        input_size: row.get("input_size"),
+        output_path: row.get("output_path"),
+        output_hash: row.get("output_hash"),
+        output_size: row.get("output_size"),
+        total_records: row.get("total_records"),
+        failed_records: row.get("failed_records"),
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -31,127 +135,220 @@ pub struct AuditSummary {
 
 #[cfg(not(tarpaulin_include))]
 impl Database {
-    pub async fn new() -> Result<Database, MedError> {
-        let database_url = Self::create_audit_db().await?;
-
-        if !Sqlite::database_exists(database_url.to_str().unwrap())
-            .await
-            .unwrap_or(false)
-        {
-            Sqlite::create_database(database_url.to_str().unwrap()).await?;
+    /// Returns the audit Database at the location, created and migrated if needed
+    pub async fn new(location: &AuditLocation) -> Result<Database, MedError> {
+        let pool = match location {
+            AuditLocation::ConfigDir | AuditLocation::File(_) => {
+                let database_url = Self::create_audit_db(location).await?;
+                Self::connect_file(&database_url).await?
+            }
+            AuditLocation::Memory | AuditLocation::Disabled => {
+                info!(
+                    "audit database {}",
+                    format!("{:?}", location).bold().green()
+                );
+                Self::connect_memory().await?
+            }
+        };
+
+        migrate(&pool).await?;
+        let chain_key = std::env::var(AUDIT_KEY_ENV)
+            .ok()
+            .filter(|key| !key.is_empty());
+        Ok(Database {
+            pool,
+            chain_key,
+            max_failure_reasons: None,
+        })
+    }
+
+    /// Returns the database with the HMAC key of the hash chain, overriding `MED_AUDIT_KEY`
+    pub fn with_chain_key(mut self, key: Option<String>) -> Self {
+        if key.is_some() {
+            self.chain_key = key;
+        }
+        self
+    }
+
+    async fn connect_file(database_url: &PathBuf) -> Result<Pool<Sqlite>, MedError> {
+        if database_url.exists() {
             info!(
-                "audit database {} created",
+                "audit database {} exist",
                 database_url.display().to_string().bold().green()
             );
         } else {
             info!(
-                "audit database {} exist",
+                "audit database {} created",
                 database_url.display().to_string().bold().green()
             );
         }
         let pool_timeout = Duration::from_secs(30);
 
-        let connection_options =
-            SqliteConnectOptions::from_str(&database_url.display().to_string().bold().green())?
-                .create_if_missing(true)
-                .journal_mode(SqliteJournalMode::Wal)
-                .synchronous(SqliteSynchronous::Normal)
-                .busy_timeout(pool_timeout);
+        let connection_options = SqliteConnectOptions::new()
+            .filename(database_url)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(pool_timeout);
 
         let pool = SqlitePoolOptions::new()
             .max_connections(20)
             .connect_with(connection_options)
             .await?;
-
-        // Self::create_table(&pool).await?;
-        Self::migrate(&pool).await?;
-
-        // Self::migrate(&pool).await?;
-        Ok(Database { pool })
-    }
-
-    async fn create_audit_db() -> Result<PathBuf, MedError> {
-        let path = dirs::config_dir().unwrap().join("med.db");
-        Ok(path)
+        Ok(pool)
     }
 
-    async fn migrate(pool: &Pool<Sqlite>) -> Result<(), MedError> {
-        // this is the implementation for the migrations folder
-        // let migrations = std::path::Path::new("./migrations");
-        // sqlx::migrate::Migrator::new(migrations)
-        //     .await?
-        //     .run(pool)
-        //     .await?;
-        Self::create_table(pool).await?;
-        Self::alter_table(pool).await?;
-        Ok(())
-    }
-
-    async fn create_table(pool: &Pool<Sqlite>) -> Result<(), MedError> {
-        let result = sqlx::query(
-            "
-            CREATE TABLE IF NOT EXISTS audit (
-                id INTEGER PRIMARY KEY,
-                user TEXT NOT NULL,
-                hostname TEXT NOT NULL,
-                total_files INTEGER NOT NULL,
-                total_records INTEGER NOT NULL,
-                failed_records INTEGER NOT NULL,
-                record_failed_reason TEXT,
-                runtime_conf TEXT NOT NULL,
-                process_failure_reason TEXT,
-                successed BOOLEAN NOT NULL DEFAULT FALSE,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
-            );
-            ",
-        )
-        .execute(pool)
-        .await?;
-        debug!("audit database {:?} create table successed", result);
-        Ok(())
+    /// Every connection to an in memory database is a new database, the pool keeps a single
+    /// connection for the lifetime of the process
+    async fn connect_memory() -> Result<Pool<Sqlite>, MedError> {
+        let connection_options = SqliteConnectOptions::from_str("sqlite::memory:")?;
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(connection_options)
+            .await?;
+        Ok(pool)
     }
 
-    async fn alter_table(pool: &Pool<Sqlite>) -> Result<(), MedError> {
-        let res = sqlx::query(
-            "select count(*) as count from pragma_table_info('audit') where name='elapsed_time';",
-        )
-        .fetch_one(pool)
-        .await?;
-
-        if res.get::<i32, &str>("count") == 0 {
-            let result = sqlx::query(
-                "
-                ALTER TABLE audit ADD COLUMN elapsed_time TEXT;
-                ",
-            )
-            .execute(pool)
-            .await?;
-            debug!("audit database {:?} alter table successed", result);
-        } else {
-            debug!("audit database alter cols exist skip");
+    async fn create_audit_db(location: &AuditLocation) -> Result<PathBuf, MedError> {
+        let path = match location {
+            AuditLocation::File(path) => path.to_owned(),
+            _ => match dirs::config_dir() {
+                Some(dir) => dir.join("med.db"),
+                None => {
+                    return Err(MedError {
+                        message: Some(
+                            "no config directory found for the audit database".to_string(),
+                        ),
+                        cause: Some(format!(
+                            "set the audit database location with --audit-db or {}",
+                            AUDIT_DB_ENV
+                        )),
+                        error_type: MedErrorType::ConfigError,
+                    })
+                }
+            },
+        };
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
         }
+        Ok(path)
+    }
 
-        Ok(())
+    /// Privite function returns the stored failure reasons of the run, capped to the maximum
+    /// number, the failed records count is kept exact
+    fn record_failed_reason(&self, summary: &Summary) -> Result<String, MedError> {
+        let reasons = &summary.metrics.metadata.record_failed_reason;
+        let stored = match self.max_failure_reasons {
+            Some(max) if reasons.len() > max => {
+                debug!(
+                    "audit stores {} of the {} failure reasons",
+                    max,
+                    reasons.len()
+                );
+                &reasons[..max]
+            }
+            _ => &reasons[..],
+        };
+        Ok(serde_json::to_string(stored)?)
     }
 
     pub async fn insert(&mut self, summary: &Summary) -> Result<i64, MedError> {
         let total_files = summary.metrics.total_files as i64;
         let total_records = summary.metrics.metadata.total_records as i64;
         let failed_records: i64 = summary.metrics.metadata.failed_records as i64;
-        let record_failed_reason =
-            serde_json::to_string(&summary.metrics.metadata.record_failed_reason)?;
+        let record_failed_reason = self.record_failed_reason(summary)?;
         let elapsed_time = summary.elapsed_time.to_owned();
 
         let id = sqlx::query!(
             r#"
-                INSERT INTO audit ( user, hostname, total_files, total_records, failed_records, record_failed_reason, runtime_conf, process_failure_reason, successed, elapsed_time )
-                VALUES ( ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                INSERT INTO audit ( user, hostname, total_files, total_records, failed_records, record_failed_reason, runtime_conf, process_failure_reason, successed, elapsed_time, cancelled )
+                VALUES ( ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
         "#,
-        summary.user, summary.hostname, total_files, total_records, failed_records, record_failed_reason, summary.runtime_conf, summary.process_failure_reason, summary.successed, elapsed_time
+        summary.user, summary.hostname, total_files, total_records, failed_records, record_failed_reason, summary.runtime_conf, summary.process_failure_reason, summary.successed, elapsed_time, summary.cancelled
         )
         .execute(&self.pool)
         .await?
         .last_insert_rowid();
         Ok(id)
     }
+
+    /// Update the audit record at the end of the run and seal it in the hash chain, a sealed
+    /// record can not be updated anymore
+    pub async fn update(&mut self, id: i64, summary: &Summary) -> Result<(), MedError> {
+        let total_files = summary.metrics.total_files as i64;
+        let total_records = summary.metrics.metadata.total_records as i64;
+        let failed_records: i64 = summary.metrics.metadata.failed_records as i64;
+        let record_failed_reason = self.record_failed_reason(summary)?;
+        let elapsed_time = summary.elapsed_time.to_owned();
+
+        let mut tx = self.pool.begin().await?;
+        let updated = sqlx::query!(
+            r#"
+                UPDATE audit SET user = ?1, hostname = ?2, total_files = ?3, total_records = ?4, failed_records = ?5, record_failed_reason = ?6, runtime_conf = ?7, process_failure_reason = ?8, successed = ?9, elapsed_time = ?10, cancelled = ?11
+                WHERE id = ?12 AND chain_index IS NULL
+        "#,
+        summary.user, summary.hostname, total_files, total_records, failed_records, record_failed_reason, summary.runtime_conf, summary.process_failure_reason, summary.successed, elapsed_time, summary.cancelled, id
+        )
+        .execute(&mut tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(MedError {
+                message: Some(format!(
+                    "audit record_id {} not found or already sealed",
+                    id
+                )),
+                cause: Some("a sealed audit record can not be updated".to_string()),
+                error_type: MedErrorType::DatabaseError,
+            });
+        }
+        // the update above holds the write lock, the chain is appended by one run at a time
+        seal(&mut tx, id, self.chain_key.as_deref()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn insert_file(
+        &self,
+        audit_id: i64,
+        config_hash: &str,
+        file: &AuditFile,
+    ) -> Result<(), MedError> {
+        sqlx::query!(
+            r#"
+                INSERT INTO audit_file ( audit_id, input_path, input_hash, config_hash, output_path, input_size, output_hash, output_size, total_records, failed_records )
+                VALUES ( ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10 )
+        "#,
+        audit_id, file.input_path, file.input_hash, config_hash, file.output_path, file.input_size, file.output_hash, file.output_size, file.total_records, file.failed_records
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the files completed by the audit run with the same config hash
+    pub async fn completed_files(
+        &self,
+        audit_id: i64,
+        config_hash: &str,
+    ) -> Result<Vec<AuditFile>, MedError> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE audit_id = ?1 AND config_hash = ?2;",
+            AUDIT_FILE_COLUMNS
+        ))
+        .bind(audit_id)
+        .bind(config_hash)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(audit_file).collect())
+    }
 }
+
+#[cfg(test)]
+#[path = "../tests/db_test.rs"]
+mod db_test;
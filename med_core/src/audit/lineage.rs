@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use serde::Serialize;
+use sqlx::{Executor, Sqlite};
+
+use crate::audit::db::{audit_file, AuditFile, AUDIT_FILE_COLUMNS};
+use crate::utils::digest::{sha256_file, sha256_hex};
+use crate::utils::error::MedError;
+
+/// The state of an output file compared to its recorded lineage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineageStatus {
+    /// the output is identical to the output written by the run
+    Unchanged,
+    /// the output was modified after the run
+    Altered,
+    /// the output was moved or deleted after the run
+    Missing,
+    /// the output hash was not recorded, by the previous releases
+    Unknown,
+}
+
+/// A file of the lineage and the state of its output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LineageCheck {
+    #[serde(flatten)]
+    pub file: AuditFile,
+    pub status: LineageStatus,
+}
+
+/// Returns the state of the output of every file of the lineage
+pub fn check_lineage(files: Vec<AuditFile>) -> Vec<LineageCheck> {
+    files
+        .into_iter()
+        .map(|file| {
+            let output_path = Path::new(&file.output_path);
+            let status = match (&file.output_hash, output_path.exists()) {
+                (_, false) => LineageStatus::Missing,
+                (None, true) => LineageStatus::Unknown,
+                (Some(output_hash), true) => match sha256_file(output_path) {
+                    Ok(hash) if &hash == output_hash => LineageStatus::Unchanged,
+                    _ => LineageStatus::Altered,
+                },
+            };
+            LineageCheck { file, status }
+        })
+        .collect()
+}
+
+/// Returns the digest of the lineage of the audit run, sealed with the audit record in the
+/// hash chain
+pub(crate) async fn lineage_digest<'c, E>(executor: E, audit_id: i64) -> Result<String, MedError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let rows = sqlx::query(&format!(
+        "{} WHERE audit_id = ?1 ORDER BY id;",
+        AUDIT_FILE_COLUMNS
+    ))
+    .bind(audit_id)
+    .fetch_all(executor)
+    .await?;
+    let files: Vec<AuditFile> = rows.iter().map(audit_file).collect();
+    Ok(sha256_hex(serde_json::to_string(&files)?.as_bytes()))
+}
+
+#[cfg(test)]
+#[path = "../tests/lineage_test.rs"]
+mod lineage_test;
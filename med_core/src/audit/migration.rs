@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    Pool, Row, Sqlite, SqliteConnection,
+};
+use tracing::{debug, info};
+
+use crate::utils::error::MedError;
+
+/// The versioned migrations of the audit database, from `db/migrations`, applied in order and
+/// recorded in the `_sqlx_migrations` table
+pub static MIGRATOR: Migrator = sqlx::migrate!("../db/migrations");
+
+/// The migrations applied by the releases before the migrator, and the query probing whether
+/// the schema already has them
+const LEGACY_MIGRATIONS: [(i64, &str); 4] = [
+    (
+        20230512195802,
+        "SELECT count(*) AS count FROM sqlite_master WHERE type = 'table' AND name = 'audit';",
+    ),
+    (
+        20230622150359,
+        "SELECT count(*) AS count FROM pragma_table_info('audit') WHERE name = 'elapsed_time';",
+    ),
+    (
+        20261019100000,
+        "SELECT count(*) AS count FROM sqlite_master WHERE type = 'table' AND name = 'audit_file';",
+    ),
+    (
+        20261019110000,
+        "SELECT count(*) AS count FROM pragma_table_info('audit') WHERE name = 'cancelled';",
+    ),
+];
+
+/// Concurrent runs on a new database race to apply the same migrations, the loser retries
+/// once the winner is done
+const MIGRATE_ATTEMPTS: u64 = 5;
+
+/// Apply the pending migrations to the audit database
+pub async fn migrate(pool: &Pool<Sqlite>) -> Result<(), MedError> {
+    let mut attempt = 1;
+    loop {
+        match try_migrate(pool).await {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt < MIGRATE_ATTEMPTS => {
+                debug!(
+                    "audit database migration attempt {} failed {:?}",
+                    attempt, err
+                );
+                tokio::time::sleep(Duration::from_millis(50 * attempt)).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+async fn try_migrate(pool: &Pool<Sqlite>) -> Result<(), MedError> {
+    let mut conn = pool.acquire().await?;
+    bootstrap_legacy(&mut conn).await?;
+    MIGRATOR.run(&mut *conn).await?;
+    Ok(())
+}
+
+/// Record the migrations already applied to a database created before the migrator, so they
+/// are not applied twice
+async fn bootstrap_legacy(conn: &mut SqliteConnection) -> Result<(), MedError> {
+    let migrated = sqlx::query(
+        "SELECT count(*) AS count FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations';",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if migrated.get::<i32, &str>("count") > 0 {
+        return Ok(());
+    }
+
+    conn.ensure_migrations_table().await?;
+    for (version, probe) in LEGACY_MIGRATIONS {
+        let applied = sqlx::query(probe).fetch_one(&mut *conn).await?;
+        if applied.get::<i32, &str>("count") == 0 {
+            continue;
+        }
+        if let Some(migration) = MIGRATOR
+            .iter()
+            .find(|m| m.version == version && !m.migration_type.is_down_migration())
+        {
+            sqlx::query(
+                "INSERT OR IGNORE INTO _sqlx_migrations ( version, description, success, checksum, execution_time ) VALUES ( ?1, ?2, TRUE, ?3, 0 );",
+            )
+            .bind(migration.version)
+            .bind(migration.description.as_ref())
+            .bind(migration.checksum.as_ref())
+            .execute(&mut *conn)
+            .await?;
+            info!(
+                "audit database migration {} already applied",
+                migration.version
+            );
+        }
+    }
+    Ok(())
+}
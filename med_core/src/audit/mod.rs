@@ -1,2 +1,9 @@
 pub mod app;
+pub mod chain;
+pub mod checkpoint;
 pub mod db;
+pub mod lineage;
+pub mod migration;
+pub mod query;
+pub mod retention;
+pub mod sink;
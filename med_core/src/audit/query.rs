@@ -0,0 +1,160 @@
+use std::io::Write;
+
+use serde::Serialize;
+use sqlx::{sqlite::SqliteRow, QueryBuilder, Row, Sqlite};
+
+use crate::audit::db::{audit_file, AuditFile, Database, AUDIT_FILE_COLUMNS};
+use crate::models::enums::Mode;
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The filter of the audit records, every condition set must match
+#[derive(Debug, Default, Clone)]
+pub struct AuditFilter {
+    /// the first day, `YYYY-MM-DD`, inclusive
+    pub since: Option<String>,
+    /// the last day, `YYYY-MM-DD`, inclusive
+    pub until: Option<String>,
+    pub user: Option<String>,
+    pub hostname: Option<String>,
+    pub mode: Option<Mode>,
+    pub successed: Option<bool>,
+    pub limit: Option<u32>,
+}
+
+/// A run recorded in the audit database
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub created_at: String,
+    pub user: String,
+    pub hostname: String,
+    pub mode: Option<String>,
+    /// the built-in profile of the run
+    pub profile: Option<String>,
+    pub total_files: i64,
+    pub total_records: i64,
+    pub failed_records: i64,
+    pub successed: bool,
+    pub cancelled: bool,
+    pub elapsed_time: Option<String>,
+    pub process_failure_reason: Option<String>,
+    pub record_failed_reason: Option<String>,
+    pub runtime_conf: String,
+}
+
+const AUDIT_COLUMNS: &str = "SELECT id, strftime('%Y-%m-%d %H:%M:%S', created_at) AS created_at, user, hostname, \
+    CASE WHEN json_valid(runtime_conf) THEN json_extract(runtime_conf, '$.mode') END AS mode, \
+    CASE WHEN json_valid(runtime_conf) THEN json_extract(runtime_conf, '$.profile') END AS profile, total_files, total_records, failed_records, successed, \
+    cancelled, elapsed_time, process_failure_reason, record_failed_reason, runtime_conf FROM audit";
+
+impl Database {
+    /// Returns the audit records matching the filter, the latest first
+    pub async fn list(&self, filter: &AuditFilter) -> Result<Vec<AuditRecord>, MedError> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(AUDIT_COLUMNS);
+        query.push(" WHERE 1 = 1");
+        if let Some(since) = &filter.since {
+            query
+                .push(" AND date(created_at) >= date(")
+                .push_bind(since)
+                .push(")");
+        }
+        if let Some(until) = &filter.until {
+            query
+                .push(" AND date(created_at) <= date(")
+                .push_bind(until)
+                .push(")");
+        }
+        if let Some(user) = &filter.user {
+            query.push(" AND user = ").push_bind(user);
+        }
+        if let Some(hostname) = &filter.hostname {
+            query.push(" AND hostname = ").push_bind(hostname);
+        }
+        if let Some(mode) = &filter.mode {
+            query
+                .push(" AND json_valid(runtime_conf) AND json_extract(runtime_conf, '$.mode') = ")
+                .push_bind(format!("{:?}", mode));
+        }
+        if let Some(successed) = filter.successed {
+            query.push(" AND successed = ").push_bind(successed);
+        }
+        query.push(" ORDER BY id DESC");
+        if let Some(limit) = filter.limit {
+            query.push(" LIMIT ").push_bind(limit);
+        }
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(audit_record).collect())
+    }
+
+    /// Returns the audit record of the id
+    pub async fn show(&self, id: i64) -> Result<AuditRecord, MedError> {
+        let row = sqlx::query(&format!("{} WHERE id = ?1", AUDIT_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => Ok(audit_record(&row)),
+            None => Err(MedError {
+                message: Some(format!("audit record_id {} not found", id)),
+                cause: Some("check the id with med audit list".to_string()),
+                error_type: MedErrorType::DatabaseError,
+            }),
+        }
+    }
+
+    /// Returns the files completed by the audit run, with their lineage
+    pub async fn audit_files(&self, audit_id: i64) -> Result<Vec<AuditFile>, MedError> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE audit_id = ?1 ORDER BY id;",
+            AUDIT_FILE_COLUMNS
+        ))
+        .bind(audit_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(audit_file).collect())
+    }
+}
+
+/// Privite function map the row to the [AuditRecord]
+fn audit_record(row: &SqliteRow) -> AuditRecord {
+    AuditRecord {
+        id: row.get("id"),
+        created_at: row.get("created_at"),
+        user: row.get("user"),
+        hostname: row.get("hostname"),
+        mode: row.get("mode"),
+        profile: row.get("profile"),
+        total_files: row.get("total_files"),
+        total_records: row.get("total_records"),
+        failed_records: row.get("failed_records"),
+        successed: row.get("successed"),
+        cancelled: row.get("cancelled"),
+        elapsed_time: row.get("elapsed_time"),
+        process_failure_reason: row.get("process_failure_reason"),
+        record_failed_reason: row.get("record_failed_reason"),
+        runtime_conf: row.get("runtime_conf"),
+    }
+}
+
+/// Write the audit records as csv, with a header
+pub fn export_csv<W: Write>(records: &[AuditRecord], writer: W) -> Result<(), MedError> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    for record in records {
+        wtr.serialize(record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Write the audit records as a json array
+pub fn export_json<W: Write>(records: &[AuditRecord], mut writer: W) -> Result<(), MedError> {
+    serde_json::to_writer_pretty(&mut writer, records)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "../tests/query_test.rs"]
+mod query_test;
@@ -0,0 +1,194 @@
+use serde::Serialize;
+use sqlx::{Row, SqliteConnection};
+use tracing::info;
+
+use crate::audit::chain::{anchor_hash, chain_algo};
+use crate::audit::db::Database;
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The sql condition of the finished runs, the runs in progress, or interrupted and resumable,
+/// have no elapsed time until their record is updated at their end
+const FINISHED: &str = "(elapsed_time IS NULL OR elapsed_time != '')";
+
+/// The audit records to keep, the records matching any limit are purged
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// purge the records created more than this number of days ago
+    pub max_age_days: Option<u32>,
+    /// keep only this number of the latest records
+    pub max_rows: Option<u32>,
+}
+
+/// The outcome of the purge
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct PurgeReport {
+    pub purged_records: u64,
+    pub purged_files: u64,
+    /// the sealed records kept as they follow a record not purged, the chain is only purged
+    /// from its start
+    pub kept_records: u64,
+    /// the chain index of the last purged sealed record, the chain is verified from it
+    pub anchor_index: Option<i64>,
+}
+
+impl RetentionPolicy {
+    /// Privite function returns the sql condition of the records to purge
+    fn condition(&self) -> Option<String> {
+        let mut conditions = vec![];
+        if let Some(days) = self.max_age_days {
+            conditions.push(format!("created_at < datetime('now', '-{} days')", days));
+        }
+        if let Some(rows) = self.max_rows {
+            conditions.push(format!(
+                "id NOT IN (SELECT id FROM audit ORDER BY id DESC LIMIT {})",
+                rows
+            ));
+        }
+        match conditions.is_empty() {
+            true => None,
+            false => Some(format!("({})", conditions.join(" OR "))),
+        }
+    }
+}
+
+impl Database {
+    /// Purge the audit records, and their files lineage, matching the retention policy
+    ///
+    /// The sealed records are only purged from the start of the hash chain, the last purged one
+    /// is kept as the anchor the chain is verified from, so the chain stays verifiable
+    pub async fn purge(&self, policy: &RetentionPolicy) -> Result<PurgeReport, MedError> {
+        let condition = match policy.condition() {
+            Some(condition) => condition,
+            None => {
+                return Err(MedError {
+                    message: Some("the retention policy has no limit".to_string()),
+                    cause: Some("set the max age or the max rows to purge".to_string()),
+                    error_type: MedErrorType::ConfigError,
+                })
+            }
+        };
+
+        // take the write lock first, the chain is not appended while purged
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("BEGIN IMMEDIATE;").execute(&mut *conn).await?;
+        match purge_records(&mut conn, &condition, self.chain_key.as_deref()).await {
+            Ok(report) => {
+                sqlx::query("COMMIT;").execute(&mut *conn).await?;
+                info!(
+                    "audit purged {} records and {} files",
+                    report.purged_records, report.purged_files
+                );
+                Ok(report)
+            }
+            Err(err) => {
+                sqlx::query("ROLLBACK;").execute(&mut *conn).await?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Reclaim the space of the purged records
+    pub async fn vacuum(&self) -> Result<(), MedError> {
+        sqlx::query("VACUUM;").execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+/// Privite function purge the records of the condition within the transaction of the connection
+async fn purge_records(
+    conn: &mut SqliteConnection,
+    condition: &str,
+    key: Option<&str>,
+) -> Result<PurgeReport, MedError> {
+    // the first sealed record not purged ends the purged part of the chain
+    let boundary: Option<i64> = sqlx::query(&format!(
+        "SELECT min(chain_index) AS chain_index FROM audit WHERE chain_index IS NOT NULL AND NOT {};",
+        condition
+    ))
+    .fetch_one(&mut *conn)
+    .await?
+    .get("chain_index");
+    let sealed = match boundary {
+        Some(boundary) => format!("chain_index < {}", boundary),
+        None => "chain_index IS NOT NULL".to_string(),
+    };
+    let purged = format!(
+        "(chain_index IS NULL AND {} AND {}) OR ({})",
+        FINISHED, condition, sealed
+    );
+
+    let kept: i64 = sqlx::query(&format!(
+        "SELECT count(*) AS count FROM audit WHERE chain_index IS NOT NULL AND {} AND NOT ({});",
+        condition, sealed
+    ))
+    .fetch_one(&mut *conn)
+    .await?
+    .get("count");
+
+    // the last purged sealed record becomes the anchor of the chain
+    let last = sqlx::query(&format!(
+        "SELECT chain_index, row_hash FROM audit WHERE {} ORDER BY chain_index DESC LIMIT 1;",
+        sealed
+    ))
+    .fetch_optional(&mut *conn)
+    .await?;
+    let purged_records = sqlx::query(&format!(
+        "SELECT count(*) AS count FROM audit WHERE {};",
+        purged
+    ))
+    .fetch_one(&mut *conn)
+    .await?
+    .get::<i64, &str>("count") as u64;
+    if let Some(last) = &last {
+        sqlx::query(
+            "INSERT INTO audit_chain_anchor ( id, chain_index, row_hash, purged_records ) VALUES ( 1, ?1, ?2, ?3 ) \
+            ON CONFLICT(id) DO UPDATE SET chain_index = excluded.chain_index, row_hash = excluded.row_hash, \
+            purged_records = purged_records + excluded.purged_records, updated_at = CURRENT_TIMESTAMP;",
+        )
+        .bind(last.get::<i64, &str>("chain_index"))
+        .bind(last.get::<String, &str>("row_hash"))
+        .bind(purged_records as i64)
+        .execute(&mut *conn)
+        .await?;
+        // the anchor is sealed with the key of the chain
+        let anchor = sqlx::query(
+            "SELECT chain_index, row_hash, purged_records FROM audit_chain_anchor WHERE id = 1;",
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        sqlx::query(
+            "UPDATE audit_chain_anchor SET chain_algo = ?1, anchor_hash = ?2 WHERE id = 1;",
+        )
+        .bind(chain_algo(key))
+        .bind(anchor_hash(
+            anchor.get("chain_index"),
+            &anchor.get::<String, &str>("row_hash"),
+            anchor.get("purged_records"),
+            key,
+        ))
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    let purged_files = sqlx::query(&format!(
+        "DELETE FROM audit_file WHERE audit_id IN (SELECT id FROM audit WHERE {});",
+        purged
+    ))
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    sqlx::query(&format!("DELETE FROM audit WHERE {};", purged))
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(PurgeReport {
+        purged_records,
+        purged_files,
+        kept_records: kept as u64,
+        anchor_index: last.map(|last| last.get::<i64, &str>("chain_index")),
+    })
+}
+
+#[cfg(test)]
+#[path = "../tests/retention_test.rs"]
+mod retention_test;
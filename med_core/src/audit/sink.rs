@@ -0,0 +1,309 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::audit::app::Summary;
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The environment variable of the audit sinks, comma separated
+pub const AUDIT_SINKS_ENV: &str = "MED_AUDIT_SINKS";
+
+/// The local syslog socket
+pub const DEFAULT_SYSLOG_SOCKET: &str = "/dev/log";
+
+/// The timeout of the webhook request
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The audit record of a completed run, as emitted to the sinks
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEvent {
+    pub audit_id: i64,
+    /// seconds since the unix epoch
+    pub timestamp: u64,
+    pub user: String,
+    pub hostname: String,
+    pub successed: bool,
+    pub cancelled: bool,
+    pub total_files: usize,
+    pub total_records: usize,
+    pub failed_records: usize,
+    pub elapsed_time: String,
+    pub process_failure_reason: Option<String>,
+    pub runtime_conf: serde_json::Value,
+}
+
+impl AuditEvent {
+    /// Returns the event of the audit summary
+    pub fn new(audit_id: i64, summary: &Summary) -> Self {
+        AuditEvent {
+            audit_id,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            user: summary.user.to_owned(),
+            hostname: summary.hostname.to_owned(),
+            successed: summary.successed,
+            cancelled: summary.cancelled,
+            total_files: summary.metrics.total_files,
+            total_records: summary.metrics.metadata.total_records,
+            failed_records: summary.metrics.metadata.failed_records,
+            elapsed_time: summary.elapsed_time.to_owned(),
+            process_failure_reason: summary.process_failure_reason.to_owned(),
+            runtime_conf: serde_json::from_str(&summary.runtime_conf)
+                .unwrap_or(serde_json::Value::Null),
+        }
+    }
+}
+
+/// A destination of the audit events, in addition to the audit database
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    /// Returns the name of the sink, for the logs
+    fn name(&self) -> String;
+
+    /// Emit the event of a completed run
+    async fn emit(&self, event: &AuditEvent) -> Result<(), MedError>;
+}
+
+/// The configuration of an audit sink
+///
+/// * `jsonl:<path>` - append a json line per event to the file
+/// * `syslog` or `syslog:<socket>` - send to the local syslog socket, `/dev/log` by default
+/// * `webhook:<url>` - post the json event to the `http://` or `https://` url
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkConfig {
+    JsonLines(PathBuf),
+    Syslog(PathBuf),
+    Webhook(String),
+}
+
+impl FromStr for SinkConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, target) = match s.trim().split_once(':') {
+            Some((kind, target)) => (kind, target.trim()),
+            None => (s.trim(), ""),
+        };
+        match (kind, target) {
+            ("jsonl", "") => Err("the jsonl sink needs a file path, jsonl:<path>".to_string()),
+            ("jsonl", path) => Ok(SinkConfig::JsonLines(PathBuf::from(path))),
+            ("syslog", "") => Ok(SinkConfig::Syslog(PathBuf::from(DEFAULT_SYSLOG_SOCKET))),
+            ("syslog", socket) => Ok(SinkConfig::Syslog(PathBuf::from(socket))),
+            ("webhook", url) if url.starts_with("http://") || url.starts_with("https://") => {
+                Ok(SinkConfig::Webhook(url.to_string()))
+            }
+            ("webhook", _) => {
+                Err("the webhook sink needs an http:// or https:// url".to_string())
+            }
+            _ => Err(format!(
+                "unknown audit sink {:?}, expected jsonl:<path>, syslog[:<socket>] or webhook:<url>",
+                s
+            )),
+        }
+    }
+}
+
+impl SinkConfig {
+    /// Returns the sinks from, in order, the cli, the `MED_AUDIT_SINKS` environment variable, or
+    /// the job configuration, none by default
+    pub fn resolve(cli: &[String], conf: &[String]) -> Result<Vec<Self>, MedError> {
+        let env: Vec<String> = std::env::var(AUDIT_SINKS_ENV)
+            .map(|sinks| {
+                sinks
+                    .split(',')
+                    .filter(|sink| !sink.trim().is_empty())
+                    .map(|sink| sink.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let sinks = match (cli.is_empty(), env.is_empty()) {
+            (false, _) => cli,
+            (true, false) => env.as_slice(),
+            (true, true) => conf,
+        };
+        sinks
+            .iter()
+            .map(|sink| {
+                SinkConfig::from_str(sink).map_err(|err| MedError {
+                    message: Some(err),
+                    cause: Some(format!("check --audit-sink or {}", AUDIT_SINKS_ENV)),
+                    error_type: MedErrorType::ConfigError,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the sink of the configuration
+    pub fn build(&self) -> Box<dyn AuditSink> {
+        match self {
+            SinkConfig::JsonLines(path) => Box::new(JsonLinesSink { path: path.clone() }),
+            SinkConfig::Syslog(socket) => Box::new(SyslogSink {
+                socket: socket.clone(),
+            }),
+            SinkConfig::Webhook(url) => Box::new(WebhookSink::new(url)),
+        }
+    }
+}
+
+/// Emit the event to every sink, a failed sink is logged and does not fail the run, the audit
+/// database keeps the record
+pub async fn emit_all(sinks: &[Box<dyn AuditSink>], event: &AuditEvent) {
+    for sink in sinks {
+        match sink.emit(event).await {
+            Ok(()) => debug!("audit event {} emitted to {}", event.audit_id, sink.name()),
+            Err(err) => warn!(
+                "audit event {} not emitted to {}: {}",
+                event.audit_id,
+                sink.name(),
+                err
+            ),
+        }
+    }
+}
+
+/// Append a json line per event to the file
+#[derive(Debug, Clone)]
+pub struct JsonLinesSink {
+    pub path: PathBuf,
+}
+
+#[async_trait]
+impl AuditSink for JsonLinesSink {
+    fn name(&self) -> String {
+        format!("jsonl:{}", self.path.display())
+    }
+
+    async fn emit(&self, event: &AuditEvent) -> Result<(), MedError> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        // a single append write, the lines of concurrent runs are not interleaved
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Send the event to the local syslog socket, with the user facility
+#[derive(Debug, Clone)]
+pub struct SyslogSink {
+    pub socket: PathBuf,
+}
+
+impl SyslogSink {
+    /// Returns the syslog message of the event, informational for the successed runs, warning
+    /// otherwise
+    pub fn message(event: &AuditEvent) -> Result<String, MedError> {
+        const FACILITY_USER: u8 = 1;
+        let severity = match event.successed {
+            true => 6,
+            false => 4,
+        };
+        Ok(format!(
+            "<{}>med[{}]: {}",
+            FACILITY_USER * 8 + severity,
+            std::process::id(),
+            serde_json::to_string(event)?
+        ))
+    }
+}
+
+#[async_trait]
+impl AuditSink for SyslogSink {
+    fn name(&self) -> String {
+        format!("syslog:{}", self.socket.display())
+    }
+
+    #[cfg(unix)]
+    async fn emit(&self, event: &AuditEvent) -> Result<(), MedError> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        socket.send_to(Self::message(event)?.as_bytes(), &self.socket)?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    async fn emit(&self, _event: &AuditEvent) -> Result<(), MedError> {
+        Err(MedError {
+            message: Some("the syslog sink is only supported on unix".to_string()),
+            cause: None,
+            error_type: MedErrorType::ConfigError,
+        })
+    }
+}
+
+/// Post the json event to the http or https url
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    pub url: String,
+    /// the timeout of the request, from the connection to the response
+    pub timeout: Duration,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Self {
+        WebhookSink {
+            url: url.to_string(),
+            timeout: WEBHOOK_TIMEOUT,
+        }
+    }
+
+    /// Privite function returns the [MedError] of a failed request, without the url which may
+    /// hold a token
+    fn request_error(&self, err: reqwest::Error) -> MedError {
+        let message = match (err.is_timeout(), err.status()) {
+            (true, _) => format!("{} timed out after {:?}", self.name(), self.timeout),
+            (false, Some(status)) => {
+                format!("{} responded with the status {}", self.name(), status)
+            }
+            (false, None) => format!("{} request failed", self.name()),
+        };
+        MedError {
+            message: Some(message),
+            cause: Some(err.without_url().to_string()),
+            error_type: MedErrorType::IoError,
+        }
+    }
+}
+
+#[async_trait]
+impl AuditSink for WebhookSink {
+    fn name(&self) -> String {
+        "webhook".to_string()
+    }
+
+    async fn emit(&self, event: &AuditEvent) -> Result<(), MedError> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .user_agent(concat!("med/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|err| self.request_error(err))?;
+        client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(event)?)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| self.request_error(err))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/sink_test.rs"]
+mod sink_test;
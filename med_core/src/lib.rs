@@ -1,4 +1,6 @@
 pub mod app;
 pub mod audit;
 pub mod models;
+pub mod strategy;
 pub mod utils;
+pub mod vault;
@@ -24,12 +24,22 @@ pub enum Mode {
     MASK,
     ENCRYPT,
     DECRYPT,
+    TOKENIZE,
+    DETOKENIZE,
+    KANONYMITY,
 }
 
 // Can also be derived with feature flag `derive`
 impl ValueEnum for Mode {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Mode::MASK, Mode::ENCRYPT, Mode::DECRYPT]
+        &[
+            Mode::MASK,
+            Mode::ENCRYPT,
+            Mode::DECRYPT,
+            Mode::TOKENIZE,
+            Mode::DETOKENIZE,
+            Mode::KANONYMITY,
+        ]
     }
 
     fn to_possible_value<'a>(&self) -> Option<PossibleValue> {
@@ -41,6 +51,12 @@ impl ValueEnum for Mode {
             Mode::DECRYPT => {
                 PossibleValue::new("decrypt").help("Decrypt the data with provided KEY")
             }
+            Mode::TOKENIZE => PossibleValue::new("tokenize")
+                .help("Replace the data with tokens stored in the vault of the provided KEY"),
+            Mode::DETOKENIZE => PossibleValue::new("detokenize")
+                .help("Restore the tokenized data from the vault of the provided KEY"),
+            Mode::KANONYMITY => PossibleValue::new("k-anonymity")
+                .help("Check and enforce the k-anonymity of the quasi identifiers of the csv data"),
         })
     }
 }
@@ -72,6 +88,9 @@ impl std::fmt::Debug for Mode {
             Self::MASK => write!(f, "MASK"),
             Self::ENCRYPT => write!(f, "ENCRYPT"),
             Self::DECRYPT => write!(f, "DECRYPT"),
+            Self::TOKENIZE => write!(f, "TOKENIZE"),
+            Self::DETOKENIZE => write!(f, "DETOKENIZE"),
+            Self::KANONYMITY => write!(f, "KANONYMITY"),
         }
     }
 }
@@ -165,6 +184,59 @@ impl std::fmt::Debug for Standard {
     }
 }
 
+/// What to do when an output file already exists
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Default)]
+pub enum OutputPolicy {
+    #[default]
+    Overwrite,
+    NoClobber,
+    Suffix,
+}
+
+impl fmt::Display for OutputPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputPolicy::Overwrite => write!(f, "overwrite"),
+            OutputPolicy::NoClobber => write!(f, "no-clobber"),
+            OutputPolicy::Suffix => write!(f, "suffix"),
+        }
+    }
+}
+
+/// Why a record failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordErrorKind {
+    /// the file could not be read
+    Read,
+    /// the record is malformed
+    Parse,
+    Encrypt,
+    Decrypt,
+    /// the value does not fit the masking strategy of the field
+    Mask,
+    Tokenize,
+    /// the token is not in the vault
+    Detokenize,
+    /// the output could not be written
+    Write,
+}
+
+impl fmt::Display for RecordErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordErrorKind::Read => write!(f, "read"),
+            RecordErrorKind::Parse => write!(f, "parse"),
+            RecordErrorKind::Encrypt => write!(f, "encrypt"),
+            RecordErrorKind::Decrypt => write!(f, "decrypt"),
+            RecordErrorKind::Mask => write!(f, "mask"),
+            RecordErrorKind::Tokenize => write!(f, "tokenize"),
+            RecordErrorKind::Detokenize => write!(f, "detokenize"),
+            RecordErrorKind::Write => write!(f, "write"),
+        }
+    }
+}
+
 #[cfg(test)]
 #[path = "../tests/models_test.rs"]
 mod models_test;
@@ -1,14 +1,145 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::models::enums::RecordErrorKind;
 use crate::utils::error::MedError;
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct Metrics {
     pub total_files: usize,
+    pub skipped_files: usize,
+    pub pruned_files: usize,
+    pub cancelled: bool,
     pub metadata: Metadata,
+    /// the outcome of every processed file
+    pub files: Vec<FileMetrics>,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct Metadata {
     pub total_records: usize,
     pub failed_records: usize,
-    pub record_failed_reason: Vec<MedError>,
+    /// the records left out of the output by the subset
+    pub dropped_records: usize,
+    pub record_failed_reason: Vec<RecordError>,
+}
+
+/// The outcome of a single processed file
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct FileMetrics {
+    pub input_path: String,
+    pub output_path: String,
+    pub input_hash: Option<String>,
+    /// the sha256 of the written output
+    pub output_hash: Option<String>,
+    pub completed: bool,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub duration_ms: u64,
+    /// the configured fields found in the file
+    pub fields_matched: Vec<String>,
+    /// the subset fields found in the file, removed from the output
+    pub fields_dropped: Vec<String>,
+    /// the k-anonymity of the quasi identifiers, in the k-anonymity mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k_anonymity: Option<KAnonymityReport>,
+    pub metadata: Metadata,
+}
+
+/// The k-anonymity of the quasi identifiers of a file, before and after the enforcement
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct KAnonymityReport {
+    /// the target k
+    pub k: usize,
+    /// the size of the smallest class of records sharing the quasi identifiers in the input
+    pub input_k: usize,
+    /// the size of the smallest class in the output, the suppressed records left out
+    pub output_k: usize,
+    /// the number of classes in the output, the suppressed records left out
+    pub classes: usize,
+    /// the index of the input records in the classes smaller than k, the header is the record 0
+    pub at_risk_records: Vec<u64>,
+    /// the records of which the quasi identifiers are suppressed
+    pub suppressed_records: usize,
+    /// the generalization level of each quasi identifier
+    pub generalization: BTreeMap<String, String>,
+}
+
+/// A failed record and where it is located in the file, the location is only known in part
+/// for some errors, e.g. the whole file failed to be read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordError {
+    pub file: String,
+    /// line of the record, starting at 1
+    pub line: Option<u64>,
+    /// column of the json error, starting at 1
+    pub column: Option<u64>,
+    /// byte offset of the record
+    pub byte: Option<u64>,
+    /// index of the record, for csv files the header is the record 0
+    pub record: Option<u64>,
+    /// the csv header or the json path of the failed field
+    pub field: Option<String>,
+    pub kind: RecordErrorKind,
+    #[serde(flatten)]
+    pub error: MedError,
+}
+
+impl RecordError {
+    /// Returns a [RecordError] of the file, without location
+    pub fn new(file: &str, kind: RecordErrorKind, error: MedError) -> Self {
+        RecordError {
+            file: file.to_string(),
+            line: None,
+            column: None,
+            byte: None,
+            record: None,
+            field: None,
+            kind,
+            error,
+        }
+    }
+
+    /// Set the location from a csv position
+    pub fn with_position(mut self, position: Option<&csv::Position>) -> Self {
+        if let Some(position) = position {
+            self.line = Some(position.line());
+            self.byte = Some(position.byte());
+            self.record = Some(position.record());
+        }
+        self
+    }
+
+    /// Set the failed field
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_string());
+        self
+    }
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.file)?;
+        if let Some(line) = self.line {
+            write!(f, " line {}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, " column {}", column)?;
+        }
+        if let Some(byte) = self.byte {
+            write!(f, " byte {}", byte)?;
+        }
+        if let Some(record) = self.record {
+            write!(f, " record {}", record)?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, " field {}", field)?;
+        }
+        write!(f, ": {}", self.error.message())?;
+        if let Some(cause) = &self.error.cause {
+            write!(f, " ({})", cause)?;
+        }
+        Ok(())
+    }
 }
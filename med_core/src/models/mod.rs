@@ -1,3 +1,4 @@
 pub mod enums;
 pub mod metrics;
 pub mod params;
+pub mod report;
@@ -2,7 +2,8 @@ use std::fmt;
 
 use serde::Serialize;
 
-use crate::models::enums::{AppMode, FileType, Mode, Standard};
+use crate::models::enums::{AppMode, FileType, Mode, OutputPolicy, Standard};
+use crate::strategy::profile::Profile;
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Params {
@@ -16,6 +17,26 @@ pub struct Params {
     pub key: Option<String>,
     pub standard: Standard,
     pub debug: bool,
+    pub output_policy: OutputPolicy,
+    pub allow_nested_output: bool,
+    pub resume: Option<i64>,
+    pub incremental: bool,
+    pub prune: bool,
+    pub chunk_size: u64,
+    pub chunk_threshold: u64,
+    pub report_path: Option<String>,
+    pub audit_db: Option<String>,
+    pub audit_max_failure_reasons: Option<usize>,
+    /// the webhook urls may carry a token, never recorded
+    #[serde(skip_serializing)]
+    pub audit_sinks: Vec<String>,
+    pub vault_path: Option<String>,
+    /// the seed of the masking strategies, recorded for the run to be reproduced
+    pub seed: Option<u64>,
+    /// the key map file keeping the surrogates of the key fields between runs
+    pub key_map_path: Option<String>,
+    /// the built-in profile, recorded by name for the run
+    pub profile: Option<Profile>,
 }
 
 impl fmt::Display for Params {
@@ -40,6 +61,21 @@ impl Default for Params {
         let debug: bool = false;
         let worker = 2;
         let standard = Standard::default();
+        let output_policy = OutputPolicy::default();
+        let allow_nested_output = false;
+        let resume = None;
+        let incremental = false;
+        let prune = false;
+        let chunk_size = 8;
+        let chunk_threshold = 64;
+        let report_path = None;
+        let audit_db = None;
+        let audit_sinks = Vec::new();
+        let audit_max_failure_reasons = None;
+        let vault_path = None;
+        let seed = None;
+        let key_map_path = None;
+        let profile = None;
 
         Params {
             app_mode,
@@ -52,6 +88,21 @@ impl Default for Params {
             standard,
             debug,
             worker,
+            output_policy,
+            allow_nested_output,
+            resume,
+            incremental,
+            prune,
+            chunk_size,
+            chunk_threshold,
+            report_path,
+            audit_db,
+            audit_sinks,
+            audit_max_failure_reasons,
+            vault_path,
+            seed,
+            key_map_path,
+            profile,
         }
     }
 }
@@ -0,0 +1,18 @@
+use serde::Serialize;
+
+use crate::models::{metrics::Metrics, params::Params};
+
+/// The machine readable summary of a run, written with --report
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub audit_id: i64,
+    pub user: String,
+    pub hostname: String,
+    pub successed: bool,
+    pub cancelled: bool,
+    pub process_failure_reason: Option<String>,
+    pub elapsed_time: String,
+    /// the runtime params, without the key
+    pub params: Params,
+    pub metrics: Metrics,
+}
@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+
+use crate::models::metrics::KAnonymityReport;
+use crate::strategy::date::{ParsedDate, DEFAULT_DATE_FORMAT};
+use crate::strategy::numeric::{bucket, ParsedNumber};
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The suppressed value of a quasi identifier
+pub const SUPPRESSED: &str = "*";
+
+/// The bucket sizes of the numbers, by generalization level
+const NUMBER_BUCKETS: &[u64] = &[5, 10, 20, 50, 100, 1000];
+
+/// What the k-anonymity mode does to the records of the classes smaller than k
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Enforcement {
+    /// report the achieved k and the records at risk, the output is the input
+    #[default]
+    Report,
+    /// generalize the quasi identifiers until k is reached, and suppress the records left
+    Generalize,
+    /// suppress the quasi identifiers of the records at risk
+    Suppress,
+}
+
+/// The k-anonymity of the csv files over their quasi identifiers, the combination of columns
+/// which can identify a person once joined with another dataset, e.g. zip, birth date and gender
+///
+/// ```yaml
+/// k_anonymity:
+///   k: 5
+///   quasi_identifiers: [zip, birth_date, gender]
+///   enforce: generalize
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KAnonymityConfig {
+    pub k: usize,
+    pub quasi_identifiers: Vec<String>,
+    #[serde(default)]
+    pub enforce: Enforcement,
+}
+
+impl KAnonymityConfig {
+    pub fn validate(&self) -> Result<(), MedError> {
+        match (self.k, self.quasi_identifiers.is_empty()) {
+            (0, _) | (_, true) => Err(MedError {
+                message: Some("the k-anonymity needs a k and quasi identifiers".to_string()),
+                cause: Some(
+                    "set k_anonymity.k to 2 or more, and the quasi_identifiers".to_string(),
+                ),
+                error_type: MedErrorType::ConfigError,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The generalization hierarchy of a quasi identifier, inferred from its values, the last level
+/// suppresses the value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hierarchy {
+    /// buckets of 5, 10, 20, 50, 100 and 1000, the codes of 4 digits or more
+    /// of the same length are text
+    Number,
+    /// `YYYY-MM-DD` dates, to the month, the year and the decade
+    Date,
+    /// the trailing characters are replaced by `*`, `75011` to `7501*`, `750**`
+    Text { len: usize },
+}
+
+impl Hierarchy {
+    /// Returns the hierarchy of the values, the empty values are ignored
+    pub fn infer(values: &[&str]) -> Self {
+        let values: Vec<&str> = values.iter().copied().filter(|v| !v.is_empty()).collect();
+        // the codes of 4 digits or more of the same length, e.g. the zip codes, are text
+        let len = values.first().map(|v| v.len()).unwrap_or(0);
+        let codes = len >= 4
+            && values
+                .iter()
+                .all(|v| v.len() == len && v.chars().all(|c| c.is_ascii_digit()));
+        if !values.is_empty() && !codes && values.iter().all(|v| ParsedNumber::parse(v).is_ok()) {
+            return Hierarchy::Number;
+        }
+        if !values.is_empty()
+            && values
+                .iter()
+                .all(|v| ParsedDate::parse(v, DEFAULT_DATE_FORMAT).is_ok())
+        {
+            return Hierarchy::Date;
+        }
+        Hierarchy::Text {
+            len: values.iter().map(|v| v.chars().count()).max().unwrap_or(0),
+        }
+    }
+
+    /// Returns the number of levels above the value itself, the last one suppresses it
+    pub fn levels(&self) -> usize {
+        match self {
+            Hierarchy::Number => NUMBER_BUCKETS.len() + 1,
+            Hierarchy::Date => 4,
+            Hierarchy::Text { len } => (*len).max(1),
+        }
+    }
+
+    /// Returns the value generalized to the level, the empty values are kept empty
+    pub fn generalize(&self, value: &str, level: usize) -> String {
+        if level == 0 || value.is_empty() {
+            return value.to_string();
+        }
+        if level >= self.levels() {
+            return SUPPRESSED.to_string();
+        }
+        match self {
+            Hierarchy::Number => match ParsedNumber::parse(value) {
+                Ok(number) => bucket(&number, NUMBER_BUCKETS[level - 1]),
+                Err(_) => SUPPRESSED.to_string(),
+            },
+            Hierarchy::Date => match ParsedDate::parse(value, DEFAULT_DATE_FORMAT) {
+                Ok(date) => {
+                    let date = date.date();
+                    match level {
+                        1 => date.format("%Y-%m").to_string(),
+                        2 => date.format("%Y").to_string(),
+                        _ => {
+                            let decade = date.year().div_euclid(10) * 10;
+                            format!("{}-{}", decade, decade + 9)
+                        }
+                    }
+                }
+                Err(_) => SUPPRESSED.to_string(),
+            },
+            Hierarchy::Text { .. } => {
+                let chars: Vec<char> = value.chars().collect();
+                let kept = chars.len().saturating_sub(level);
+                match kept {
+                    0 => SUPPRESSED.to_string(),
+                    _ => format!(
+                        "{}{}",
+                        chars[..kept].iter().collect::<String>(),
+                        "*".repeat(chars.len() - kept)
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Returns the name of the level, for the report
+    pub fn label(&self, level: usize) -> String {
+        match (self, level) {
+            (_, 0) => "none".to_string(),
+            (_, level) if level >= self.levels() => "suppressed".to_string(),
+            (Hierarchy::Number, level) => format!("bucket of {}", NUMBER_BUCKETS[level - 1]),
+            (Hierarchy::Date, 1) => "month".to_string(),
+            (Hierarchy::Date, 2) => "year".to_string(),
+            (Hierarchy::Date, _) => "decade".to_string(),
+            (Hierarchy::Text { .. }, level) => format!("{} trailing characters", level),
+        }
+    }
+}
+
+/// Returns the size of the equivalence class of each record, the records with the same quasi
+/// identifiers
+fn class_sizes(records: &[Vec<String>]) -> Vec<usize> {
+    let mut sizes: HashMap<&[String], usize> = HashMap::new();
+    records
+        .iter()
+        .for_each(|record| *sizes.entry(record.as_slice()).or_default() += 1);
+    records
+        .iter()
+        .map(|record| sizes[record.as_slice()])
+        .collect()
+}
+
+/// Returns true if the quasi identifiers of the record are all suppressed
+fn is_suppressed(record: &[String]) -> bool {
+    record.iter().all(|value| value == SUPPRESSED)
+}
+
+/// Returns the quasi identifiers of the records, generalized or suppressed by the enforcement,
+/// and the report of their k-anonymity
+///
+/// The generalization is global, a quasi identifier is generalized to the same level in every
+/// record, the quasi identifier of the most distinct values first, until at most k records are
+/// left in the classes smaller than k, so a few outliers do not generalize the whole file. The
+/// records left are suppressed, the fully suppressed records do not count in the achieved k.
+///
+/// # Arguments
+///
+/// * `names` - the quasi identifiers
+/// * `records` - the values of the quasi identifiers of each record
+/// * `indexes` - the index of each record in the file, for the report
+pub fn k_anonymize(
+    config: &KAnonymityConfig,
+    names: &[String],
+    records: Vec<Vec<String>>,
+    indexes: &[u64],
+) -> (Vec<Vec<String>>, KAnonymityReport) {
+    let k = config.k;
+    let input_sizes = class_sizes(&records);
+    let mut report = KAnonymityReport {
+        k,
+        input_k: input_sizes.iter().copied().min().unwrap_or(0),
+        at_risk_records: input_sizes
+            .iter()
+            .zip(indexes)
+            .filter(|(size, _)| **size < k)
+            .map(|(_, index)| *index)
+            .collect(),
+        ..Default::default()
+    };
+
+    let hierarchies: Vec<Hierarchy> = (0..names.len())
+        .map(|i| {
+            let values: Vec<&str> = records.iter().map(|record| record[i].as_str()).collect();
+            Hierarchy::infer(&values)
+        })
+        .collect();
+    let mut levels = vec![0; names.len()];
+    let generalize = |levels: &[usize]| -> Vec<Vec<String>> {
+        records
+            .iter()
+            .map(|record| {
+                record
+                    .iter()
+                    .zip(&hierarchies)
+                    .zip(levels)
+                    .map(|((value, hierarchy), level)| hierarchy.generalize(value, *level))
+                    .collect()
+            })
+            .collect()
+    };
+
+    let mut output = match config.enforce {
+        Enforcement::Report => records.clone(),
+        Enforcement::Suppress => records.clone(),
+        Enforcement::Generalize => loop {
+            let output = generalize(&levels);
+            let at_risk = class_sizes(&output)
+                .iter()
+                .filter(|size| **size < k)
+                .count();
+            if at_risk <= k {
+                break output;
+            }
+            // the quasi identifier of the most distinct values is generalized first
+            let next = (0..names.len())
+                .filter(|i| levels[*i] < hierarchies[*i].levels())
+                .max_by_key(|i| {
+                    output
+                        .iter()
+                        .map(|record| record[*i].as_str())
+                        .collect::<HashSet<&str>>()
+                        .len()
+                });
+            match next {
+                Some(i) => levels[i] += 1,
+                None => break output,
+            }
+        },
+    };
+
+    if config.enforce != Enforcement::Report {
+        let sizes = class_sizes(&output);
+        for (record, size) in output.iter_mut().zip(sizes) {
+            if size < k {
+                record
+                    .iter_mut()
+                    .for_each(|value| *value = SUPPRESSED.to_string());
+                report.suppressed_records += 1;
+            }
+        }
+    }
+
+    let kept: Vec<Vec<String>> = output
+        .iter()
+        .filter(|record| !is_suppressed(record))
+        .cloned()
+        .collect();
+    report.output_k = class_sizes(&kept).into_iter().min().unwrap_or(0);
+    report.classes = kept.iter().collect::<HashSet<_>>().len();
+    report.generalization = names
+        .iter()
+        .zip(&hierarchies)
+        .zip(&levels)
+        .map(|((name, hierarchy), level)| (name.to_owned(), hierarchy.label(*level)))
+        .collect::<BTreeMap<String, String>>();
+    (output, report)
+}
+
+#[cfg(test)]
+#[path = "../tests/anonymity_test.rs"]
+mod anonymity_test;
@@ -0,0 +1,361 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::rule::RecordFields;
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The condition of the records a rule applies to, a simple expression over the other fields of
+/// the record, e.g. `country == "DE" and consent == false`
+///
+/// The operands are the field names, the csv columns or the json sibling keys, the json paths
+/// from the record root, `$.customer.country`, and the literals, `"DE"`, `42`, `true`, `false`
+/// and `null`, the missing and empty fields are null. The values are compared as numbers when
+/// both are numbers, otherwise as strings, with `==`, `!=`, `<`, `<=`, `>` and `>=`, and the
+/// comparisons are combined with `and`, `or`, `not` and the parentheses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Op, Operand),
+    /// the field is set, neither null, `false` nor `0`
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Field(String),
+    Literal(String),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Condition {
+    /// Returns the condition of the expression
+    pub fn parse(source: &str) -> Result<Self, MedError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            source,
+            tokens,
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(Condition {
+                source: source.to_string(),
+                expr,
+            }),
+            Some(token) => Err(parser.error(&format!("unexpected {:?}", token))),
+        }
+    }
+
+    /// Returns true if the record matches the condition
+    pub fn matches(&self, record: &dyn RecordFields) -> bool {
+        self.expr.eval(record)
+    }
+
+    /// Returns the fields of the condition, the json paths start with `$.`
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields = Vec::new();
+        self.expr.fields(&mut fields);
+        fields
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = MedError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        Condition::parse(&source)
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        condition.source
+    }
+}
+
+impl Expr {
+    fn eval(&self, record: &dyn RecordFields) -> bool {
+        match self {
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.eval(record)),
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.eval(record)),
+            Expr::Not(expr) => !expr.eval(record),
+            Expr::Compare(left, op, right) => {
+                compare(left.resolve(record), *op, right.resolve(record))
+            }
+            Expr::Truthy(operand) => match operand.resolve(record) {
+                Some(value) => !value.eq_ignore_ascii_case("false") && value != "0",
+                None => false,
+            },
+        }
+    }
+
+    fn fields<'a>(&'a self, fields: &mut Vec<&'a str>) {
+        match self {
+            Expr::Or(exprs) | Expr::And(exprs) => exprs.iter().for_each(|expr| expr.fields(fields)),
+            Expr::Not(expr) => expr.fields(fields),
+            Expr::Compare(left, _, right) => {
+                left.field(fields);
+                right.field(fields);
+            }
+            Expr::Truthy(operand) => operand.field(fields),
+        }
+    }
+}
+
+impl Operand {
+    /// Privite function returns the value of the operand, None if null or empty
+    fn resolve(&self, record: &dyn RecordFields) -> Option<String> {
+        match self {
+            Operand::Field(name) => record.field(name).filter(|value| !value.is_empty()),
+            Operand::Literal(value) => Some(value.to_owned()),
+            Operand::Null => None,
+        }
+    }
+
+    fn field<'a>(&'a self, fields: &mut Vec<&'a str>) {
+        if let Operand::Field(name) = self {
+            if !fields.contains(&name.as_str()) {
+                fields.push(name);
+            }
+        }
+    }
+}
+
+/// Returns the comparison of the values, as numbers when both are numbers, the null values are
+/// only equal to null
+fn compare(left: Option<String>, op: Op, right: Option<String>) -> bool {
+    let ordering = match (&left, &right) {
+        (Some(left), Some(right)) => match (left.parse::<f64>(), right.parse::<f64>()) {
+            (Ok(left), Ok(right)) => left.partial_cmp(&right),
+            _ => Some(left.cmp(right)),
+        },
+        (None, None) => Some(Ordering::Equal),
+        _ => None,
+    };
+    match (op, ordering) {
+        (Op::Eq, ordering) => ordering == Some(Ordering::Equal),
+        (Op::Ne, ordering) => ordering != Some(Ordering::Equal),
+        // the null values are not ordered
+        (_, _) if left.is_none() || right.is_none() => false,
+        (Op::Lt, Some(ordering)) => ordering == Ordering::Less,
+        (Op::Le, Some(ordering)) => ordering != Ordering::Greater,
+        (Op::Gt, Some(ordering)) => ordering == Ordering::Greater,
+        (Op::Ge, Some(ordering)) => ordering != Ordering::Less,
+        (_, None) => false,
+    }
+}
+
+fn condition_error(source: &str, cause: &str) -> MedError {
+    MedError {
+        message: Some(format!("invalid condition {:?}", source)),
+        cause: Some(cause.to_string()),
+        error_type: MedErrorType::ConfigError,
+    }
+}
+
+/// Privite function returns the tokens of the expression
+fn tokenize(source: &str) -> Result<Vec<Token>, MedError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    _ => Token::Close,
+                });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some('\\') => value.extend(chars.next()),
+                        Some(next) => value.push(next),
+                        None => return Err(condition_error(source, "unterminated string")),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            '=' | '!' | '<' | '>' | '&' | '|' => {
+                chars.next();
+                let next = chars.peek().copied();
+                let token = match (c, next) {
+                    ('=', Some('=')) => Token::Op(Op::Eq),
+                    ('!', Some('=')) => Token::Op(Op::Ne),
+                    ('<', Some('=')) => Token::Op(Op::Le),
+                    ('>', Some('=')) => Token::Op(Op::Ge),
+                    ('&', Some('&')) => Token::And,
+                    ('|', Some('|')) => Token::Or,
+                    ('<', _) => Token::Op(Op::Lt),
+                    ('>', _) => Token::Op(Op::Gt),
+                    ('!', _) => Token::Not,
+                    _ => return Err(condition_error(source, &format!("unexpected {:?}", c))),
+                };
+                // the two characters operators
+                if !matches!(token, Token::Op(Op::Lt) | Token::Op(Op::Gt) | Token::Not) {
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next.is_alphanumeric() || "_-.$[]".contains(next) {
+                        word.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if word.is_empty() {
+                    return Err(condition_error(source, &format!("unexpected {:?}", c)));
+                }
+                tokens.push(match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Ident(word),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// The recursive descent parser of the tokens, `or` binds looser than `and`, than `not`
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Expr, MedError> {
+        let mut exprs = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            exprs.push(self.and()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::Or(exprs),
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, MedError> {
+        let mut exprs = vec![self.unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            exprs.push(self.unary()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::And(exprs),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, MedError> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Open) => {
+                self.pos += 1;
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(self.error("missing )")),
+                }
+            }
+            _ => {
+                let left = self.operand()?;
+                match self.peek() {
+                    Some(Token::Op(op)) => {
+                        let op = *op;
+                        self.pos += 1;
+                        Ok(Expr::Compare(left, op, self.operand()?))
+                    }
+                    _ => Ok(Expr::Truthy(left)),
+                }
+            }
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, MedError> {
+        match self.next() {
+            Some(Token::Str(value)) => Ok(Operand::Literal(value)),
+            Some(Token::Ident(word)) => Ok(match word.as_str() {
+                "null" => Operand::Null,
+                "true" | "false" => Operand::Literal(word),
+                _ if word.parse::<f64>().is_ok() => Operand::Literal(word),
+                _ => Operand::Field(word),
+            }),
+            Some(token) => Err(self.error(&format!("unexpected {:?}", token))),
+            None => Err(self.error("missing operand")),
+        }
+    }
+
+    fn error(&self, cause: &str) -> MedError {
+        condition_error(self.source, cause)
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/condition_test.rs"]
+mod condition_test;
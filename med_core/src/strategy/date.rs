@@ -0,0 +1,120 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::mask_key::MaskKey;
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The date format of the values by default
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The unit a date is truncated to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateUnit {
+    Month,
+    Year,
+}
+
+/// A date, or a date and time, parsed with the input format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsedDate {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+}
+
+impl ParsedDate {
+    /// Returns the date of the value in the `chrono` format
+    pub fn parse(value: &str, format: &str) -> Result<Self, MedError> {
+        NaiveDateTime::parse_from_str(value, format)
+            .map(ParsedDate::DateTime)
+            .or_else(|_| NaiveDate::parse_from_str(value, format).map(ParsedDate::Date))
+            .map_err(|err| MedError {
+                message: Some(format!(
+                    "the value is not a date of the format {:?}",
+                    format
+                )),
+                cause: Some(err.to_string()),
+                error_type: MedErrorType::ConfigError,
+            })
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            ParsedDate::Date(date) => *date,
+            ParsedDate::DateTime(datetime) => datetime.date(),
+        }
+    }
+
+    /// Returns the date formatted, the time of the day is kept
+    pub fn format(&self, format: &str) -> String {
+        match self {
+            ParsedDate::Date(date) => date.format(format).to_string(),
+            ParsedDate::DateTime(datetime) => datetime.format(format).to_string(),
+        }
+    }
+
+    pub fn shift(&self, days: i64) -> Self {
+        match self {
+            ParsedDate::Date(date) => ParsedDate::Date(*date + Duration::days(days)),
+            ParsedDate::DateTime(datetime) => {
+                ParsedDate::DateTime(*datetime + Duration::days(days))
+            }
+        }
+    }
+
+    /// Returns the first day of the month or year of the date, at midnight
+    pub fn truncate(&self, unit: DateUnit) -> Self {
+        let date = self.date();
+        let truncated = match unit {
+            DateUnit::Month => date.with_day(1),
+            DateUnit::Year => date.with_day(1).and_then(|date| date.with_month(1)),
+        }
+        .unwrap_or(date);
+        match self {
+            ParsedDate::Date(_) => ParsedDate::Date(truncated),
+            ParsedDate::DateTime(_) => {
+                ParsedDate::DateTime(truncated.and_hms_opt(0, 0, 0).unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// Returns the shift in days, between `-max_days` and `max_days` but never 0, of the entity, the
+/// same entity is shifted by the same days with the same mask key and seed, so its intervals are
+/// kept, the seed is reused by the resumed and incremental runs
+pub fn shift_days(key: &MaskKey, seed: u64, entity: &str, max_days: u32) -> i64 {
+    let max_days = max_days.max(1) as u64;
+    let hash = key.mac(&[&seed.to_be_bytes(), entity.as_bytes()]);
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    let days = (u64::from_be_bytes(bytes) % (2 * max_days)) as i64;
+    match days < max_days as i64 {
+        true => days - max_days as i64,
+        false => days - max_days as i64 + 1,
+    }
+}
+
+/// Returns the age bucket, `30-39` for buckets of 10, of the date of birth at the day, the ages
+/// from `max` are bucketed as `90+`
+pub fn age_bucket(birth: NaiveDate, today: NaiveDate, size: u32, max: Option<u32>) -> String {
+    let size = size.max(1);
+    let mut age = today.year() - birth.year();
+    if (today.month(), today.day()) < (birth.month(), birth.day()) {
+        age -= 1;
+    }
+    let age = age.max(0) as u32;
+    match max {
+        Some(max) if age >= max => format!("{}+", max),
+        _ => {
+            let start = age / size * size;
+            match size {
+                1 => start.to_string(),
+                _ => format!("{}-{}", start, start + size - 1),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/date_test.rs"]
+mod date_test;
@@ -0,0 +1,285 @@
+use std::fmt;
+
+use chrono::{Duration, NaiveDate};
+use rand::{seq::SliceRandom, Rng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::mask_key::MaskKey;
+
+const FIRST_NAMES: &[&str] = &[
+    "James",
+    "Mary",
+    "Robert",
+    "Patricia",
+    "John",
+    "Jennifer",
+    "Michael",
+    "Linda",
+    "David",
+    "Elizabeth",
+    "William",
+    "Barbara",
+    "Richard",
+    "Susan",
+    "Joseph",
+    "Jessica",
+    "Thomas",
+    "Sarah",
+    "Charles",
+    "Karen",
+    "Daniel",
+    "Lisa",
+    "Matthew",
+    "Nancy",
+    "Anthony",
+    "Sandra",
+    "Mark",
+    "Ashley",
+    "Paul",
+    "Emily",
+    "Steven",
+    "Donna",
+    "Andrew",
+    "Michelle",
+    "Joshua",
+    "Carol",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Smith",
+    "Johnson",
+    "Williams",
+    "Brown",
+    "Jones",
+    "Garcia",
+    "Miller",
+    "Davis",
+    "Rodriguez",
+    "Martinez",
+    "Hernandez",
+    "Lopez",
+    "Wilson",
+    "Anderson",
+    "Thomas",
+    "Taylor",
+    "Moore",
+    "Jackson",
+    "Martin",
+    "Lee",
+    "Thompson",
+    "White",
+    "Harris",
+    "Clark",
+    "Lewis",
+    "Robinson",
+    "Walker",
+    "Young",
+    "Allen",
+    "King",
+    "Wright",
+    "Scott",
+    "Hill",
+    "Green",
+    "Adams",
+    "Baker",
+];
+
+const STREETS: &[&str] = &[
+    "Maple",
+    "Oak",
+    "Pine",
+    "Cedar",
+    "Elm",
+    "Washington",
+    "Lake",
+    "Hill",
+    "Park",
+    "Main",
+    "Church",
+    "Highland",
+    "Sunset",
+    "River",
+    "Meadow",
+    "Forest",
+    "Spring",
+    "Willow",
+];
+
+const STREET_SUFFIXES: &[&str] = &["Street", "Avenue", "Road", "Lane", "Drive", "Court", "Way"];
+
+const CITIES: &[&str] = &[
+    "Springfield",
+    "Riverside",
+    "Fairview",
+    "Franklin",
+    "Greenville",
+    "Bristol",
+    "Clinton",
+    "Georgetown",
+    "Salem",
+    "Madison",
+    "Arlington",
+    "Ashland",
+];
+
+const COMPANY_SUFFIXES: &[&str] = &[
+    "Holdings",
+    "Group",
+    "Partners",
+    "Industries",
+    "Labs",
+    "Systems",
+    "Logistics",
+    "Consulting",
+    "& Co",
+    "Ltd",
+];
+
+/// The reserved domains, the fake emails are never delivered
+const EMAIL_DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+/// The kind of fake value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FakeKind {
+    FirstName,
+    LastName,
+    FullName,
+    Email,
+    /// every digit of the original phone is replaced, its format is kept
+    Phone,
+    Address,
+    Company,
+    /// a date between 1940 and 2010, `YYYY-MM-DD`
+    Date,
+    Uuid,
+    /// a german IBAN with valid check digits
+    Iban,
+}
+
+impl fmt::Display for FakeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FakeKind::FirstName => write!(f, "first_name"),
+            FakeKind::LastName => write!(f, "last_name"),
+            FakeKind::FullName => write!(f, "full_name"),
+            FakeKind::Email => write!(f, "email"),
+            FakeKind::Phone => write!(f, "phone"),
+            FakeKind::Address => write!(f, "address"),
+            FakeKind::Company => write!(f, "company"),
+            FakeKind::Date => write!(f, "date"),
+            FakeKind::Uuid => write!(f, "uuid"),
+            FakeKind::Iban => write!(f, "iban"),
+        }
+    }
+}
+
+impl FakeKind {
+    /// Returns the fake value of the original value, seeded by the HMAC of the value keyed with
+    /// the mask key, the same value gets the same fake value of the kind, in every file and run
+    /// of the same key, the empty values are kept empty
+    pub fn fake(&self, value: &str, key: &MaskKey) -> String {
+        if value.is_empty() {
+            return String::new();
+        }
+        let mut rng = key.rng(&[self.to_string().as_bytes(), value.as_bytes()]);
+        match self {
+            FakeKind::FirstName => pick(&mut rng, FIRST_NAMES).to_string(),
+            FakeKind::LastName => pick(&mut rng, LAST_NAMES).to_string(),
+            FakeKind::FullName => format!(
+                "{} {}",
+                pick(&mut rng, FIRST_NAMES),
+                pick(&mut rng, LAST_NAMES)
+            ),
+            FakeKind::Email => format!(
+                "{}.{}{}@{}",
+                pick(&mut rng, FIRST_NAMES).to_lowercase(),
+                pick(&mut rng, LAST_NAMES).to_lowercase(),
+                rng.gen_range(1..100),
+                pick(&mut rng, EMAIL_DOMAINS)
+            ),
+            FakeKind::Phone => fake_phone(&mut rng, value),
+            FakeKind::Address => format!(
+                "{} {} {}, {}",
+                rng.gen_range(1..10000),
+                pick(&mut rng, STREETS),
+                pick(&mut rng, STREET_SUFFIXES),
+                pick(&mut rng, CITIES)
+            ),
+            FakeKind::Company => format!(
+                "{} {}",
+                pick(&mut rng, LAST_NAMES),
+                pick(&mut rng, COMPANY_SUFFIXES)
+            ),
+            FakeKind::Date => {
+                let start = NaiveDate::from_ymd_opt(1940, 1, 1).unwrap_or_default();
+                (start + Duration::days(rng.gen_range(0..365 * 70)))
+                    .format("%Y-%m-%d")
+                    .to_string()
+            }
+            FakeKind::Uuid => fake_uuid(&mut rng),
+            FakeKind::Iban => fake_iban(&mut rng),
+        }
+    }
+}
+
+fn pick<'a>(rng: &mut ChaCha20Rng, values: &[&'a str]) -> &'a str {
+    values.choose(rng).copied().unwrap_or_default()
+}
+
+/// Replace every digit of the phone, keeping its format
+fn fake_phone(rng: &mut ChaCha20Rng, value: &str) -> String {
+    if !value.chars().any(|c| c.is_ascii_digit()) {
+        return format!(
+            "+1-555-{:03}-{:04}",
+            rng.gen_range(100..1000),
+            rng.gen_range(0..10000)
+        );
+    }
+    value
+        .chars()
+        .map(|c| match c.is_ascii_digit() {
+            true => char::from(b'0' + rng.gen_range(0..10u8)),
+            false => c,
+        })
+        .collect()
+}
+
+/// Returns a random version 4 uuid
+fn fake_uuid(rng: &mut ChaCha20Rng) -> String {
+    let mut bytes: [u8; 16] = rng.gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Returns a german IBAN, `DE`, the check digits and 18 random digits
+fn fake_iban(rng: &mut ChaCha20Rng) -> String {
+    let bban: String = (0..18)
+        .map(|_| char::from(b'0' + rng.gen_range(0..10u8)))
+        .collect();
+    // the check digits of ISO 13616, `DE` is 1314
+    let check = 98 - mod97(&format!("{}131400", bban));
+    format!("DE{:02}{}", check, bban)
+}
+
+/// Returns the remainder of the decimal digits by 97
+pub(crate) fn mod97(digits: &str) -> u32 {
+    digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .fold(0, |acc, d| (acc * 10 + d) % 97)
+}
+
+#[cfg(test)]
+#[path = "../tests/fake_test.rs"]
+mod fake_test;
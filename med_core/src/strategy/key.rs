@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::strategy::mask_key::MaskKey;
+use crate::utils::error::{MedError, MedErrorType};
+use crate::utils::helpers::{commit_output, discard_output, temp_output_path};
+
+/// The surrogates tried for a value before the key space of its format is exhausted
+const MAX_ATTEMPTS: u32 = 64;
+
+/// The surrogates of the key values, by domain and by the keyed hash of the value
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyMapState {
+    pub domains: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// The run-wide mapping of the key values to their surrogates, shared by the worker threads, the
+/// same value of a domain gets the same surrogate in every file, so the masked files still join
+/// on their keys
+///
+/// The mapping is kept in memory for the run, or loaded from and saved to a json file to keep
+/// the surrogates between runs, the values are stored by their HMAC keyed with the mask key, never
+/// in clear, so they can not be found by hashing the candidate values without the key
+#[derive(Debug, Default, Clone)]
+pub struct KeyMap {
+    path: Option<PathBuf>,
+    state: Arc<RwLock<KeyMapState>>,
+    /// the surrogates given, by domain, a surrogate is never given to two values
+    used: Arc<RwLock<HashSet<(String, String)>>>,
+}
+
+impl KeyMap {
+    /// Returns the key map of the file, empty if there is none yet
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the key map json file
+    pub fn load(path: &Path) -> Result<Self, MedError> {
+        let state = match path.exists() {
+            true => serde_json::from_str::<KeyMapState>(&fs::read_to_string(path)?)?,
+            false => KeyMapState::default(),
+        };
+        let used = state
+            .domains
+            .iter()
+            .flat_map(|(domain, keys)| {
+                keys.values()
+                    .map(|surrogate| (domain.to_owned(), surrogate.to_owned()))
+            })
+            .collect();
+        debug!(
+            "key map {} loaded {} domains",
+            path.display(),
+            state.domains.len()
+        );
+        Ok(KeyMap {
+            path: Some(path.to_path_buf()),
+            state: Arc::new(RwLock::new(state)),
+            used: Arc::new(RwLock::new(used)),
+        })
+    }
+
+    /// Write the key map to its file, if it was loaded from one
+    pub fn save(&self) -> Result<(), MedError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let temp_path = temp_output_path(path);
+        let result = File::create(&temp_path)
+            .map_err(MedError::from)
+            .and_then(|mut file| {
+                let state = self.state.read().unwrap();
+                file.write_all(serde_json::to_string_pretty(&*state)?.as_bytes())?;
+                commit_output(file, &temp_path, path)
+            });
+        if result.is_err() {
+            discard_output(&temp_path);
+        }
+        result
+    }
+
+    /// Returns the surrogate of the value in the domain, a new surrogate of the same format if
+    /// the value has none yet, the empty values are kept empty
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - the fields sharing the surrogates, e.g. `customer` for `customers.id` and
+    ///   `orders.customer_id`
+    /// * `value` - the key value
+    /// * `key` - the mask key of the lookups and the surrogates
+    /// * `seed` - the seed of the run
+    pub fn surrogate(
+        &self,
+        domain: &str,
+        value: &str,
+        key: &MaskKey,
+        seed: u64,
+    ) -> Result<String, MedError> {
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+        let lookup = hex::encode(key.mac(&[domain.as_bytes(), value.as_bytes()]));
+        if let Some(surrogate) = self.get(domain, &lookup) {
+            return Ok(surrogate);
+        }
+        // another worker may give the value a surrogate in the meantime
+        let mut state = self.state.write().unwrap();
+        let keys = state.domains.entry(domain.to_owned()).or_default();
+        if let Some(surrogate) = keys.get(&lookup) {
+            return Ok(surrogate.to_owned());
+        }
+        let mut used = self.used.write().unwrap();
+        for attempt in 0..MAX_ATTEMPTS {
+            let surrogate = format_preserving(key, seed, domain, value, attempt);
+            if used.insert((domain.to_owned(), surrogate.to_owned())) {
+                keys.insert(lookup, surrogate.to_owned());
+                return Ok(surrogate);
+            }
+        }
+        Err(MedError {
+            message: Some(format!(
+                "no surrogate left for a key of the domain {}",
+                domain
+            )),
+            cause: Some("the keys of this format are too few to be replaced".to_string()),
+            error_type: MedErrorType::ConfigError,
+        })
+    }
+
+    /// Returns the number of surrogates of the domain
+    pub fn count(&self, domain: &str) -> usize {
+        self.state
+            .read()
+            .unwrap()
+            .domains
+            .get(domain)
+            .map(|keys| keys.len())
+            .unwrap_or(0)
+    }
+
+    /// Privite function returns the surrogate of the value hash already given
+    fn get(&self, domain: &str, lookup: &str) -> Option<String> {
+        self.state
+            .read()
+            .unwrap()
+            .domains
+            .get(domain)
+            .and_then(|keys| keys.get(lookup))
+            .cloned()
+    }
+}
+
+/// Returns the value with its digits and letters replaced, keeping their case and the other
+/// characters, seeded by the HMAC of the run seed, the domain, the value and the attempt keyed
+/// with the mask key
+fn format_preserving(key: &MaskKey, seed: u64, domain: &str, value: &str, attempt: u32) -> String {
+    let mut rng = key.rng(&[
+        &seed.to_be_bytes(),
+        domain.as_bytes(),
+        value.as_bytes(),
+        &attempt.to_be_bytes(),
+    ]);
+    value
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => char::from(b'0' + rng.gen_range(0..10u8)),
+            'a'..='z' => char::from(b'a' + rng.gen_range(0..26u8)),
+            'A'..='Z' => char::from(b'A' + rng.gen_range(0..26u8)),
+            _ => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "../tests/key_test.rs"]
+mod key_test;
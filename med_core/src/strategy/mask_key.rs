@@ -0,0 +1,147 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use sha2::Sha256;
+
+use crate::utils::error::{MedError, MedErrorType};
+use crate::utils::helpers::discard_output;
+
+/// The environment variable of the mask key
+pub const MASK_KEY_ENV: &str = "MED_MASK_KEY";
+
+/// The file of the mask key in the config directory, created on first use
+pub const MASK_KEY_FILE: &str = "med_mask.key";
+
+/// The secret key of the fakes and the surrogates of the mask mode
+///
+/// They are derived from the HMAC of the value keyed with it, the same value gets the same fake
+/// with the same key, and a fake can not be linked back to its value without the key. The key
+/// is never written to the audit, the sinks or the manifest.
+#[derive(Clone)]
+pub struct MaskKey(Arc<Vec<u8>>);
+
+/// The key is a secret, never printed
+impl fmt::Debug for MaskKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MaskKey(***)")
+    }
+}
+
+/// A random key, the fakes are only the same within the run
+impl Default for MaskKey {
+    fn default() -> Self {
+        MaskKey::new(&rand::random::<[u8; 32]>())
+    }
+}
+
+impl MaskKey {
+    pub fn new(key: &[u8]) -> Self {
+        MaskKey(Arc::new(key.to_vec()))
+    }
+
+    /// Returns the HMAC-SHA256 of the parts, each followed by `\0`
+    pub fn mac(&self, parts: &[&[u8]]) -> [u8; 32] {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes a key of any size");
+        for part in parts {
+            mac.update(part);
+            mac.update(&[0]);
+        }
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Returns the random generator seeded by the HMAC of the parts
+    pub fn rng(&self, parts: &[&[u8]]) -> ChaCha20Rng {
+        ChaCha20Rng::from_seed(self.mac(parts))
+    }
+}
+
+/// Returns the mask key from, in order, the cli, the `MED_MASK_KEY` environment variable, or
+/// `med_mask.key` in the config directory, created with a random key on first use, an empty cli
+/// key is not set
+pub fn mask_key(cli: Option<&str>) -> Result<String, MedError> {
+    let env = std::env::var(MASK_KEY_ENV).ok();
+    match cli.filter(|key| !key.is_empty()).or(env.as_deref()) {
+        Some(key) if !key.is_empty() => Ok(key.to_string()),
+        Some(_) => Err(MedError {
+            message: Some("the mask key is empty".to_string()),
+            cause: Some(format!("check --key or {}", MASK_KEY_ENV)),
+            error_type: MedErrorType::ConfigError,
+        }),
+        None => match dirs::config_dir() {
+            Some(dir) => mask_key_file(&dir.join(MASK_KEY_FILE)),
+            None => Err(MedError {
+                message: Some("no config directory found for the mask key".to_string()),
+                cause: Some(format!("set the mask key with --key or {}", MASK_KEY_ENV)),
+                error_type: MedErrorType::ConfigError,
+            }),
+        },
+    }
+}
+
+/// Returns the local secret of the config hash, the mask key of the `MED_MASK_KEY` environment
+/// variable or of the config directory, never the cli key, None without a config directory
+pub fn config_secret() -> Option<String> {
+    match std::env::var(MASK_KEY_ENV) {
+        Ok(key) if !key.is_empty() => Some(key),
+        _ => dirs::config_dir().and_then(|dir| mask_key_file(&dir.join(MASK_KEY_FILE)).ok()),
+    }
+}
+
+/// Returns the key of the file, created with a random key, readable by the user only, if there
+/// is none yet
+pub fn mask_key_file(path: &Path) -> Result<String, MedError> {
+    if path.exists() {
+        return read_key(path);
+    }
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    // the key is written to a temp file of the process first, then linked to the path, so a
+    // concurrent run never reads a partial key
+    let temp_path = path.with_extension(format!("{}.med-tmp", std::process::id()));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let key = hex::encode(rand::random::<[u8; 32]>());
+    let linked = options
+        .open(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(key.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::hard_link(&temp_path, path));
+    discard_output(&temp_path);
+    match linked {
+        Ok(()) => Ok(key),
+        // created by a concurrent run
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => read_key(path),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Privite function returns the key of the file
+fn read_key(path: &Path) -> Result<String, MedError> {
+    let key = fs::read_to_string(path)?.trim().to_string();
+    match key.is_empty() {
+        true => Err(MedError {
+            message: Some(format!("the mask key file {} is empty", path.display())),
+            cause: Some(format!("set the mask key with --key or {}", MASK_KEY_ENV)),
+            error_type: MedErrorType::ConfigError,
+        }),
+        false => Ok(key),
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/mask_key_test.rs"]
+mod mask_key_test;
@@ -0,0 +1,10 @@
+pub mod anonymity;
+pub mod condition;
+pub mod date;
+pub mod fake;
+pub mod key;
+pub mod mask_key;
+pub mod numeric;
+pub mod profile;
+pub mod rule;
+pub mod subset;
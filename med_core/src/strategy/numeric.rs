@@ -0,0 +1,100 @@
+use rand::Rng;
+
+use crate::strategy::mask_key::MaskKey;
+use crate::utils::error::{MedError, MedErrorType};
+
+/// A number and its decimals, as written in the value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParsedNumber {
+    pub value: f64,
+    pub decimals: usize,
+}
+
+impl ParsedNumber {
+    pub fn parse(value: &str) -> Result<Self, MedError> {
+        let trimmed = value.trim();
+        match trimmed.parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(ParsedNumber {
+                value: number,
+                decimals: trimmed
+                    .split_once('.')
+                    .map(|(_, decimals)| {
+                        decimals.chars().take_while(|c| c.is_ascii_digit()).count()
+                    })
+                    .unwrap_or(0),
+            }),
+            _ => Err(MedError {
+                message: Some("the value is not a number".to_string()),
+                cause: Some("the numeric strategies only apply to numbers".to_string()),
+                error_type: MedErrorType::ConfigError,
+            }),
+        }
+    }
+
+    /// Returns the number with the decimals
+    pub fn format(value: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, value);
+        // no negative zero
+        match formatted
+            .trim_start_matches('-')
+            .chars()
+            .all(|c| c == '0' || c == '.')
+        {
+            true => formatted.trim_start_matches('-').to_string(),
+            false => formatted,
+        }
+    }
+}
+
+/// Returns the number with a random noise of up to `max`, or of up to `max` times the number
+/// when `relative`, the noise is seeded by the HMAC of the run seed and the number keyed with the
+/// mask key, so the run is reproducible with its key and seed, and the seed alone does not undo it
+pub fn noise(key: &MaskKey, seed: u64, number: &ParsedNumber, max: f64, relative: bool) -> String {
+    let max = match relative {
+        true => (max * number.value).abs(),
+        false => max.abs(),
+    };
+    let mut rng = key.rng(&[&seed.to_be_bytes(), &number.value.to_be_bytes()]);
+    let noise = match max > 0.0 {
+        true => rng.gen_range(-max..=max),
+        false => 0.0,
+    };
+    ParsedNumber::format(number.value + noise, number.decimals)
+}
+
+/// Returns the number rounded to the significant digits, `123456` is `120000` with 2 digits
+pub fn round_significant(number: &ParsedNumber, digits: u32) -> String {
+    if number.value == 0.0 {
+        return ParsedNumber::format(0.0, 0);
+    }
+    let digits = digits.max(1) as i32;
+    let magnitude = number.value.abs().log10().floor() as i32;
+    let scale = 10f64.powi(digits - 1 - magnitude);
+    let rounded = (number.value * scale).round() / scale;
+    let decimals = (digits - 1 - magnitude).clamp(0, number.decimals as i32) as usize;
+    ParsedNumber::format(rounded, decimals)
+}
+
+/// Returns the range of the number, `30-39` for buckets of 10
+pub fn bucket(number: &ParsedNumber, size: u64) -> String {
+    let size = size.max(1) as i64;
+    let start = (number.value.floor() as i64).div_euclid(size) * size;
+    match size {
+        1 => start.to_string(),
+        _ => format!("{}-{}", start, start + size - 1),
+    }
+}
+
+/// Returns the top of the number above the top, or the bottom of the number below the bottom,
+/// None if the number is within them
+pub fn top_coding(number: &ParsedNumber, top: Option<f64>, bottom: Option<f64>) -> Option<String> {
+    match (top, bottom) {
+        (Some(top), _) if number.value > top => Some(top.to_string()),
+        (_, Some(bottom)) if number.value < bottom => Some(bottom.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/numeric_test.rs"]
+mod numeric_test;
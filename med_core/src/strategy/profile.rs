@@ -0,0 +1,231 @@
+use std::fmt;
+
+use clap::{builder::PossibleValue, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::strategy::date::DateUnit;
+use crate::strategy::fake::FakeKind;
+use crate::strategy::rule::{FieldRule, Strategy};
+
+/// A built-in compliance profile, the masking strategies of the fields found by their name, set
+/// with `--profile` or `profile` in the job configuration
+///
+/// The field names are matched ignoring the case, `_`, `-`, `.` and spaces, `Email_Address` and
+/// `emailaddress` alike, against the patterns of the profile where `*` matches anything, the
+/// first pattern matching wins. The fields and rules of the job configuration take precedence.
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    GdprBasic,
+    Pci,
+    HipaaSafeHarbor,
+}
+
+// Can also be derived with feature flag `derive`
+impl ValueEnum for Profile {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Profile::GdprBasic, Profile::Pci, Profile::HipaaSafeHarbor]
+    }
+
+    fn to_possible_value<'a>(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Profile::GdprBasic => PossibleValue::new("gdpr-basic")
+                .help("Pseudonymize the names, contacts, addresses, national ids and birth dates"),
+            Profile::Pci => PossibleValue::new("pci")
+                .help("Mask the card numbers, security codes, PINs and expiry dates"),
+            Profile::HipaaSafeHarbor => PossibleValue::new("hipaa-safe-harbor")
+                .help("Remove the 18 identifiers of the HIPAA Safe Harbor method"),
+        })
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_possible_value()
+            .expect("no values are skipped")
+            .get_name()
+            .fmt(f)
+    }
+}
+
+impl fmt::Debug for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::str::FromStr for Profile {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for variant in Self::value_variants() {
+            if variant.to_possible_value().unwrap().matches(s, false) {
+                return Ok(*variant);
+            }
+        }
+        Err(format!("invalid variant: {}", s))
+    }
+}
+
+fn fake(kind: FakeKind) -> Strategy {
+    Strategy::Fake { kind }
+}
+
+fn year() -> Strategy {
+    Strategy::DateTruncate {
+        to: DateUnit::Year,
+        input_format: None,
+        output_format: None,
+    }
+}
+
+/// The age of the date of birth, the ages over 89 are aggregated as `90+`
+fn age() -> Strategy {
+    Strategy::AgeBucket {
+        size: 1,
+        max: Some(90),
+        input_format: None,
+    }
+}
+
+/// The patterns of the names, in every profile
+const NAMES: &[(&str, FakeKind)] = &[
+    ("*firstname*", FakeKind::FirstName),
+    ("givenname", FakeKind::FirstName),
+    ("*lastname*", FakeKind::LastName),
+    ("surname", FakeKind::LastName),
+    ("familyname", FakeKind::LastName),
+    ("name", FakeKind::FullName),
+    ("*fullname*", FakeKind::FullName),
+    ("*cardholder*", FakeKind::FullName),
+    ("*patientname*", FakeKind::FullName),
+    ("*customername*", FakeKind::FullName),
+];
+
+impl Profile {
+    /// Returns the patterns of the field names and their strategy, in order
+    pub fn patterns(&self) -> Vec<(&'static str, Strategy)> {
+        let names = NAMES.iter().map(|(pattern, kind)| (*pattern, fake(*kind)));
+        match self {
+            Profile::GdprBasic => names
+                .chain([
+                    ("*email*", fake(FakeKind::Email)),
+                    ("*phone*", fake(FakeKind::Phone)),
+                    ("*mobile*", fake(FakeKind::Phone)),
+                    ("ip", Strategy::Mask),
+                    ("*ipaddress*", Strategy::Mask),
+                    ("*address*", fake(FakeKind::Address)),
+                    ("*street*", fake(FakeKind::Address)),
+                    ("*iban*", fake(FakeKind::Iban)),
+                    ("*birthdate*", year()),
+                    ("*dateofbirth*", year()),
+                    ("*birthday*", year()),
+                    ("dob", year()),
+                    ("*nationalid*", Strategy::Mask),
+                    ("ssn*", Strategy::Mask),
+                    ("*ssn", Strategy::Mask),
+                    ("*passport*", Strategy::Mask),
+                    ("*taxid*", Strategy::Mask),
+                ])
+                .collect(),
+            Profile::Pci => vec![
+                ("*cardnumber*", Strategy::Mask),
+                ("*creditcard*", Strategy::Mask),
+                ("*ccnumber*", Strategy::Mask),
+                ("pan", Strategy::Mask),
+                ("*cvv*", Strategy::Mask),
+                ("*cvc*", Strategy::Mask),
+                ("*securitycode*", Strategy::Mask),
+                ("pin", Strategy::Mask),
+                ("*pinblock*", Strategy::Mask),
+                ("*trackdata*", Strategy::Mask),
+                ("*expir*", Strategy::Mask),
+                ("*cardholder*", fake(FakeKind::FullName)),
+            ],
+            // the identifiers are redacted, the fakes and the surrogates are derived from the
+            // values, a re-identification code the method forbids
+            Profile::HipaaSafeHarbor => NAMES
+                .iter()
+                .map(|(pattern, _)| (*pattern, Strategy::Mask))
+                .chain([
+                    ("*email*", Strategy::Mask),
+                    ("*phone*", Strategy::Mask),
+                    ("*fax*", Strategy::Mask),
+                    ("ip", Strategy::Mask),
+                    ("*ipaddress*", Strategy::Mask),
+                    ("*address*", Strategy::Mask),
+                    ("*street*", Strategy::Mask),
+                    ("city", Strategy::Mask),
+                    ("town", Strategy::Mask),
+                    ("*zip*", Strategy::Mask),
+                    ("*postalcode*", Strategy::Mask),
+                    ("*birthdate*", age()),
+                    ("*dateofbirth*", age()),
+                    ("*birthday*", age()),
+                    ("dob", age()),
+                    ("*admission*", year()),
+                    ("*discharge*", year()),
+                    ("*deathdate*", year()),
+                    ("*dateofdeath*", year()),
+                    ("ssn*", Strategy::Mask),
+                    ("*ssn", Strategy::Mask),
+                    ("*socialsecurity*", Strategy::Mask),
+                    ("*mrn*", Strategy::Mask),
+                    ("*medicalrecord*", Strategy::Mask),
+                    ("*healthplan*", Strategy::Mask),
+                    ("*beneficiary*", Strategy::Mask),
+                    ("*account*", Strategy::Mask),
+                    ("*licen*", Strategy::Mask),
+                    ("*vehicle*", Strategy::Mask),
+                    ("vin", Strategy::Mask),
+                    ("*serialnumber*", Strategy::Mask),
+                    ("*deviceid*", Strategy::Mask),
+                    ("*url", Strategy::Mask),
+                    ("*biometric*", Strategy::Mask),
+                    ("*fingerprint*", Strategy::Mask),
+                    ("*photo*", Strategy::Mask),
+                ])
+                .collect(),
+        }
+    }
+
+    /// Returns the rule of the field, of the first pattern matching its name
+    pub fn rule(&self, field: &str) -> Option<FieldRule> {
+        let name = normalize(field);
+        self.patterns()
+            .into_iter()
+            .find(|(pattern, _)| matches(pattern, &name))
+            .map(|(_, strategy)| strategy.into())
+    }
+}
+
+/// Privite function returns the name in lower case, without `_`, `-`, `.` and spaces
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-' | '.' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Privite function returns true if the name matches the pattern, `*` matches anything
+fn matches(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if parts.len() == 1 {
+        return name == first;
+    }
+    if !name.starts_with(first) || name.len() < first.len() + last.len() || !name.ends_with(last) {
+        return false;
+    }
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+#[path = "../tests/profile_test.rs"]
+mod profile_test;
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::strategy::condition::Condition;
+use crate::strategy::date::{age_bucket, shift_days, DateUnit, ParsedDate, DEFAULT_DATE_FORMAT};
+use crate::strategy::fake::FakeKind;
+use crate::strategy::key::KeyMap;
+use crate::strategy::mask_key::MaskKey;
+use crate::strategy::numeric::{bucket, noise, round_significant, top_coding, ParsedNumber};
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The masking strategy of a field in the mask mode, configured in the `rules` of the job
+/// configuration, the fields without a rule are replaced by the mask symbols
+///
+/// ```yaml
+/// rules:
+///   name:
+///     strategy: fake
+///     kind: full_name
+///   admission_date:
+///     strategy: date_shift
+///     key: patient_id
+///   customer_id:
+///     strategy: key
+///     domain: customer
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum Strategy {
+    /// replace with the mask symbols
+    Mask,
+    /// replace with a plausible fake value, see [FakeKind]
+    Fake { kind: FakeKind },
+    /// replace with a surrogate of the same format, the same value of the `domain` gets the same
+    /// surrogate in every file of the run, see [KeyMap]
+    Key { domain: String },
+    /// shift the date by up to `max_days` days, by the same days for the same `key` field value
+    /// within the run, or for the whole run without key
+    DateShift {
+        #[serde(default)]
+        key: Option<String>,
+        #[serde(default = "default_max_days")]
+        max_days: u32,
+        #[serde(default)]
+        input_format: Option<String>,
+        #[serde(default)]
+        output_format: Option<String>,
+    },
+    /// truncate the date to the first day of its month or year
+    DateTruncate {
+        to: DateUnit,
+        #[serde(default)]
+        input_format: Option<String>,
+        #[serde(default)]
+        output_format: Option<String>,
+    },
+    /// replace the date of birth by the age bucket, `30-39`, the ages from `max` by `90+`
+    AgeBucket {
+        #[serde(default = "default_bucket_size")]
+        size: u32,
+        #[serde(default)]
+        max: Option<u32>,
+        #[serde(default)]
+        input_format: Option<String>,
+    },
+    /// add a random noise of up to `max`, or of up to `max` times the number when `relative`
+    Noise {
+        max: f64,
+        #[serde(default)]
+        relative: bool,
+    },
+    /// round the number to the significant `digits`
+    Round { digits: u32 },
+    /// replace the number by its range, `30-39` for a `size` of 10
+    Bucket { size: u64 },
+    /// replace the numbers above the `top` by the top, and below the `bottom` by the bottom
+    TopCoding {
+        #[serde(default)]
+        top: Option<f64>,
+        #[serde(default)]
+        bottom: Option<f64>,
+    },
+}
+
+/// The rule of a field, its masking strategy and the condition of the records it applies to, the
+/// field of the records not matching the condition is kept
+///
+/// ```yaml
+/// rules:
+///   iban:
+///     strategy: mask
+///     when: country == "DE" or consent == false
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldRule {
+    #[serde(flatten)]
+    pub strategy: Strategy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<Condition>,
+}
+
+impl From<Strategy> for FieldRule {
+    fn from(strategy: Strategy) -> Self {
+        FieldRule {
+            strategy,
+            when: None,
+        }
+    }
+}
+
+impl FieldRule {
+    /// Returns true if the rule applies to the record, always without condition
+    pub fn applies(&self, record: &dyn RecordFields) -> bool {
+        match &self.when {
+            Some(condition) => condition.matches(record),
+            None => true,
+        }
+    }
+}
+
+fn default_max_days() -> u32 {
+    365
+}
+
+fn default_bucket_size() -> u32 {
+    10
+}
+
+/// The fields of the record a value belongs to, before it is masked
+pub trait RecordFields {
+    /// Returns the value of the field of the record
+    fn field(&self, name: &str) -> Option<String>;
+}
+
+/// A csv record and its headers
+pub struct CsvFields<'a> {
+    pub headers: &'a StringRecord,
+    pub record: &'a StringRecord,
+}
+
+impl RecordFields for CsvFields<'_> {
+    fn field(&self, name: &str) -> Option<String> {
+        self.headers
+            .iter()
+            .position(|header| header == name)
+            .and_then(|i| self.record.get(i))
+            .map(|value| value.to_string())
+    }
+}
+
+/// The sibling keys of a json value
+impl RecordFields for Map<String, Value> {
+    fn field(&self, name: &str) -> Option<String> {
+        match self.get(name)? {
+            Value::String(value) => Some(value.to_owned()),
+            Value::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+impl RecordFields for HashMap<String, String> {
+    fn field(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+/// The value has no record
+impl RecordFields for () {
+    fn field(&self, _name: &str) -> Option<String> {
+        None
+    }
+}
+
+/// The context of a masked value
+pub struct RuleContext<'a> {
+    pub mask_symbols: &'a str,
+    /// the seed of the run, see [ProcessRuntime](crate::app::processor::ProcessRuntime)
+    pub seed: u64,
+    /// the surrogates of the run, shared by the files
+    pub keys: &'a KeyMap,
+    /// the secret key of the fakes and the surrogates
+    pub mask_key: &'a MaskKey,
+    pub record: &'a dyn RecordFields,
+}
+
+impl Strategy {
+    /// Returns true if the masked value is keyed with the mask key
+    pub fn is_keyed(&self) -> bool {
+        matches!(self, Strategy::Fake { .. }) || self.is_seeded()
+    }
+
+    /// Returns true if the masked value depends on the seed of the run
+    pub fn is_seeded(&self) -> bool {
+        matches!(
+            self,
+            Strategy::Key { .. } | Strategy::DateShift { .. } | Strategy::Noise { .. }
+        )
+    }
+
+    /// Returns the masked value, the empty values are kept empty by the strategies other than
+    /// [Strategy::Mask]
+    pub fn apply(&self, value: &str, context: &RuleContext) -> Result<String, MedError> {
+        if value.is_empty() && *self != Strategy::Mask {
+            return Ok(String::new());
+        }
+        match self {
+            Strategy::Mask => Ok(context.mask_symbols.to_string()),
+            Strategy::Fake { kind } => Ok(kind.fake(value, context.mask_key)),
+            Strategy::Key { domain } => {
+                context
+                    .keys
+                    .surrogate(domain, value, context.mask_key, context.seed)
+            }
+            Strategy::DateShift {
+                key,
+                max_days,
+                input_format,
+                output_format,
+            } => {
+                let input_format = input_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+                let date = ParsedDate::parse(value, input_format)?;
+                let entity = match key {
+                    Some(key) => match context.record.field(key) {
+                        Some(entity) if !entity.is_empty() => entity,
+                        _ => {
+                            return Err(MedError {
+                                message: Some(format!("the date shift key {} is missing", key)),
+                                cause: Some(
+                                    "the dates are shifted by the value of the key field"
+                                        .to_string(),
+                                ),
+                                error_type: MedErrorType::ConfigError,
+                            })
+                        }
+                    },
+                    None => String::new(),
+                };
+                let days = shift_days(context.mask_key, context.seed, &entity, *max_days);
+                Ok(date
+                    .shift(days)
+                    .format(output_format.as_deref().unwrap_or(input_format)))
+            }
+            Strategy::DateTruncate {
+                to,
+                input_format,
+                output_format,
+            } => {
+                let input_format = input_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+                let date = ParsedDate::parse(value, input_format)?;
+                Ok(date
+                    .truncate(*to)
+                    .format(output_format.as_deref().unwrap_or(input_format)))
+            }
+            Strategy::AgeBucket {
+                size,
+                max,
+                input_format,
+            } => {
+                let input_format = input_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+                let birth = ParsedDate::parse(value, input_format)?.date();
+                Ok(age_bucket(
+                    birth,
+                    chrono::Local::now().date_naive(),
+                    *size,
+                    *max,
+                ))
+            }
+            Strategy::Noise { max, relative } => Ok(noise(
+                context.mask_key,
+                context.seed,
+                &ParsedNumber::parse(value)?,
+                *max,
+                *relative,
+            )),
+            Strategy::Round { digits } => {
+                Ok(round_significant(&ParsedNumber::parse(value)?, *digits))
+            }
+            Strategy::Bucket { size } => Ok(bucket(&ParsedNumber::parse(value)?, *size)),
+            Strategy::TopCoding { top, bottom } => {
+                Ok(top_coding(&ParsedNumber::parse(value)?, *top, *bottom)
+                    .unwrap_or_else(|| value.to_string()))
+            }
+        }
+    }
+}
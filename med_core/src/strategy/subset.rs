@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::strategy::condition::Condition;
+use crate::strategy::rule::RecordFields;
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The records and fields kept in the output, to build smaller datasets, the records are
+/// filtered on their values before they are masked
+///
+/// ```yaml
+/// subset:
+///   drop_when: country == "FR"
+///   sample: 10
+///   sample_key: customer_id
+///   limit: 1000
+///   drop_fields: [notes]
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subset {
+    /// the records matching the condition are dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_when: Option<Condition>,
+    /// the percentage of the records kept, sampled with the seed of the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<f64>,
+    /// the field the records are sampled by, the records of the same key value are kept or
+    /// dropped together in every file, by record index otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_key: Option<String>,
+    /// the maximum number of records kept per file, the first ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// the fields removed from the output, the csv columns or the json keys
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub drop_fields: Vec<String>,
+}
+
+impl Subset {
+    /// Returns true if the subset keeps every record and field
+    pub fn is_empty(&self) -> bool {
+        *self == Subset::default()
+    }
+
+    /// Returns true if the subset filters the records
+    pub fn filters_records(&self) -> bool {
+        self.drop_when.is_some() || self.sample.is_some() || self.limit.is_some()
+    }
+
+    pub fn validate(&self) -> Result<(), MedError> {
+        match self.sample {
+            Some(sample) if !(0.0..=100.0).contains(&sample) => Err(MedError {
+                message: Some(format!("the subset sample {} is not a percentage", sample)),
+                cause: Some("the sample is the percentage of records kept, 0 to 100".to_string()),
+                error_type: MedErrorType::ConfigError,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns true if the record is kept by the drop condition and the sample, the limit is
+    /// counted by the caller as the records are written
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - the record, before it is masked
+    /// * `index` - the index of the record in the file
+    /// * `seed` - the seed of the run
+    pub fn keeps(&self, record: &dyn RecordFields, index: u64, seed: u64) -> bool {
+        if let Some(condition) = &self.drop_when {
+            if condition.matches(record) {
+                return false;
+            }
+        }
+        match self.sample {
+            Some(sample) => {
+                let key = match &self.sample_key {
+                    Some(field) => record.field(field).unwrap_or_default(),
+                    None => index.to_string(),
+                };
+                sampled(seed, &key, sample)
+            }
+            None => true,
+        }
+    }
+
+    /// Returns true if the limit of the file is reached
+    pub fn limit_reached(&self, kept: usize) -> bool {
+        matches!(self.limit, Some(limit) if kept >= limit)
+    }
+}
+
+/// Returns true if the key is within the sample percentage, seeded by the run seed
+pub fn sampled(seed: u64, key: &str, sample: f64) -> bool {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_be_bytes());
+    hasher.update(key.as_bytes());
+    let hash = hasher.finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    (u64::from_be_bytes(bytes) as f64 / u64::MAX as f64) * 100.0 < sample
+}
+
+#[cfg(test)]
+#[path = "../tests/subset_test.rs"]
+mod subset_test;
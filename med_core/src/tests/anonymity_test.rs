@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    app::processor::FileProcessor,
+    models::{
+        enums::{FileType, Mode},
+        params::Params,
+    },
+    strategy::anonymity::{k_anonymize, Enforcement, Hierarchy, KAnonymityConfig},
+    utils::{config::JobConfig, error::MedErrorType},
+};
+
+fn config(k: usize, enforce: Enforcement) -> KAnonymityConfig {
+    KAnonymityConfig {
+        k,
+        quasi_identifiers: vec!["zip".to_string(), "age".to_string()],
+        enforce,
+    }
+}
+
+fn records(values: &[(&str, &str)]) -> Vec<Vec<String>> {
+    values
+        .iter()
+        .map(|(zip, age)| vec![zip.to_string(), age.to_string()])
+        .collect()
+}
+
+#[test]
+fn test_hierarchy() {
+    assert_eq!(Hierarchy::infer(&["34", "7", ""]), Hierarchy::Number);
+    assert_eq!(Hierarchy::infer(&["2020-01-31", ""]), Hierarchy::Date);
+    // the zip codes are text
+    assert_eq!(
+        Hierarchy::infer(&["75011", "01000"]),
+        Hierarchy::Text { len: 5 }
+    );
+
+    let number = Hierarchy::Number;
+    assert_eq!(number.generalize("34", 0), "34");
+    assert_eq!(number.generalize("34", 1), "30-34");
+    assert_eq!(number.generalize("34", 2), "30-39");
+    assert_eq!(number.generalize("34", number.levels()), "*");
+    assert_eq!(number.generalize("", 3), "");
+
+    let date = Hierarchy::Date;
+    assert_eq!(date.generalize("1984-03-12", 1), "1984-03");
+    assert_eq!(date.generalize("1984-03-12", 2), "1984");
+    assert_eq!(date.generalize("1984-03-12", 3), "1980-1989");
+    assert_eq!(date.generalize("1984-03-12", 4), "*");
+    assert_eq!(date.label(3), "decade");
+
+    let text = Hierarchy::Text { len: 5 };
+    assert_eq!(text.generalize("75011", 2), "750**");
+    assert_eq!(text.generalize("75011", 5), "*");
+    assert_eq!(text.label(2), "2 trailing characters");
+}
+
+#[test]
+fn test_k_anonymize_report() {
+    let input = records(&[("75011", "34"), ("75011", "34"), ("75012", "36")]);
+    let (output, report) = k_anonymize(
+        &config(2, Enforcement::Report),
+        &["zip".to_string(), "age".to_string()],
+        input.clone(),
+        &[1, 2, 3],
+    );
+    // the output is the input
+    assert_eq!(output, input);
+    assert_eq!(report.input_k, 1);
+    assert_eq!(report.output_k, 1);
+    assert_eq!(report.classes, 2);
+    assert_eq!(report.at_risk_records, vec![3]);
+    assert_eq!(report.suppressed_records, 0);
+}
+
+#[test]
+fn test_k_anonymize_enforce() {
+    let names = ["zip".to_string(), "age".to_string()];
+    let input = records(&[
+        ("75011", "31"),
+        ("75011", "34"),
+        ("75012", "36"),
+        ("75013", "38"),
+        ("69001", "72"),
+    ]);
+    let indexes = [1, 2, 3, 4, 5];
+
+    let (output, report) = k_anonymize(
+        &config(2, Enforcement::Generalize),
+        &names,
+        input.clone(),
+        &indexes,
+    );
+    assert_eq!(output[0], vec!["7501*", "30-34"]);
+    assert_eq!(output[3], vec!["7501*", "35-39"]);
+    // the outlier is suppressed, rather than generalizing every record
+    assert_eq!(output[4], vec!["*", "*"]);
+    assert_eq!(report.output_k, 2);
+    assert_eq!(report.classes, 2);
+    assert_eq!(report.suppressed_records, 1);
+    assert_eq!(
+        report.generalization,
+        BTreeMap::from([
+            ("age".to_string(), "bucket of 5".to_string()),
+            ("zip".to_string(), "1 trailing characters".to_string()),
+        ])
+    );
+
+    let (output, report) = k_anonymize(&config(2, Enforcement::Suppress), &names, input, &indexes);
+    assert!(output.iter().all(|record| record == &vec!["*", "*"]));
+    assert_eq!(report.suppressed_records, 5);
+    assert_eq!(report.output_k, 0);
+}
+
+#[tokio::test]
+async fn test_k_anonymity_mode() {
+    let job_conf: JobConfig = serde_yaml::from_str(
+        "
+mask_symbols: '#####'
+fields: []
+k_anonymity:
+  k: 2
+  quasi_identifiers: [zip, birth_date, gender]
+  enforce: generalize
+",
+    )
+    .unwrap();
+    let params = Params {
+        file_path: "../demo/data/input/kanon".to_owned(),
+        output_path: "../demo/data/output/csv/kanon".to_owned(),
+        file_type: FileType::CSV,
+        mode: Mode::KANONYMITY,
+        ..Default::default()
+    };
+    let mut processor = FileProcessor::new(params.clone(), job_conf.clone()).await;
+    let metrics = processor.run().await.unwrap();
+    assert_eq!(metrics.metadata.total_records, 12);
+    let report = metrics.files[0].k_anonymity.clone().unwrap();
+    assert_eq!(report.input_k, 1);
+    assert_eq!(report.at_risk_records, (2..=11).collect::<Vec<u64>>());
+    assert_eq!(report.output_k, 2);
+    assert_eq!(report.suppressed_records, 2);
+    assert_eq!(report.generalization["birth_date"], "decade");
+
+    let records: Vec<csv::StringRecord> =
+        csv::Reader::from_path("../demo/data/output/csv/kanon/patients.csv")
+            .unwrap()
+            .records()
+            .map(|record| record.unwrap())
+            .collect();
+    // the other columns are kept
+    assert_eq!(
+        records[0].iter().collect::<Vec<_>>(),
+        vec!["P01", "7501*", "1980-1989", "F", "asthma"]
+    );
+    assert_eq!(
+        records[10].iter().collect::<Vec<_>>(),
+        vec!["P11", "*", "*", "*", "cancer"]
+    );
+
+    // the json files are refused
+    let params = Params {
+        file_type: FileType::JSON,
+        ..params
+    };
+    let mut processor = FileProcessor::new(params, job_conf).await;
+    assert_eq!(
+        processor.run().await.unwrap_err().error_type,
+        MedErrorType::ConfigError
+    );
+}
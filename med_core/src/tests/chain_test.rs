@@ -0,0 +1,114 @@
+use crate::audit::{
+    app::Summary,
+    chain::{ChainIssueKind, GENESIS_HASH},
+    db::{AuditLocation, Database},
+};
+
+async fn sealed_db(key: Option<&str>, runs: usize) -> Database {
+    let mut db = Database::new(&AuditLocation::Memory).await.unwrap();
+    // not the key of the environment
+    db.chain_key = key.map(|k| k.to_string());
+    for run in 0..runs {
+        let summary = Summary {
+            user: format!("chain_test_{}", run),
+            successed: true,
+            ..Default::default()
+        };
+        let id = db.insert(&summary).await.unwrap();
+        db.update(id, &summary).await.unwrap();
+    }
+    db
+}
+
+#[tokio::test]
+async fn test_chain_verify() {
+    let mut db = sealed_db(None, 3).await;
+    // a run in progress is not sealed yet
+    db.insert(&Summary::default()).await.unwrap();
+
+    let report = db.verify().await.unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.sealed, 3);
+    assert_eq!(report.unsealed, 1);
+    assert_eq!(report.head_index, Some(3));
+
+    let first = sqlx::query_scalar::<_, String>("SELECT prev_hash FROM audit WHERE id = 1")
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert_eq!(first, GENESIS_HASH);
+
+    // a sealed record can not be updated
+    assert!(db.update(1, &Summary::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_chain_verify_tampered() {
+    let db = sealed_db(None, 4).await;
+    sqlx::query("UPDATE audit SET failed_records = 0, successed = TRUE WHERE id = 2")
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE audit SET user = 'someone' WHERE id = 2")
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    sqlx::query("DELETE FROM audit WHERE id = 3")
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+    let report = db.verify().await.unwrap();
+    assert!(!report.is_valid());
+    let kinds: Vec<(i64, ChainIssueKind)> = report
+        .issues
+        .iter()
+        .map(|issue| (issue.id, issue.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![(2, ChainIssueKind::Modified), (4, ChainIssueKind::Missing)]
+    );
+}
+
+#[tokio::test]
+async fn test_chain_verify_reordered() {
+    let db = sealed_db(None, 3).await;
+    // swap the chain positions of the first two records
+    for (id, index) in [(1, -1), (2, 1), (1, 2)] {
+        sqlx::query("UPDATE audit SET chain_index = ?1 WHERE id = ?2")
+            .bind(index)
+            .bind(id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+    }
+
+    let report = db.verify().await.unwrap();
+    assert!(report
+        .issues
+        .iter()
+        .any(|issue| issue.kind == ChainIssueKind::Reordered));
+}
+
+#[tokio::test]
+async fn test_chain_verify_hmac() {
+    let db = sealed_db(Some("secret"), 2).await;
+    assert!(db.verify().await.unwrap().is_valid());
+
+    // the content can not be checked without the key
+    let mut db = db;
+    db.chain_key = None;
+    let report = db.verify().await.unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.unverified, 2);
+
+    // nor with the wrong key
+    let db = db.with_chain_key(Some("guess".to_string()));
+    let report = db.verify().await.unwrap();
+    assert_eq!(report.issues.len(), 2);
+    assert!(report
+        .issues
+        .iter()
+        .all(|issue| issue.kind == ChainIssueKind::Modified));
+}
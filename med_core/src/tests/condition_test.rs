@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use crate::{
+    strategy::{
+        condition::Condition,
+        rule::{FieldRule, Strategy},
+    },
+    utils::error::MedErrorType,
+};
+
+fn record(fields: &[(&str, &str)]) -> HashMap<String, String> {
+    fields
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+fn matches(condition: &str, fields: &[(&str, &str)]) -> bool {
+    Condition::parse(condition)
+        .unwrap()
+        .matches(&record(fields))
+}
+
+#[test]
+fn test_condition_compare() {
+    assert!(matches(r#"country == "DE""#, &[("country", "DE")]));
+    assert!(!matches("country == 'DE'", &[("country", "FR")]));
+    assert!(matches("country != 'DE'", &[("country", "FR")]));
+    assert!(matches("consent == false", &[("consent", "false")]));
+    // as numbers when both are numbers
+    assert!(matches("age >= 18", &[("age", "18.0")]));
+    assert!(!matches("age < 9", &[("age", "10")]));
+    assert!(matches("since > '2020-01-01'", &[("since", "2021-06-01")]));
+    // the missing and empty fields are null
+    assert!(matches("email == null", &[("email", "")]));
+    assert!(matches("email == null", &[]));
+    assert!(!matches("age > 18", &[]));
+    assert!(matches("vip", &[("vip", "true")]));
+    assert!(!matches("vip", &[("vip", "0")]));
+}
+
+#[test]
+fn test_condition_logic() {
+    let fields = [("country", "DE"), ("consent", "true"), ("age", "15")];
+    assert!(matches("country == 'DE' and consent == true", &fields));
+    assert!(matches("country == 'FR' or age < 16", &fields));
+    assert!(!matches("not (country == 'DE' || age < 16)", &fields));
+    // and binds tighter than or
+    assert!(matches(
+        "country == 'FR' and consent == false or age < 16",
+        &fields
+    ));
+    assert!(!matches(
+        "country == 'FR' and (consent == false or age < 16)",
+        &fields
+    ));
+    assert!(matches("!(consent == false) && $.country != 'x'", &fields));
+}
+
+#[test]
+fn test_condition_invalid() {
+    for condition in [
+        "",
+        "country ==",
+        "country = 'DE'",
+        "(country == 'DE'",
+        "country == 'DE",
+        "country == 'DE' 'FR'",
+    ] {
+        assert_eq!(
+            Condition::parse(condition).unwrap_err().error_type,
+            MedErrorType::ConfigError,
+            "{}",
+            condition
+        );
+    }
+    let condition =
+        Condition::parse("$.customer.country == 'DE' and $.customer.country != age").unwrap();
+    assert_eq!(condition.fields(), vec!["$.customer.country", "age"]);
+    assert_eq!(
+        condition.to_string(),
+        "$.customer.country == 'DE' and $.customer.country != age"
+    );
+}
+
+#[test]
+fn test_field_rule_from_yaml() {
+    let rule: FieldRule =
+        serde_yaml::from_str("strategy: fake\nkind: email\nwhen: consent == false").unwrap();
+    assert!(matches!(rule.strategy, Strategy::Fake { .. }));
+    assert!(rule.applies(&record(&[("consent", "false")])));
+    assert!(!rule.applies(&record(&[("consent", "true")])));
+
+    let rule: FieldRule = serde_yaml::from_str("strategy: mask").unwrap();
+    assert_eq!(rule, FieldRule::from(Strategy::Mask));
+    assert!(rule.applies(&()));
+
+    assert!(serde_yaml::from_str::<FieldRule>("strategy: mask\nwhen: a ==").is_err());
+}
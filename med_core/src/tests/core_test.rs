@@ -1,6 +1,9 @@
 use crate::app::core::App;
-use crate::models::enums::{FileType, Mode};
+use crate::app::manifest::Manifest;
+use crate::app::processor::FileProcessor;
+use crate::models::enums::{FileType, Mode, OutputPolicy};
 use crate::models::params::Params;
+use crate::utils::config::JobConfig;
 use crate::utils::error::MedErrorType::ConfigError;
 use crate::utils::error::{MedError, MedErrorType};
 
@@ -13,12 +16,17 @@ async fn test_csv_mask_app() {
     };
 
     let mut new_app = App::new(new_params.clone()).await.unwrap();
-    assert_eq!(new_app.hostname, whoami::hostname());
+    assert_eq!(
+        new_app.hostname,
+        whoami::fallible::hostname().unwrap_or_default()
+    );
     assert_eq!(new_app.params, new_params);
     assert_eq!(new_app.user, whoami::username());
 
     let metrics = new_app.process().await.unwrap();
     assert_eq!(metrics.total_files, 0);
+    // the mask symbols need no mask key, it is never resolved
+    assert_eq!(new_app.params.key, new_params.key);
 }
 
 #[tokio::test]
@@ -98,8 +106,8 @@ async fn test_processor_run_encrypt() {
 
     let new_params = Params {
         conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
-        output_path: "../demo/data/input/csv".to_owned(),
-        file_path: "../demo/data/output/csv/demo/data/input/csv".to_owned(),
+        file_path: "../demo/data/output/csv/mask".to_owned(),
+        output_path: "../demo/data/output/csv/decrypt".to_owned(),
         file_type: FileType::CSV,
         mode: Mode::DECRYPT,
         key: Some("123".to_owned()),
@@ -143,3 +151,340 @@ async fn test_processor_run_json_mask() {
     let metrics = new_app.process().await.unwrap();
     assert_eq!(metrics.metadata.failed_records, 0);
 }
+
+#[tokio::test]
+async fn test_processor_output_relative_to_input() {
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_json.yaml".to_owned(),
+        file_path: "../demo/data/input/json".to_owned(),
+        output_path: "../demo/data/output/json/mask".to_owned(),
+        file_type: FileType::JSON,
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+
+    let mut new_app = App::new(new_params).await.unwrap();
+    new_app.process().await.unwrap();
+    assert!(std::path::Path::new("../demo/data/output/json/mask/level/generated.json").exists());
+}
+
+#[tokio::test]
+async fn test_processor_no_clobber() {
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
+        file_path: "../demo/data/input/csv/level".to_owned(),
+        output_path: "../demo/data/output/csv/no_clobber".to_owned(),
+        file_type: FileType::CSV,
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+
+    let mut new_app = App::new(new_params.clone()).await.unwrap();
+    new_app.process().await.unwrap();
+
+    let mut new_app = App::new(Params {
+        output_policy: OutputPolicy::NoClobber,
+        ..new_params
+    })
+    .await
+    .unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 0);
+    assert_eq!(metrics.skipped_files, 1);
+}
+
+#[tokio::test]
+async fn test_processor_nested_output() {
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
+        file_path: "../demo/data/input/csv".to_owned(),
+        output_path: "../demo/data/input/csv/output".to_owned(),
+        file_type: FileType::CSV,
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+
+    let mut new_app = App::new(new_params).await.unwrap();
+    new_app.process().await.unwrap();
+    assert_eq!(
+        new_app.audit.summary.process_failure_reason,
+        Some(
+            serde_json::to_string(&MedError {
+                message: Some(
+                    "output directory ../demo/data/input/csv/output is inside the input directory ../demo/data/input/csv"
+                        .to_owned()
+                ),
+                cause: Some(
+                    "use --allow-nested-output to write inside the input directory".to_owned()
+                ),
+                error_type: ConfigError,
+            })
+            .unwrap()
+        )
+    );
+    assert!(!std::path::Path::new("../demo/data/input/csv/output").exists());
+}
+
+#[tokio::test]
+async fn test_processor_resume() {
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_json.yaml".to_owned(),
+        file_path: "../demo/data/input/json".to_owned(),
+        output_path: "../demo/data/output/json/resume".to_owned(),
+        file_type: FileType::JSON,
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+
+    let mut new_app = App::new(new_params.clone()).await.unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 4);
+    let audit_id = new_app.audit.id.unwrap();
+    // the seed is recorded in the audit
+    let seed = new_app.params.seed.unwrap();
+    assert!(new_app
+        .audit
+        .summary
+        .runtime_conf
+        .contains(&format!("\"seed\":{}", seed)));
+
+    // the completed files are skipped
+    let mut new_app = App::new(Params {
+        resume: Some(audit_id),
+        ..new_params.clone()
+    })
+    .await
+    .unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 0);
+    assert_eq!(metrics.skipped_files, 4);
+    // and kept by the resumed run
+    assert_eq!(new_app.params.seed, Some(seed));
+
+    // the resumed run records the skipped files as completed as well
+    let resumed_id = new_app.audit.id.unwrap();
+    let mut new_app = App::new(Params {
+        resume: Some(resumed_id),
+        ..new_params.clone()
+    })
+    .await
+    .unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.skipped_files, 4);
+
+    // another output directory gets all the files
+    let other_output = "../demo/data/output/json/resume_other";
+    let mut new_app = App::new(Params {
+        resume: Some(audit_id),
+        output_path: other_output.to_owned(),
+        ..new_params.clone()
+    })
+    .await
+    .unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 4);
+    assert_eq!(metrics.skipped_files, 0);
+    std::fs::remove_dir_all(other_output).unwrap();
+
+    // a different configuration process all the files again
+    let mut new_app = App::new(Params {
+        resume: Some(audit_id),
+        mode: Mode::ENCRYPT,
+        key: Some("123".to_owned()),
+        ..new_params
+    })
+    .await
+    .unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 4);
+    assert_eq!(metrics.skipped_files, 0);
+}
+
+#[tokio::test]
+async fn test_processor_incremental() {
+    let dir = std::path::Path::new("../demo/data/output/incremental");
+    if dir.exists() {
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    std::fs::create_dir_all(dir.join("input")).unwrap();
+    let input = dir.join("input/random_data.csv");
+    std::fs::copy("../demo/data/input/csv/random_data.csv", &input).unwrap();
+
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
+        file_path: "../demo/data/output/incremental/input".to_owned(),
+        output_path: "../demo/data/output/incremental/output".to_owned(),
+        file_type: FileType::CSV,
+        mode: Mode::MASK,
+        incremental: true,
+        prune: true,
+        ..Default::default()
+    };
+
+    let mut new_app = App::new(new_params.clone()).await.unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 1);
+
+    let seed = Manifest::load(&dir.join("output")).unwrap().seed;
+    assert!(seed.is_some());
+
+    let mut new_app = App::new(new_params.clone()).await.unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 0);
+    assert_eq!(metrics.skipped_files, 1);
+    // the seed of the previous incremental run is reused
+    assert_eq!(new_app.params.seed, seed);
+
+    std::fs::write(&input, "name,email\nmed,med@med.com\n").unwrap();
+    let mut new_app = App::new(new_params.clone()).await.unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 1);
+    assert_eq!(metrics.metadata.total_records, 1);
+
+    std::fs::remove_file(&input).unwrap();
+    let mut new_app = App::new(new_params).await.unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.total_files, 0);
+    assert_eq!(metrics.pruned_files, 1);
+    assert!(!dir.join("output/random_data.csv").exists());
+}
+
+#[test]
+fn test_hash_config_seed() {
+    let params = Params {
+        seed: Some(1),
+        ..Default::default()
+    };
+    let reseeded = Params {
+        seed: Some(2),
+        ..Default::default()
+    };
+    // the seed does not change the output of the masked fields
+    let job_conf: JobConfig =
+        serde_yaml::from_str("mask_symbols: '#####'\nfields: [name]").unwrap();
+    assert_eq!(
+        FileProcessor::hash_config(&params, &job_conf),
+        FileProcessor::hash_config(&reseeded, &job_conf)
+    );
+    let job_conf: JobConfig = serde_yaml::from_str(
+        "
+mask_symbols: '#####'
+fields: []
+rules:
+  salary:
+    strategy: noise
+    max: 100
+",
+    )
+    .unwrap();
+    assert_ne!(
+        FileProcessor::hash_config(&params, &job_conf),
+        FileProcessor::hash_config(&reseeded, &job_conf)
+    );
+}
+
+#[test]
+fn test_hash_config_key() {
+    let job_conf: JobConfig =
+        serde_yaml::from_str("mask_symbols: '#####'\nfields: [name]").unwrap();
+    let hash = |key: &str| {
+        let params = Params {
+            mode: Mode::ENCRYPT,
+            key: Some(key.to_owned()),
+            ..Default::default()
+        };
+        FileProcessor::hash_config(&params, &job_conf)
+    };
+    // another key changes the output
+    assert_eq!(hash("123"), hash("123"));
+    assert_ne!(hash("123"), hash("456"));
+}
+
+#[tokio::test]
+async fn test_processor_cancelled() {
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
+        file_path: "../demo/data/input/csv".to_owned(),
+        output_path: "../demo/data/output/cancelled".to_owned(),
+        file_type: FileType::CSV,
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+
+    let mut new_app = App::new(new_params).await.unwrap();
+    let token = new_app.cancellation_token();
+    token.cancel();
+    let metrics = new_app.process().await.unwrap();
+    assert!(metrics.cancelled);
+    assert_eq!(metrics.total_files, 0);
+    assert!(new_app.audit.summary.cancelled);
+    assert!(!new_app.audit.summary.successed);
+}
+
+#[tokio::test]
+async fn test_processor_report() {
+    let report_path = "../demo/data/output/report/report.json";
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
+        file_path: "../demo/data/input/csv".to_owned(),
+        output_path: "../demo/data/output/report/csv".to_owned(),
+        file_type: FileType::CSV,
+        mode: Mode::MASK,
+        report_path: Some(report_path.to_owned()),
+        ..Default::default()
+    };
+
+    let mut new_app = App::new(new_params).await.unwrap();
+    let metrics = new_app.process().await.unwrap();
+    assert_eq!(metrics.files.len(), metrics.total_files);
+    let file = &metrics.files[0];
+    assert!(file.completed);
+    assert!(file.bytes_read > 0);
+    assert!(file.bytes_written > 0);
+    assert_eq!(file.fields_matched, vec!["name".to_string()]);
+
+    let audit_id = new_app.update_audit("1s".to_owned()).await.unwrap();
+    new_app.write_report(audit_id).unwrap();
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(report_path).unwrap()).unwrap();
+    assert_eq!(report["audit_id"], audit_id);
+    assert_eq!(report["params"]["key"], "****");
+    assert_eq!(
+        report["metrics"]["files"][0]["metadata"]["total_records"],
+        file.metadata.total_records
+    );
+}
+
+#[tokio::test]
+async fn test_resume_memory_audit() {
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
+        audit_db: Some(":memory:".to_owned()),
+        resume: Some(1),
+        ..Default::default()
+    };
+
+    match App::new(new_params).await {
+        Ok(_) => unimplemented!(),
+        Err(err) => assert_eq!(err.error_type, ConfigError),
+    }
+}
+
+#[tokio::test]
+async fn test_is_resumable() {
+    let new_params = Params {
+        conf_path: "../demo/conf/conf_csv.yaml".to_owned(),
+        audit_db: Some(":memory:".to_owned()),
+        ..Default::default()
+    };
+    let new_app = App::new(new_params.clone()).await.unwrap();
+    assert!(!new_app.is_resumable());
+
+    let new_app = App::new(Params {
+        audit_db: None,
+        ..new_params
+    })
+    .await
+    .unwrap();
+    assert!(new_app.is_resumable());
+}
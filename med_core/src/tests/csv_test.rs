@@ -3,10 +3,12 @@ use crate::{
         csv::{csv_fields_exist, csv_processor},
         processor::ProcessRuntime,
     },
-    models::enums::{Mode, Standard},
-    utils::{crypto::Cypher, error::MedErrorType},
+    models::enums::{Mode, RecordErrorKind, Standard},
+    strategy::mask_key::MaskKey,
+    utils::{crypto::Cypher, digest::sha256_file, error::MedErrorType, helpers::temp_output_path},
 };
 use csv::StringRecord;
+use std::path::Path;
 
 #[test]
 fn test_csv_fields_exist() {
@@ -14,8 +16,10 @@ fn test_csv_fields_exist() {
     let mut headers = StringRecord::new();
     headers.push_field("job_type");
     headers.push_field("name");
-    let index = csv_fields_exist(headers, &fields);
+    let index = csv_fields_exist(headers.clone(), &fields);
     assert_eq!(index[0], 1);
+    // no header matching is not an error, the file is written unchanged
+    assert!(csv_fields_exist(headers, &["email".to_string()]).is_empty());
 }
 
 #[test]
@@ -33,6 +37,7 @@ fn test_csv_processor_error() {
         standard: None,
         mask_symbols: Some("#####".to_string()),
         mode: Mode::MASK,
+        ..Default::default()
     };
 
     match csv_processor(
@@ -53,16 +58,64 @@ fn test_csv_processor_error() {
 
 #[tokio::test]
 async fn test_csv_processor_format_err() {
+    let files_path: &str = "../demo/data/input/format_err/csv/format_err.csv";
+    let output_path = "../demo/data/output/csv/format_err/processor_err/random_data.csv";
+
+    // the chunked files report the same errors, located in the file
+    let mut causes = Vec::new();
+    for chunk_threshold in [0, 1] {
+        let (tx_metadata, rx_metadata) = flume::unbounded();
+        let process_runtime = ProcessRuntime {
+            fields: vec!["name".to_string()],
+            cypher: None,
+            standard: None,
+            mask_symbols: Some("#####".to_string()),
+            mode: Mode::MASK,
+            chunk_size: 8,
+            chunk_threshold,
+            ..Default::default()
+        };
+
+        csv_processor(
+            tx_metadata.clone(),
+            files_path,
+            output_path,
+            process_runtime,
+        )
+        .unwrap();
+
+        // drop the channel once it done.
+        drop(tx_metadata);
+
+        rx_metadata.iter().for_each(|item| {
+            assert_eq!(item.metadata.failed_records, 1);
+            let record_error = &item.metadata.record_failed_reason[0];
+            assert_eq!(record_error.kind, RecordErrorKind::Parse);
+            assert_eq!(record_error.line, Some(2));
+            assert_eq!(record_error.record, Some(1));
+            assert_eq!(record_error.byte, Some(15));
+            assert_eq!(record_error.field, None);
+            causes.push(record_error.to_string());
+        });
+    }
+    assert_eq!(causes.len(), 2);
+    assert_eq!(causes[0], causes[1]);
+}
+
+#[tokio::test]
+async fn test_csv_processor_decrypt_error() {
     let (tx_metadata, rx_metadata) = flume::unbounded();
+    let key = "123";
     let process_runtime = ProcessRuntime {
         fields: vec!["name".to_string()],
-        cypher: None,
-        standard: None,
-        mask_symbols: Some("#####".to_string()),
-        mode: Mode::MASK,
+        cypher: Some(Cypher::new(key)),
+        standard: Some(Standard::DES64),
+        mask_symbols: None,
+        mode: Mode::DECRYPT,
+        ..Default::default()
     };
-    let files_path: &str = "../demo/data/input/format_err/csv/format_err.csv";
-    let output_path = "../demo/data/output/csv/format_err/processor_err/random_data.csv";
+    let files_path: &str = "../demo/data/input/format_err/csv/encrypt_err.csv";
+    let output_path = "../demo/data/output/csv/format_err/decrypt_err.csv";
 
     csv_processor(
         tx_metadata.clone(),
@@ -76,24 +129,128 @@ async fn test_csv_processor_format_err() {
     drop(tx_metadata);
 
     rx_metadata.iter().for_each(|item| {
-        assert_eq!(item.failed_records, 1);
+        assert_eq!(item.metadata.failed_records, 1);
+        let record_error = &item.metadata.record_failed_reason[0];
+        assert_eq!(record_error.kind, RecordErrorKind::Decrypt);
+        assert_eq!(record_error.line, Some(2));
+        assert_eq!(record_error.field, Some("name".to_string()));
     });
 }
 
 #[tokio::test]
-async fn test_csv_processor_decrypt_error() {
+async fn test_csv_processor_atomic_write_failed() {
     let (tx_metadata, rx_metadata) = flume::unbounded();
-    let key = "123";
     let process_runtime = ProcessRuntime {
         fields: vec!["name".to_string()],
-        cypher: Some(Cypher::new(key)),
-        standard: Some(Standard::DES64),
-        mask_symbols: None,
-        mode: Mode::DECRYPT,
+        cypher: None,
+        standard: None,
+        mask_symbols: Some("#####".to_string()),
+        mode: Mode::MASK,
+        ..Default::default()
     };
-    let files_path: &str = "../demo/data/input/format_err/csv/encrypt_err.csv";
-    let output_path = "../demo/data/output/csv/format_err/decrypt_err.csv";
+    let files_path: &str = "../demo/data/input/csv/random_data.csv";
+    // the output is an existing directory, the final rename will fail
+    let output_path = "../demo/data/output/csv/format_err";
 
+    match csv_processor(
+        tx_metadata.clone(),
+        files_path,
+        output_path,
+        process_runtime,
+    ) {
+        Ok(_) => unimplemented!(),
+        Err(err) => {
+            assert_eq!(err.error_type, MedErrorType::IoError);
+        }
+    }
+    assert!(!temp_output_path(Path::new(output_path)).exists());
+
+    drop(tx_metadata);
+    assert_eq!(rx_metadata.iter().count(), 0);
+}
+
+#[tokio::test]
+async fn test_csv_processor_chunked() {
+    let files_path: &str = "../demo/data/input/multiline/quoted_newline.csv";
+    let sequential_path = "../demo/data/output/csv/chunked/sequential.csv";
+    let chunked_path = "../demo/data/output/csv/chunked/chunked.csv";
+    std::fs::create_dir_all("../demo/data/output/csv/chunked").unwrap();
+
+    // the chunks of 64 bytes split the quoted new lines of the file
+    for (output_path, chunk_threshold) in [(sequential_path, 0), (chunked_path, 1)] {
+        let (tx_metadata, rx_metadata) = flume::unbounded();
+        let process_runtime = ProcessRuntime {
+            fields: vec!["name".to_string()],
+            cypher: Some(Cypher::new("123")),
+            standard: Some(Standard::AES128),
+            mode: Mode::ENCRYPT,
+            chunk_size: 64,
+            chunk_threshold,
+            ..Default::default()
+        };
+
+        csv_processor(
+            tx_metadata.clone(),
+            files_path,
+            output_path,
+            process_runtime,
+        )
+        .unwrap();
+        drop(tx_metadata);
+
+        rx_metadata.iter().for_each(|item| {
+            assert_eq!(item.metadata.total_records, 40);
+            assert_eq!(item.metadata.failed_records, 0);
+            // the whole input is hashed, chunked or not
+            assert_eq!(
+                item.input_hash,
+                Some(sha256_file(Path::new(files_path)).unwrap())
+            );
+            // the output is hashed while written
+            assert_eq!(
+                item.output_hash,
+                Some(sha256_file(Path::new(output_path)).unwrap())
+            );
+        });
+    }
+
+    let sequential = std::fs::read_to_string(sequential_path).unwrap();
+    let chunked = std::fs::read_to_string(chunked_path).unwrap();
+    assert_eq!(sequential, chunked);
+    assert_eq!(
+        csv::Reader::from_path(chunked_path)
+            .unwrap()
+            .records()
+            .count(),
+        40
+    );
+}
+
+#[tokio::test]
+async fn test_csv_date_strategies() {
+    let files_path = "../demo/data/input/dates/patients.csv";
+    let output_path = "../demo/data/output/csv/dates/patients.csv";
+    std::fs::create_dir_all("../demo/data/output/csv/dates").unwrap();
+
+    let rules = "
+admission: { strategy: date_shift, key: patient_id, max_days: 30 }
+discharge: { strategy: date_shift, key: patient_id, max_days: 30 }
+birth: { strategy: date_truncate, to: year }
+";
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let process_runtime = ProcessRuntime {
+        fields: vec![
+            "admission".to_string(),
+            "discharge".to_string(),
+            "birth".to_string(),
+        ],
+        rules: serde_yaml::from_str(rules).unwrap(),
+        seed: 42,
+        mode: Mode::MASK,
+        // the output is committed, the same with every key file
+        mask_key: MaskKey::new(b"secret"),
+        ..Default::default()
+    };
     csv_processor(
         tx_metadata.clone(),
         files_path,
@@ -101,11 +258,119 @@ async fn test_csv_processor_decrypt_error() {
         process_runtime,
     )
     .unwrap();
-
-    // drop the channel once it done.
     drop(tx_metadata);
-
     rx_metadata.iter().for_each(|item| {
-        assert_eq!(item.failed_records, 1);
+        assert_eq!(item.metadata.failed_records, 1);
+        assert_eq!(
+            item.metadata.record_failed_reason[0].kind,
+            RecordErrorKind::Mask
+        );
+        assert_eq!(
+            item.metadata.record_failed_reason[0].field,
+            Some("birth".to_string())
+        );
     });
+
+    let days = |record: &StringRecord, i: usize| {
+        chrono::NaiveDate::parse_from_str(&record[i], "%Y-%m-%d").unwrap()
+    };
+    let records: Vec<StringRecord> = csv::Reader::from_path(output_path)
+        .unwrap()
+        .records()
+        .map(|record| record.unwrap())
+        .collect();
+    // the intervals of a patient are kept
+    assert_eq!((days(&records[0], 2) - days(&records[0], 1)).num_days(), 10);
+    assert_eq!((days(&records[1], 2) - days(&records[1], 1)).num_days(), 1);
+    assert_ne!(&records[0][1], "2023-06-15");
+    assert_eq!(&records[0][3], "1990-01-01");
+    assert_eq!(&records[1][3], "");
+}
+
+#[tokio::test]
+async fn test_csv_conditional_rules() {
+    let files_path = "../demo/data/input/keys/customers.csv";
+    let output_path = "../demo/data/output/csv/conditions/customers.csv";
+    std::fs::create_dir_all("../demo/data/output/csv/conditions").unwrap();
+
+    let rules = "
+name: { strategy: mask, when: country == 'DE' }
+";
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let process_runtime = ProcessRuntime {
+        fields: vec!["name".to_string()],
+        mask_symbols: Some("#####".to_string()),
+        rules: serde_yaml::from_str(rules).unwrap(),
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+    csv_processor(
+        tx_metadata.clone(),
+        files_path,
+        output_path,
+        process_runtime,
+    )
+    .unwrap();
+    drop(tx_metadata);
+    rx_metadata
+        .iter()
+        .for_each(|item| assert_eq!(item.metadata.failed_records, 0));
+
+    let names: Vec<String> = csv::Reader::from_path(output_path)
+        .unwrap()
+        .records()
+        .map(|record| record.unwrap()[1].to_string())
+        .collect();
+    // only the german customers are masked
+    assert_eq!(names, vec!["#####", "Bob Smith", "#####"]);
+}
+
+#[tokio::test]
+async fn test_csv_subset() {
+    let files_path = "../demo/data/input/keys/orders.csv";
+    std::fs::create_dir_all("../demo/data/output/csv/subset").unwrap();
+
+    for (chunk_threshold, output_path) in [
+        (0, "../demo/data/output/csv/subset/orders.csv"),
+        (1, "../demo/data/output/csv/subset/orders_chunked.csv"),
+    ] {
+        let (tx_metadata, rx_metadata) = flume::unbounded();
+        let process_runtime = ProcessRuntime {
+            fields: vec!["customer_id".to_string()],
+            mask_symbols: Some("#####".to_string()),
+            subset: serde_yaml::from_str(
+                "drop_when: customer_id == 'C003'\nlimit: 2\ndrop_fields: [order_id]",
+            )
+            .unwrap(),
+            chunk_size: 16,
+            chunk_threshold,
+            mode: Mode::MASK,
+            ..Default::default()
+        };
+        csv_processor(
+            tx_metadata.clone(),
+            files_path,
+            output_path,
+            process_runtime,
+        )
+        .unwrap();
+        drop(tx_metadata);
+        rx_metadata.iter().for_each(|item| {
+            assert_eq!(item.metadata.total_records, 4);
+            assert_eq!(item.metadata.dropped_records, 2);
+            assert_eq!(item.fields_dropped, vec!["order_id"]);
+        });
+
+        let mut reader = csv::Reader::from_path(output_path).unwrap();
+        assert_eq!(
+            reader.headers().unwrap(),
+            &StringRecord::from(vec!["customer_id", "amount"])
+        );
+        let amounts: Vec<String> = reader
+            .records()
+            .map(|record| record.unwrap()[1].to_string())
+            .collect();
+        // the first two records not dropped
+        assert_eq!(amounts, vec!["120.50", "89.90"]);
+    }
 }
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::{
+    strategy::{
+        date::{age_bucket, shift_days, DateUnit, ParsedDate},
+        key::KeyMap,
+        mask_key::MaskKey,
+        rule::{RuleContext, Strategy},
+    },
+    utils::error::MedErrorType,
+};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+#[test]
+fn test_shift_days() {
+    let key = MaskKey::new(b"secret");
+    for entity in ["", "P001", "P002", "P003"] {
+        let days = shift_days(&key, 42, entity, 30);
+        assert!(days != 0 && (-30..=30).contains(&days));
+        assert_eq!(shift_days(&key, 42, entity, 30), days);
+    }
+    assert_ne!(
+        (1..20u64)
+            .map(|seed| shift_days(&key, seed, "P001", 365))
+            .collect::<Vec<_>>(),
+        vec![shift_days(&key, 1, "P001", 365); 19]
+    );
+    // the shift is keyed, the seed of the audit alone does not give it
+    assert_ne!(
+        (1..20)
+            .map(|i| shift_days(&MaskKey::new(&[i]), 42, "P001", 365))
+            .collect::<Vec<_>>(),
+        vec![shift_days(&MaskKey::new(&[1]), 42, "P001", 365); 19]
+    );
+}
+
+#[test]
+fn test_parsed_date() {
+    let parsed = ParsedDate::parse("2023-06-15 08:30:00", "%Y-%m-%d %H:%M:%S").unwrap();
+    assert_eq!(parsed.date(), date(2023, 6, 15));
+    assert_eq!(
+        parsed.shift(20).format("%Y-%m-%d %H:%M:%S"),
+        "2023-07-05 08:30:00"
+    );
+    assert_eq!(
+        parsed.truncate(DateUnit::Year).format("%Y-%m-%d %H:%M:%S"),
+        "2023-01-01 00:00:00"
+    );
+    let parsed = ParsedDate::parse("15/06/2023", "%d/%m/%Y").unwrap();
+    assert_eq!(parsed.truncate(DateUnit::Month).format("%Y-%m"), "2023-06");
+    let err = ParsedDate::parse("2023-13-01", "%Y-%m-%d").unwrap_err();
+    assert_eq!(err.error_type, MedErrorType::ConfigError);
+    // the value is never reported
+    assert!(!err.message.unwrap().contains("2023-13-01"));
+}
+
+#[test]
+fn test_age_bucket() {
+    let today = date(2026, 10, 19);
+    assert_eq!(age_bucket(date(1990, 10, 19), today, 10, None), "30-39");
+    assert_eq!(age_bucket(date(1996, 10, 20), today, 10, None), "20-29");
+    assert_eq!(age_bucket(date(1930, 1, 1), today, 10, Some(90)), "90+");
+    assert_eq!(age_bucket(date(1980, 1, 1), today, 5, Some(90)), "45-49");
+    assert_eq!(age_bucket(date(1980, 1, 1), today, 1, None), "46");
+}
+
+#[test]
+fn test_date_shift_keeps_intervals() {
+    let strategy: Strategy =
+        serde_yaml::from_str("strategy: date_shift\nkey: patient_id\nmax_days: 30").unwrap();
+    let patient = HashMap::from([("patient_id".to_string(), "P001".to_string())]);
+    let context = RuleContext {
+        mask_symbols: "#####",
+        seed: 7,
+        keys: &KeyMap::default(),
+        mask_key: &MaskKey::default(),
+        record: &patient,
+    };
+    let admission = strategy.apply("2023-06-15", &context).unwrap();
+    let discharge = strategy.apply("2023-06-25", &context).unwrap();
+    let interval = NaiveDate::parse_from_str(&discharge, "%Y-%m-%d").unwrap()
+        - NaiveDate::parse_from_str(&admission, "%Y-%m-%d").unwrap();
+    assert_eq!(interval.num_days(), 10);
+    assert_ne!(admission, "2023-06-15");
+    assert_eq!(strategy.apply("", &context).unwrap(), "");
+
+    // the key is required
+    let context = RuleContext {
+        mask_symbols: "#####",
+        seed: 7,
+        keys: &KeyMap::default(),
+        mask_key: &MaskKey::default(),
+        record: &(),
+    };
+    assert!(strategy.apply("2023-06-15", &context).is_err());
+}
+
+#[test]
+fn test_date_strategies_from_yaml() {
+    let context = RuleContext {
+        mask_symbols: "#####",
+        seed: 7,
+        keys: &KeyMap::default(),
+        mask_key: &MaskKey::default(),
+        record: &(),
+    };
+    let strategy: Strategy = serde_yaml::from_str(
+        "strategy: date_truncate\nto: month\ninput_format: \"%d/%m/%Y\"\noutput_format: \"%Y-%m\"",
+    )
+    .unwrap();
+    assert_eq!(strategy.apply("15/06/2023", &context).unwrap(), "2023-06");
+    assert!(strategy.apply("2023-06-15", &context).is_err());
+
+    let strategy: Strategy =
+        serde_yaml::from_str("strategy: age_bucket\nsize: 10\nmax: 90").unwrap();
+    assert_eq!(
+        strategy,
+        Strategy::AgeBucket {
+            size: 10,
+            max: Some(90),
+            input_format: None
+        }
+    );
+    assert_eq!(strategy.apply("1900-01-01", &context).unwrap(), "90+");
+}
@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::audit::{
+    app::Summary,
+    db::{AuditLocation, Database},
+};
+
+#[test]
+fn test_audit_location_from_str() {
+    assert_eq!(
+        AuditLocation::from_str(":memory:"),
+        Ok(AuditLocation::Memory)
+    );
+    assert_eq!(AuditLocation::from_str("off"), Ok(AuditLocation::Disabled));
+    assert_eq!(
+        AuditLocation::from_str("/shared/med.db"),
+        Ok(AuditLocation::File(PathBuf::from("/shared/med.db")))
+    );
+    assert!(AuditLocation::from_str(" ").is_err());
+}
+
+#[test]
+fn test_audit_location_resolve() {
+    // the cli location wins over the environment and the job config
+    let location = AuditLocation::resolve(Some(":memory:"), Some("off")).unwrap();
+    assert_eq!(location, AuditLocation::Memory);
+    assert!(!location.is_persistent());
+    assert!(AuditLocation::resolve(Some(""), None).is_err());
+}
+
+#[tokio::test]
+async fn test_database_file() {
+    let path = PathBuf::from("../demo/data/output/audit/shared/med.db");
+    if path.exists() {
+        std::fs::remove_file(&path).unwrap();
+    }
+    let mut db = Database::new(&AuditLocation::File(path.clone()))
+        .await
+        .unwrap();
+    assert!(path.exists());
+    let id = db.insert(&Summary::default()).await.unwrap();
+    assert_eq!(id, 1);
+}
+
+#[tokio::test]
+async fn test_database_memory() {
+    for location in [AuditLocation::Memory, AuditLocation::Disabled] {
+        let mut db = Database::new(&location).await.unwrap();
+        let id = db.insert(&Summary::default()).await.unwrap();
+        assert_eq!(id, 1);
+        // the single connection keeps the records
+        assert_eq!(db.show(id).await.unwrap().id, id);
+    }
+}
+
+#[tokio::test]
+async fn test_database_legacy_upgrade() {
+    use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection, Row};
+
+    // a database created by the releases before the migrator, without the cancelled col
+    let path = PathBuf::from("../demo/data/output/audit/legacy/med.db");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    if path.exists() {
+        std::fs::remove_file(&path).unwrap();
+    }
+    let mut conn = SqliteConnectOptions::new()
+        .filename(&path)
+        .create_if_missing(true)
+        .connect()
+        .await
+        .unwrap();
+    sqlx::query(
+        "CREATE TABLE audit (
+            id INTEGER PRIMARY KEY,
+            user TEXT NOT NULL,
+            hostname TEXT NOT NULL,
+            total_files INTEGER NOT NULL,
+            total_records INTEGER NOT NULL,
+            failed_records INTEGER NOT NULL,
+            record_failed_reason TEXT,
+            runtime_conf TEXT NOT NULL,
+            process_failure_reason TEXT,
+            successed BOOLEAN NOT NULL DEFAULT FALSE,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+        );
+        ALTER TABLE audit ADD COLUMN elapsed_time TEXT;
+        INSERT INTO audit ( user, hostname, total_files, total_records, failed_records, runtime_conf, elapsed_time )
+        VALUES ( 'med', 'legacy', 1, 1, 0, '{}', '1s' );",
+    )
+    .execute(&mut conn)
+    .await
+    .unwrap();
+    conn.close().await.unwrap();
+
+    let db = Database::new(&AuditLocation::File(path)).await.unwrap();
+    let versions = sqlx::query("SELECT count(*) AS count FROM _sqlx_migrations;")
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        versions.get::<i32, &str>("count") as usize,
+        crate::audit::migration::MIGRATOR
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .count()
+    );
+    // the legacy record is kept, and upgraded with the new cols
+    let record = db.show(1).await.unwrap();
+    assert_eq!(record.hostname, "legacy");
+    assert!(!record.cancelled);
+}
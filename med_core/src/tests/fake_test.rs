@@ -0,0 +1,96 @@
+use crate::strategy::{
+    fake::{mod97, FakeKind},
+    key::KeyMap,
+    mask_key::MaskKey,
+    rule::{RuleContext, Strategy},
+};
+
+#[test]
+fn test_fake_is_seeded_by_value() {
+    let key = MaskKey::new(b"secret");
+    let kinds = [
+        FakeKind::FirstName,
+        FakeKind::LastName,
+        FakeKind::FullName,
+        FakeKind::Email,
+        FakeKind::Phone,
+        FakeKind::Address,
+        FakeKind::Company,
+        FakeKind::Date,
+        FakeKind::Uuid,
+        FakeKind::Iban,
+    ];
+    for kind in kinds {
+        let fake = kind.fake("Antony Brandt", &key);
+        assert!(!fake.is_empty());
+        assert_ne!(fake, "Antony Brandt");
+        assert_eq!(kind.fake("Antony Brandt", &key), fake, "{}", kind);
+        assert_eq!(kind.fake("", &key), "");
+    }
+    assert_ne!(
+        FakeKind::Uuid.fake("Antony Brandt", &key),
+        FakeKind::Uuid.fake("Antony Brandt ", &key)
+    );
+    // the fakes can not be linked to their values without the key
+    assert_ne!(
+        FakeKind::Uuid.fake("Antony Brandt", &key),
+        FakeKind::Uuid.fake("Antony Brandt", &MaskKey::new(b"other"))
+    );
+}
+
+#[test]
+fn test_fake_formats() {
+    let key = MaskKey::default();
+    let email = FakeKind::Email.fake("antony@corp.com", &key);
+    assert!(
+        email.ends_with("@example.com")
+            || email.ends_with("@example.org")
+            || email.ends_with("@example.net")
+    );
+
+    // every digit is replaced, the format is kept
+    let phones: Vec<String> = (0..20)
+        .map(|i| FakeKind::Phone.fake(&format!("+1 (416) 555-01{:02}", i), &key))
+        .collect();
+    for phone in &phones {
+        assert_eq!(phone.len(), "+1 (416) 555-0199".len());
+        assert!(phone.starts_with('+'));
+        assert_eq!(&phone[2..4], " (");
+        assert_eq!(&phone[7..9], ") ");
+    }
+    assert!(phones.iter().any(|phone| !phone.starts_with("+1")));
+
+    let date = FakeKind::Date.fake("1984-02-29", &key);
+    assert!(chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_ok());
+
+    let uuid = FakeKind::Uuid.fake("42", &key);
+    assert_eq!(uuid.len(), 36);
+    assert_eq!(&uuid[14..15], "4");
+
+    // a valid IBAN is 1 modulo 97, once its country and check digits are moved to its end
+    let iban = FakeKind::Iban.fake("GB82WEST12345698765432", &key);
+    assert_eq!(iban.len(), 22);
+    assert_eq!(mod97(&format!("{}1314{}", &iban[4..], &iban[2..4])), 1);
+}
+
+#[test]
+fn test_strategy_from_yaml() {
+    let strategy: Strategy = serde_yaml::from_str("strategy: fake\nkind: full_name").unwrap();
+    assert_eq!(
+        strategy,
+        Strategy::Fake {
+            kind: FakeKind::FullName
+        }
+    );
+    let context = RuleContext {
+        mask_symbols: "#####",
+        seed: 0,
+        keys: &KeyMap::default(),
+        mask_key: &MaskKey::default(),
+        record: &(),
+    };
+    assert_eq!(strategy.apply("", &context).unwrap(), "");
+    let strategy: Strategy = serde_yaml::from_str("strategy: mask").unwrap();
+    assert_eq!(strategy.apply("Antony Brandt", &context).unwrap(), "#####");
+    assert!(serde_yaml::from_str::<Strategy>("strategy: fake\nkind: planet").is_err());
+}
@@ -1,8 +1,14 @@
 use crate::{
-    app::{json::json_processor, processor::ProcessRuntime},
-    models::enums::Mode,
-    utils::{crypto::Cypher, error::MedErrorType},
+    app::{
+        json::{json_processor, write_json},
+        processor::ProcessRuntime,
+    },
+    models::enums::{Mode, RecordErrorKind},
+    strategy::{fake::FakeKind, key::KeyMap, mask_key::MaskKey, rule::Strategy},
+    utils::{crypto::Cypher, digest::sha256_file, error::MedErrorType, helpers::temp_output_path},
 };
+use serde_json::json;
+use std::path::Path;
 
 // const KEY: &str = "123";
 
@@ -19,6 +25,7 @@ async fn test_json_processor_error() {
         standard: None,
         mask_symbols: Some("#####".to_string()),
         mode: Mode::MASK,
+        ..Default::default()
     };
 
     let files_path: &str = "";
@@ -40,7 +47,7 @@ async fn test_json_processor_error() {
     drop(tx_metadata);
 
     rx_metadata.iter().for_each(|item| {
-        assert_eq!(item.total_records, 0);
+        assert_eq!(item.metadata.total_records, 0);
     });
 }
 
@@ -53,6 +60,7 @@ async fn test_json_processor_format_err() {
         standard: None,
         mask_symbols: Some("#####".to_string()),
         mode: Mode::MASK,
+        ..Default::default()
     };
     let files_path: &str = "../demo/data/input/format_err/json/format_err.json";
     let output_path = "../demo/data/output/json/format_err/generated.json";
@@ -69,7 +77,47 @@ async fn test_json_processor_format_err() {
     drop(tx_metadata);
 
     rx_metadata.iter().for_each(|item| {
-        assert_eq!(item.failed_records, 1);
+        assert_eq!(item.metadata.failed_records, 1);
+        let record_error = &item.metadata.record_failed_reason[0];
+        assert_eq!(record_error.kind, RecordErrorKind::Parse);
+        assert_eq!(record_error.line, Some(1));
+        assert!(record_error.column.is_some());
+    });
+}
+
+#[tokio::test]
+async fn test_json_decrypt_error() {
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let process_runtime = ProcessRuntime {
+        fields: vec!["name".to_string()],
+        cypher: Some(Cypher::new("1234")),
+        standard: Some(crate::models::enums::Standard::DES64),
+        mask_symbols: None,
+        mode: Mode::DECRYPT,
+        ..Default::default()
+    };
+    // the input is not encrypted, every name fails to be decrypted
+    let files_path: &str = "../demo/data/input/json/generated.json";
+    let output_path: &str = "../demo/data/output/json/format_err/decrypt_err.json";
+
+    json_processor(
+        tx_metadata.clone(),
+        files_path,
+        output_path,
+        process_runtime,
+    )
+    .unwrap();
+
+    // drop the channel once it done.
+    drop(tx_metadata);
+
+    rx_metadata.iter().for_each(|item| {
+        assert!(item.completed);
+        assert!(item.metadata.failed_records > 0);
+        let record_error = &item.metadata.record_failed_reason[0];
+        assert_eq!(record_error.kind, RecordErrorKind::Decrypt);
+        assert_eq!(record_error.record, Some(0));
+        assert_eq!(record_error.field, Some("$[0].friends[0].name".to_string()));
     });
 }
 
@@ -82,6 +130,7 @@ async fn test_json_is_obj() {
         standard: None,
         mask_symbols: Some("#####".to_string()),
         mode: Mode::MASK,
+        ..Default::default()
     };
     let files_path: &str = "../demo/data/input/json/is_obj.json";
     let output_path = "../demo/data/output/json/mask/is_obj.json";
@@ -99,7 +148,7 @@ async fn test_json_is_obj() {
 
     rx_metadata.iter().for_each(|item| {
         // println!("item : {:?}", item );
-        assert_eq!(item.total_records, 1);
+        assert_eq!(item.metadata.total_records, 1);
     });
 }
 
@@ -112,6 +161,7 @@ async fn test_json_arr_in_arr() {
         standard: None,
         mask_symbols: Some("#####".to_string()),
         mode: Mode::MASK,
+        ..Default::default()
     };
     let files_path: &str = "../demo/data/input/json/arr_in_arr.json";
     let output_path = "../demo/data/output/json/mask/arr_in_arr.json";
@@ -129,7 +179,7 @@ async fn test_json_arr_in_arr() {
 
     rx_metadata.iter().for_each(|item| {
         // println!("item : {:?}", item );
-        assert_eq!(item.failed_records, 0);
+        assert_eq!(item.metadata.failed_records, 0);
     });
 }
 
@@ -142,6 +192,7 @@ async fn test_json_encrypt() {
         standard: Some(crate::models::enums::Standard::DES64),
         mask_symbols: None,
         mode: Mode::ENCRYPT,
+        ..Default::default()
     };
     let files_path: &str = "../demo/data/input/json/level/generated.json";
     let output_path: &str = "../demo/data/output/json/encrypt/generated.json";
@@ -159,7 +210,7 @@ async fn test_json_encrypt() {
 
     rx_metadata.iter().for_each(|item| {
         // info!("item : {:?}", item);
-        assert_eq!(item.failed_records, 0);
+        assert_eq!(item.metadata.failed_records, 0);
     });
 }
 
@@ -172,6 +223,7 @@ async fn test_json_decrypt() {
         standard: Some(crate::models::enums::Standard::DES64),
         mask_symbols: None,
         mode: Mode::DECRYPT,
+        ..Default::default()
     };
     let output_path: &str = "../demo/data/output/json/decrypt/generated.json";
     let files_path: &str = "../demo/data/output/json/encrypt/generated.json";
@@ -189,6 +241,236 @@ async fn test_json_decrypt() {
 
     rx_metadata.iter().for_each(|item| {
         // info!("item : {:?}", item);
-        assert_eq!(item.failed_records, 0);
+        assert_eq!(item.metadata.failed_records, 0);
+    });
+}
+
+#[tokio::test]
+async fn test_write_json_atomic() {
+    let output_path = "../demo/data/output/json/mask/write_json.json";
+    let output_hash = write_json(&json!({"name": "#####"}), output_path).unwrap();
+    assert!(Path::new(output_path).exists());
+    assert_eq!(output_hash, sha256_file(Path::new(output_path)).unwrap());
+    assert!(!temp_output_path(Path::new(output_path)).exists());
+
+    // the output is an existing directory, the final rename will fail
+    let output_path = "../demo/data/output/json/format_err";
+    match write_json(&json!({"name": "#####"}), output_path) {
+        Ok(_) => unimplemented!(),
+        Err(err) => {
+            assert_eq!(err.error_type, MedErrorType::IoError);
+        }
+    }
+    assert!(!temp_output_path(Path::new(output_path)).exists());
+}
+
+#[tokio::test]
+async fn test_json_fake_rule() {
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let process_runtime = ProcessRuntime {
+        fields: vec!["name".to_string(), "data".to_string()],
+        mask_symbols: Some("#####".to_string()),
+        rules: [(
+            "name".to_string(),
+            Strategy::Fake {
+                kind: FakeKind::FullName,
+            }
+            .into(),
+        )]
+        .into_iter()
+        .collect(),
+        mode: Mode::MASK,
+        mask_key: MaskKey::new(b"secret"),
+        ..Default::default()
+    };
+    let files_path: &str = "../demo/data/input/json/is_obj.json";
+    let output_path = "../demo/data/output/json/fake/is_obj.json";
+    std::fs::create_dir_all("../demo/data/output/json/fake").unwrap();
+
+    json_processor(
+        tx_metadata.clone(),
+        files_path,
+        output_path,
+        process_runtime,
+    )
+    .unwrap();
+    drop(tx_metadata);
+    rx_metadata
+        .iter()
+        .for_each(|item| assert_eq!(item.metadata.failed_records, 0));
+
+    let output: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output_path).unwrap()).unwrap();
+    let key = MaskKey::new(b"secret");
+    assert_eq!(output["name"], FakeKind::FullName.fake("hello world", &key));
+    assert_eq!(
+        output["test"][0]["name"],
+        FakeKind::FullName.fake("code", &key)
+    );
+    // the fields without a rule are masked
+    assert_eq!(output["data"], "#####");
+}
+
+#[tokio::test]
+async fn test_json_numeric_rules() {
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let process_runtime = ProcessRuntime {
+        fields: ["name", "age", "salary", "bonus"]
+            .iter()
+            .map(|field| field.to_string())
+            .collect(),
+        mask_symbols: Some("#####".to_string()),
+        rules: [
+            ("age".to_string(), Strategy::Bucket { size: 10 }),
+            ("salary".to_string(), Strategy::Round { digits: 2 }),
+            (
+                "bonus".to_string(),
+                Strategy::Noise {
+                    max: 100.0,
+                    relative: false,
+                },
+            ),
+            ("name".to_string(), Strategy::Round { digits: 2 }),
+        ]
+        .into_iter()
+        .map(|(field, strategy)| (field, strategy.into()))
+        .collect(),
+        mode: Mode::MASK,
+        seed: 42,
+        ..Default::default()
+    };
+    let files_path: &str = "../demo/data/input/numbers/employees.json";
+    let output_path = "../demo/data/output/json/numbers/employees.json";
+    std::fs::create_dir_all("../demo/data/output/json/numbers").unwrap();
+
+    json_processor(
+        tx_metadata.clone(),
+        files_path,
+        output_path,
+        process_runtime,
+    )
+    .unwrap();
+    drop(tx_metadata);
+    let metadata: Vec<_> = rx_metadata.iter().collect();
+    // the name is not a number
+    assert_eq!(metadata[0].metadata.failed_records, 1);
+    assert_eq!(
+        metadata[0].metadata.record_failed_reason[0].kind,
+        RecordErrorKind::Mask
+    );
+
+    let output: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output_path).unwrap()).unwrap();
+    // a bucket is a string, a rounded number stays a number
+    assert_eq!(output["age"], "30-39");
+    assert_eq!(output["salary"], json!(48000));
+    assert_eq!(output["bonus"], serde_json::Value::Null);
+    assert_eq!(output["name"], "");
+    assert_eq!(output["employee_id"], "E001");
+}
+
+#[tokio::test]
+async fn test_json_conditional_rules() {
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let rules = "
+name: { strategy: mask, when: country == 'DE' or consent == false }
+note: { strategy: mask, when: $.customer.country == 'DE' }
+";
+    let process_runtime = ProcessRuntime {
+        fields: vec!["name".to_string(), "note".to_string()],
+        mask_symbols: Some("#####".to_string()),
+        rules: serde_yaml::from_str(rules).unwrap(),
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+    let files_path: &str = "../demo/data/input/conditions/orders.json";
+    let output_path = "../demo/data/output/json/conditions/orders.json";
+    std::fs::create_dir_all("../demo/data/output/json/conditions").unwrap();
+
+    json_processor(
+        tx_metadata.clone(),
+        files_path,
+        output_path,
+        process_runtime,
+    )
+    .unwrap();
+    drop(tx_metadata);
+    rx_metadata
+        .iter()
+        .for_each(|item| assert_eq!(item.metadata.failed_records, 0));
+
+    let output: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output_path).unwrap()).unwrap();
+    // the sibling keys of the field
+    assert_eq!(output[0]["customer"]["name"], "#####");
+    assert_eq!(output[1]["customer"]["name"], "Bob Smith");
+    // the json path from the record root
+    assert_eq!(output[0]["items"][0]["note"], "#####");
+    assert_eq!(output[1]["items"][0]["note"], "leave at the door");
+}
+
+#[tokio::test]
+async fn test_json_subset() {
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let process_runtime = ProcessRuntime {
+        fields: vec!["name".to_string()],
+        mask_symbols: Some("#####".to_string()),
+        subset: serde_yaml::from_str("drop_when: $.customer.country == 'DE'\ndrop_fields: [note]")
+            .unwrap(),
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+    let files_path: &str = "../demo/data/input/conditions/orders.json";
+    let output_path = "../demo/data/output/json/subset/orders.json";
+    std::fs::create_dir_all("../demo/data/output/json/subset").unwrap();
+
+    json_processor(
+        tx_metadata.clone(),
+        files_path,
+        output_path,
+        process_runtime,
+    )
+    .unwrap();
+    drop(tx_metadata);
+    rx_metadata.iter().for_each(|item| {
+        assert_eq!(item.metadata.total_records, 2);
+        assert_eq!(item.metadata.dropped_records, 1);
+        assert_eq!(item.fields_dropped, vec!["note"]);
     });
+
+    let output: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output_path).unwrap()).unwrap();
+    assert_eq!(
+        output,
+        json!([{
+            "customer": { "name": "#####", "country": "FR", "consent": true },
+            "items": [{ "sku": "B-2" }]
+        }])
+    );
+
+    // the dropped records get no surrogate
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let keys = KeyMap::default();
+    let process_runtime = ProcessRuntime {
+        fields: vec!["name".to_string()],
+        rules: serde_yaml::from_str("name: { strategy: key, domain: customer }").unwrap(),
+        subset: serde_yaml::from_str("drop_when: $.customer.country == 'DE'").unwrap(),
+        keys: keys.clone(),
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+    let output_path = std::env::temp_dir().join("med_json_subset_keys.json");
+    json_processor(
+        tx_metadata.clone(),
+        files_path,
+        &output_path.display().to_string(),
+        process_runtime,
+    )
+    .unwrap();
+    drop(tx_metadata);
+    rx_metadata
+        .iter()
+        .for_each(|item| assert_eq!(item.metadata.dropped_records, 1));
+    assert_eq!(keys.count("customer"), 1);
+    std::fs::remove_file(output_path).unwrap();
 }
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{
+    app::processor::FileProcessor,
+    models::{
+        enums::{FileType, Mode},
+        params::Params,
+    },
+    strategy::{key::KeyMap, mask_key::MaskKey},
+    utils::config::JobConfig,
+    utils::digest::sha256_hex,
+};
+
+#[test]
+fn test_surrogate_is_consistent() {
+    let key = MaskKey::new(b"secret");
+    let keys = KeyMap::default();
+    let surrogate = keys.surrogate("customer", "C001", &key, 42).unwrap();
+    assert_ne!(surrogate, "C001");
+    // the format is kept
+    assert_eq!(surrogate.len(), 4);
+    assert!(surrogate.starts_with(|c: char| c.is_ascii_uppercase()));
+    assert!(surrogate[1..].chars().all(|c| c.is_ascii_digit()));
+    assert_eq!(
+        keys.surrogate("customer", "C001", &key, 42).unwrap(),
+        surrogate
+    );
+    // shared by the clones, as by the workers
+    assert_eq!(
+        keys.clone().surrogate("customer", "C001", &key, 7).unwrap(),
+        surrogate
+    );
+    assert_eq!(keys.surrogate("customer", "", &key, 42).unwrap(), "");
+    assert_eq!(keys.count("customer"), 1);
+    assert_eq!(keys.count("order"), 0);
+    // the surrogates are keyed, another key gives another surrogate
+    let other = MaskKey::new(b"other");
+    assert_ne!(
+        KeyMap::default()
+            .surrogate("customer", "C001", &other, 42)
+            .unwrap(),
+        surrogate
+    );
+}
+
+#[test]
+fn test_surrogate_is_unique() {
+    // the one digit keys have ten surrogates, one for each
+    let key = MaskKey::new(b"secret");
+    let keys = KeyMap::default();
+    let mut surrogates: Vec<String> = (0..10)
+        .map(|i| keys.surrogate("digit", &i.to_string(), &key, 42).unwrap())
+        .collect();
+    surrogates.sort();
+    assert_eq!(
+        surrogates,
+        (0..10).map(|i| i.to_string()).collect::<Vec<_>>()
+    );
+    // the domains have their own surrogates
+    assert!(keys.surrogate("other", "1", &key, 42).is_ok());
+    assert_eq!(keys.count("digit"), 10);
+    // the lookup of another key needs an eleventh surrogate, the value is not reported
+    let err = keys
+        .surrogate("digit", "7", &MaskKey::new(b"other"), 42)
+        .unwrap_err();
+    assert!(!err.message.unwrap().contains('7'));
+}
+
+#[test]
+fn test_key_map_persisted() {
+    let path = Path::new("../demo/data/output/keys/persisted/key_map.json");
+    if path.exists() {
+        std::fs::remove_file(path).unwrap();
+    }
+    let key = MaskKey::new(b"secret");
+    let keys = KeyMap::load(path).unwrap();
+    let surrogate = keys.surrogate("customer", "C001", &key, 1).unwrap();
+    keys.save().unwrap();
+    // the values are stored by their keyed hash
+    let content = std::fs::read_to_string(path).unwrap();
+    assert!(!content.contains("C001"));
+    assert!(!content.contains(&sha256_hex(b"customer\0C001")));
+
+    // another run, with another seed, keeps the surrogate
+    let keys = KeyMap::load(path).unwrap();
+    assert_eq!(
+        keys.surrogate("customer", "C001", &key, 2).unwrap(),
+        surrogate
+    );
+    assert_ne!(
+        keys.surrogate("customer", "C002", &key, 2).unwrap(),
+        surrogate
+    );
+
+    // the key map of the run only is not saved
+    KeyMap::default().save().unwrap();
+}
+
+#[tokio::test]
+async fn test_keys_join_across_files() {
+    let output = Path::new("../demo/data/output/keys/join");
+    if output.exists() {
+        std::fs::remove_dir_all(output).unwrap();
+    }
+    let job_conf: JobConfig = serde_yaml::from_str(
+        "
+mask_symbols: '#####'
+fields: [name]
+rules:
+  id: { strategy: key, domain: customer }
+  customer_id: { strategy: key, domain: customer }
+  order_id: { strategy: key, domain: order }
+key_map: ../demo/data/output/keys/join/key_map.json
+",
+    )
+    .unwrap();
+    let params = Params {
+        file_path: "../demo/data/input/keys".to_owned(),
+        output_path: "../demo/data/output/keys/join/output".to_owned(),
+        file_type: FileType::CSV,
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+    let mut processor = FileProcessor::new(params, job_conf).await;
+    let metrics = processor.run().await.unwrap();
+    assert_eq!(metrics.total_files, 2);
+    assert_eq!(metrics.metadata.failed_records, 0);
+
+    let read = |name: &str| -> Vec<csv::StringRecord> {
+        csv::Reader::from_path(output.join("output").join(name))
+            .unwrap()
+            .records()
+            .map(|record| record.unwrap())
+            .collect()
+    };
+    let customers: HashMap<String, String> = read("customers.csv")
+        .iter()
+        .map(|record| (record[0].to_string(), record[2].to_string()))
+        .collect();
+    let orders = read("orders.csv");
+    // the orders still join on the customers, the unknown customer stays unknown
+    assert_eq!(customers.get(&orders[0][1]), Some(&"DE".to_string()));
+    assert_eq!(orders[0][1], orders[2][1]);
+    assert_eq!(customers.get(&orders[1][1]), Some(&"DE".to_string()));
+    assert_eq!(customers.get(&orders[3][1]), None);
+    assert!(!customers.contains_key("C001"));
+    assert_eq!(orders[0][0].len(), 6);
+    assert_eq!(&orders[0][0][1..2], "-");
+    assert_eq!(&orders[0][2], "120.50");
+
+    // the surrogates are saved for the next run
+    let keys = KeyMap::load(&output.join("key_map.json")).unwrap();
+    assert_eq!(keys.count("customer"), 4);
+    assert_eq!(keys.count("order"), 4);
+}
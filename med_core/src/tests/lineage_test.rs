@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use crate::audit::{
+    app::Summary,
+    chain::ChainIssueKind,
+    db::{AuditFile, AuditLocation, Database},
+    lineage::{check_lineage, LineageStatus},
+};
+use crate::utils::digest::sha256_file;
+
+const LINEAGE_DIR: &str = "../demo/data/output/audit/lineage";
+
+fn lineage_file(name: &str, content: &str) -> AuditFile {
+    std::fs::create_dir_all(LINEAGE_DIR).unwrap();
+    let output_path = format!("{}/{}", LINEAGE_DIR, name);
+    std::fs::write(&output_path, content).unwrap();
+    AuditFile {
+        input_path: name.to_string(),
+        input_hash: "input".to_string(),
+        input_size: Some(10),
+        output_hash: Some(sha256_file(Path::new(&output_path)).unwrap()),
+        output_size: Some(content.len() as i64),
+        output_path,
+        total_records: Some(1),
+        failed_records: Some(0),
+    }
+}
+
+#[test]
+fn test_check_lineage() {
+    let unchanged = lineage_file("unchanged.csv", "name\n#####\n");
+    let altered = lineage_file("altered.csv", "name\n#####\n");
+    std::fs::write(&altered.output_path, "name\nbob\n").unwrap();
+    let missing = AuditFile {
+        output_path: format!("{}/missing.csv", LINEAGE_DIR),
+        ..unchanged.clone()
+    };
+    let unknown = AuditFile {
+        output_hash: None,
+        ..unchanged.clone()
+    };
+
+    let status: Vec<LineageStatus> = check_lineage(vec![unchanged, altered, missing, unknown])
+        .into_iter()
+        .map(|check| check.status)
+        .collect();
+    assert_eq!(
+        status,
+        vec![
+            LineageStatus::Unchanged,
+            LineageStatus::Altered,
+            LineageStatus::Missing,
+            LineageStatus::Unknown
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_lineage_sealed() {
+    let mut db = Database::new(&AuditLocation::Memory).await.unwrap();
+    db.chain_key = None;
+    let file = lineage_file("sealed.csv", "name\n#####\n");
+    let id = db.insert(&Summary::default()).await.unwrap();
+    db.insert_file(id, "config", &file).await.unwrap();
+    db.update(id, &Summary::default()).await.unwrap();
+
+    assert_eq!(db.audit_files(id).await.unwrap(), vec![file]);
+    assert!(db.verify().await.unwrap().is_valid());
+
+    // the lineage is sealed with the audit record
+    sqlx::query("UPDATE audit_file SET output_hash = 'forged' WHERE audit_id = ?1")
+        .bind(id)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    let report = db.verify().await.unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].kind, ChainIssueKind::Modified);
+}
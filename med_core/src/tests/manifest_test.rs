@@ -0,0 +1,59 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::app::manifest::Manifest;
+use crate::utils::digest::sha256_file;
+
+#[tokio::test]
+async fn test_manifest_incremental() {
+    let dir = Path::new("../demo/data/output/manifest");
+    if dir.exists() {
+        fs::remove_dir_all(dir).unwrap();
+    }
+    fs::create_dir_all(dir).unwrap();
+    let input = dir.join("input.csv");
+    let output = dir.join("output.csv");
+    fs::write(&input, "name\nmed\n").unwrap();
+    fs::write(&output, "name\n#####\n").unwrap();
+
+    let mut manifest = Manifest::load(dir).unwrap();
+    assert!(manifest.files.is_empty());
+    assert!(!manifest.is_unchanged("input.csv", &input, "conf"));
+
+    let input_hash = sha256_file(&input).unwrap();
+    manifest
+        .record(
+            "input.csv",
+            &input,
+            &input_hash,
+            "conf",
+            &output.display().to_string(),
+        )
+        .unwrap();
+    manifest.save().unwrap();
+
+    let mut manifest = Manifest::load(dir).unwrap();
+    // the output path is relative to the output root, whatever the working directory
+    assert_eq!(manifest.files["input.csv"].output_path, "output.csv");
+    assert!(manifest.is_unchanged("input.csv", &input, "conf"));
+    assert!(!manifest.is_unchanged("input.csv", &input, "other conf"));
+
+    // same content rewritten is still unchanged
+    fs::write(&input, "name\nmed\n").unwrap();
+    assert!(manifest.is_unchanged("input.csv", &input, "conf"));
+
+    fs::write(&input, "name\nmed\ncore\n").unwrap();
+    assert!(!manifest.is_unchanged("input.csv", &input, "conf"));
+
+    assert_eq!(
+        manifest
+            .prune(&HashSet::from(["input.csv".to_owned()]))
+            .unwrap(),
+        0
+    );
+    assert!(output.exists());
+    assert_eq!(manifest.prune(&HashSet::new()).unwrap(), 1);
+    assert!(!output.exists());
+    assert!(manifest.files.is_empty());
+}
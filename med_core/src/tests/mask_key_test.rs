@@ -0,0 +1,31 @@
+use crate::strategy::mask_key::{mask_key_file, MaskKey};
+
+#[test]
+fn test_mask_key() {
+    let key = MaskKey::new(b"secret");
+    assert_eq!(
+        key.mac(&[b"a", b"b"]),
+        MaskKey::new(b"secret").mac(&[b"a", b"b"])
+    );
+    // the parts are separated
+    assert_ne!(key.mac(&[b"a", b"b"]), key.mac(&[b"ab"]));
+    assert_ne!(key.mac(&[b"a"]), MaskKey::new(b"other").mac(&[b"a"]));
+    // the key is never printed
+    assert_eq!(format!("{:?}", key), "MaskKey(***)");
+}
+
+#[test]
+fn test_mask_key_file() {
+    let path = std::path::Path::new("../demo/data/output/mask_key/med_mask.key");
+    let _ = std::fs::remove_file(path);
+    let key = mask_key_file(path).unwrap();
+    assert_eq!(key.len(), 64);
+    // the key of the first run is kept
+    assert_eq!(mask_key_file(path).unwrap(), key);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -19,9 +19,28 @@ async fn test_mode_enum() {
     assert_eq!(mode.to_string(), "decrypt");
     assert_eq!(format!("{mode:?}"), "DECRYPT");
 
+    let mode = Mode::TOKENIZE;
+    assert_eq!(mode.to_string(), "tokenize");
+    assert_eq!(format!("{mode:?}"), "TOKENIZE");
+
+    let mode = Mode::DETOKENIZE;
+    assert_eq!(mode.to_string(), "detokenize");
+    assert_eq!(format!("{mode:?}"), "DETOKENIZE");
+
+    let mode = Mode::KANONYMITY;
+    assert_eq!(mode.to_string(), "k-anonymity");
+    assert_eq!(format!("{mode:?}"), "KANONYMITY");
+
     assert_eq!(
         Mode::value_variants(),
-        &[Mode::MASK, Mode::ENCRYPT, Mode::DECRYPT]
+        &[
+            Mode::MASK,
+            Mode::ENCRYPT,
+            Mode::DECRYPT,
+            Mode::TOKENIZE,
+            Mode::DETOKENIZE,
+            Mode::KANONYMITY
+        ]
     );
 
     assert_eq!(
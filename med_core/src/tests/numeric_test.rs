@@ -0,0 +1,125 @@
+use crate::{
+    strategy::{
+        key::KeyMap,
+        mask_key::MaskKey,
+        numeric::{bucket, noise, round_significant, top_coding, ParsedNumber},
+        rule::{RuleContext, Strategy},
+    },
+    utils::error::MedErrorType,
+};
+
+fn number(value: &str) -> ParsedNumber {
+    ParsedNumber::parse(value).unwrap()
+}
+
+#[test]
+fn test_parsed_number() {
+    assert_eq!(
+        number("42"),
+        ParsedNumber {
+            value: 42.0,
+            decimals: 0
+        }
+    );
+    assert_eq!(number(" -3.250 ").decimals, 3);
+    assert_eq!(number("1e3").value, 1000.0);
+    assert_eq!(
+        ParsedNumber::parse("forty").unwrap_err().error_type,
+        MedErrorType::ConfigError
+    );
+    assert!(ParsedNumber::parse("inf").is_err());
+    assert_eq!(ParsedNumber::format(-0.001, 2), "0.00");
+    assert_eq!(ParsedNumber::format(2.5, 1), "2.5");
+}
+
+#[test]
+fn test_noise() {
+    let key = MaskKey::new(b"secret");
+    for value in ["100", "250.50", "-12", "0.5"] {
+        let parsed = number(value);
+        let noisy = noise(&key, 42, &parsed, 10.0, false);
+        assert_eq!(noisy, noise(&key, 42, &parsed, 10.0, false));
+        assert!((noisy.parse::<f64>().unwrap() - parsed.value).abs() <= 10.0);
+        assert_eq!(number(&noisy).decimals, parsed.decimals);
+    }
+    // relative to the number
+    let noisy = noise(&key, 42, &number("1000"), 0.05, true);
+    assert!((noisy.parse::<f64>().unwrap() - 1000.0).abs() <= 50.0);
+    // another seed, another noise
+    assert_ne!(
+        (1..20u64)
+            .map(|seed| noise(&key, seed, &number("1000"), 100.0, false))
+            .collect::<Vec<_>>(),
+        vec![noise(&key, 1, &number("1000"), 100.0, false); 19]
+    );
+    // another key, another noise, the seed alone does not give it
+    assert_ne!(
+        (1..20)
+            .map(|i| noise(&MaskKey::new(&[i]), 42, &number("1000"), 100.0, false))
+            .collect::<Vec<_>>(),
+        vec![noise(&MaskKey::new(&[1]), 42, &number("1000"), 100.0, false); 19]
+    );
+    assert_eq!(noise(&key, 42, &number("7"), 0.0, false), "7");
+}
+
+#[test]
+fn test_round_significant() {
+    assert_eq!(round_significant(&number("123456"), 2), "120000");
+    assert_eq!(round_significant(&number("0.012345"), 3), "0.0123");
+    assert_eq!(round_significant(&number("-987.6"), 1), "-1000");
+    assert_eq!(round_significant(&number("3.14159"), 3), "3.14");
+    assert_eq!(round_significant(&number("0"), 2), "0");
+}
+
+#[test]
+fn test_bucket_and_top_coding() {
+    assert_eq!(bucket(&number("34"), 10), "30-39");
+    assert_eq!(bucket(&number("34.9"), 5), "30-34");
+    assert_eq!(bucket(&number("-3"), 10), "-10--1");
+    assert_eq!(bucket(&number("34"), 1), "34");
+
+    assert_eq!(
+        top_coding(&number("250000"), Some(100000.0), None),
+        Some("100000".to_string())
+    );
+    assert_eq!(
+        top_coding(&number("-5"), None, Some(0.0)),
+        Some("0".to_string())
+    );
+    assert_eq!(top_coding(&number("50"), Some(100.0), Some(0.0)), None);
+}
+
+#[test]
+fn test_numeric_strategies_from_yaml() {
+    let context = RuleContext {
+        mask_symbols: "#####",
+        seed: 7,
+        keys: &KeyMap::default(),
+        mask_key: &MaskKey::default(),
+        record: &(),
+    };
+    let strategy: Strategy = serde_yaml::from_str("strategy: noise\nmax: 5").unwrap();
+    assert_eq!(
+        strategy,
+        Strategy::Noise {
+            max: 5.0,
+            relative: false
+        }
+    );
+    assert_eq!(
+        strategy.apply("120", &context).unwrap(),
+        strategy.apply("120", &context).unwrap()
+    );
+    assert_eq!(strategy.apply("", &context).unwrap(), "");
+    assert!(strategy.apply("n/a", &context).is_err());
+
+    let strategy: Strategy = serde_yaml::from_str("strategy: round\ndigits: 2").unwrap();
+    assert_eq!(strategy.apply("48213", &context).unwrap(), "48000");
+
+    let strategy: Strategy = serde_yaml::from_str("strategy: bucket\nsize: 10").unwrap();
+    assert_eq!(strategy.apply("34", &context).unwrap(), "30-39");
+
+    let strategy: Strategy = serde_yaml::from_str("strategy: top_coding\ntop: 90").unwrap();
+    assert_eq!(strategy.apply("97", &context).unwrap(), "90");
+    assert_eq!(strategy.apply("45", &context).unwrap(), "45");
+}
@@ -0,0 +1,153 @@
+use crate::{
+    app::processor::FileProcessor,
+    models::{
+        enums::{FileType, Mode},
+        params::Params,
+    },
+    strategy::{
+        date::DateUnit,
+        fake::FakeKind,
+        profile::Profile,
+        rule::{FieldRule, Strategy},
+    },
+    utils::config::JobConfig,
+};
+
+fn strategy(profile: Profile, field: &str) -> Option<Strategy> {
+    profile.rule(field).map(|rule| rule.strategy)
+}
+
+#[test]
+fn test_profile_rule() {
+    let profile = Profile::GdprBasic;
+    // the case, `_`, `-`, `.` and spaces are ignored
+    for field in ["email", "Email_Address", "contact.e-mail", "WORK EMAIL"] {
+        assert_eq!(
+            strategy(profile, field),
+            Some(Strategy::Fake {
+                kind: FakeKind::Email
+            }),
+            "{}",
+            field
+        );
+    }
+    assert_eq!(
+        strategy(profile, "first_name"),
+        Some(Strategy::Fake {
+            kind: FakeKind::FirstName
+        })
+    );
+    assert_eq!(
+        strategy(profile, "birth_date"),
+        Some(Strategy::DateTruncate {
+            to: DateUnit::Year,
+            input_format: None,
+            output_format: None,
+        })
+    );
+    // the first pattern matching wins
+    assert_eq!(strategy(profile, "ip_address"), Some(Strategy::Mask));
+    for field in ["business_name", "ethnicity", "hourly_rate", "id", "country"] {
+        assert_eq!(strategy(profile, field), None, "{}", field);
+    }
+
+    assert_eq!(strategy(Profile::Pci, "card_number"), Some(Strategy::Mask));
+    assert_eq!(strategy(Profile::Pci, "email"), None);
+    assert!(matches!(
+        strategy(Profile::HipaaSafeHarbor, "dob"),
+        Some(Strategy::AgeBucket { max: Some(90), .. })
+    ));
+    // the identifiers are redacted, never replaced by a value derived from them
+    for field in [
+        "patient_mrn",
+        "patient_name",
+        "email",
+        "phone",
+        "fax",
+        "street",
+    ] {
+        assert_eq!(
+            strategy(Profile::HipaaSafeHarbor, field),
+            Some(Strategy::Mask),
+            "{}",
+            field
+        );
+    }
+    assert_eq!(
+        Profile::HipaaSafeHarbor.rule("zip_code"),
+        Some(FieldRule::from(Strategy::Mask))
+    );
+}
+
+#[test]
+fn test_profile_names() {
+    assert_eq!(Profile::HipaaSafeHarbor.to_string(), "hipaa-safe-harbor");
+    assert_eq!("pci".parse::<Profile>(), Ok(Profile::Pci));
+    assert!("sox".parse::<Profile>().is_err());
+    assert_eq!(
+        serde_json::to_string(&Profile::GdprBasic).unwrap(),
+        "\"gdpr-basic\""
+    );
+    assert_eq!(
+        serde_yaml::from_str::<Profile>("hipaa-safe-harbor").unwrap(),
+        Profile::HipaaSafeHarbor
+    );
+}
+
+#[tokio::test]
+async fn test_profile_merged_with_job_config() {
+    let job_conf = JobConfig::new(std::path::Path::new("../demo/conf/conf_profile.yaml"))
+        .await
+        .unwrap();
+    let params = Params {
+        file_path: "../demo/data/input/profile".to_owned(),
+        output_path: "../demo/data/output/csv/profile".to_owned(),
+        file_type: FileType::CSV,
+        mode: Mode::MASK,
+        ..Default::default()
+    };
+    let mut processor = FileProcessor::new(params.clone(), job_conf.clone()).await;
+    let metrics = processor.run().await.unwrap();
+    assert_eq!(metrics.metadata.failed_records, 0);
+    assert_eq!(
+        metrics.files[0].fields_matched,
+        vec![
+            "First_Name",
+            "last-name",
+            "Email",
+            "phone",
+            "birth_date",
+            "country"
+        ]
+    );
+
+    let records: Vec<csv::StringRecord> =
+        csv::Reader::from_path("../demo/data/output/csv/profile/customers.csv")
+            .unwrap()
+            .records()
+            .map(|record| record.unwrap())
+            .collect();
+    let record = &records[0];
+    assert_eq!(&record[0], "C001");
+    assert_ne!(&record[1], "Anna");
+    assert_ne!(&record[2], "Schmidt");
+    // the rule of the job config over the fake email of the profile
+    assert_eq!(&record[3], "#####");
+    assert_ne!(&record[4], "+49 30 1234567");
+    assert_eq!(record[4].len(), "+49 30 1234567".len());
+    assert_eq!(&record[5], "1984-01-01");
+    assert_eq!(&record[6], "Schmidt GmbH");
+    assert_eq!(&record[7], "#####");
+
+    // the profile of the params over the job config, it changes the output
+    let pci = Params {
+        profile: Some(Profile::Pci),
+        ..params.clone()
+    };
+    assert_ne!(
+        FileProcessor::hash_config(&pci, &job_conf),
+        FileProcessor::hash_config(&params, &job_conf)
+    );
+    let processor = FileProcessor::new(pci, job_conf).await;
+    assert_eq!(processor.process_runtime.profile, Some(Profile::Pci));
+}
@@ -0,0 +1,105 @@
+use crate::{
+    audit::{
+        app::Summary,
+        db::{AuditLocation, Database},
+        query::{export_csv, export_json, AuditFilter},
+    },
+    models::{enums::Mode, params::Params},
+    strategy::profile::Profile,
+};
+
+async fn insert_audit(db: &mut Database, user: &str, mode: Mode, successed: bool) -> i64 {
+    let params = Params {
+        mode,
+        ..Default::default()
+    };
+    let summary = Summary {
+        user: user.to_owned(),
+        hostname: "query_test".to_owned(),
+        runtime_conf: serde_json::to_string(&params).unwrap(),
+        successed,
+        ..Default::default()
+    };
+    db.insert(&summary).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_audit_list() {
+    let mut db = Database::new(&AuditLocation::Memory).await.unwrap();
+    let user = format!("query_test_{}", std::process::id());
+    let mask_id = insert_audit(&mut db, &user, Mode::MASK, true).await;
+    let encrypt_id = insert_audit(&mut db, &user, Mode::ENCRYPT, false).await;
+
+    let filter = AuditFilter {
+        user: Some(user.clone()),
+        ..Default::default()
+    };
+    let records = db.list(&filter).await.unwrap();
+    assert_eq!(records.len(), 2);
+    // the latest first
+    assert_eq!(records[0].id, encrypt_id);
+    assert_eq!(records[0].mode, Some("ENCRYPT".to_string()));
+
+    let filter = AuditFilter {
+        user: Some(user.clone()),
+        mode: Some(Mode::MASK),
+        successed: Some(true),
+        ..Default::default()
+    };
+    let records = db.list(&filter).await.unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].id, mask_id);
+
+    let filter = AuditFilter {
+        user: Some(user.clone()),
+        since: Some("2000-01-01".to_owned()),
+        until: Some("2000-12-31".to_owned()),
+        ..Default::default()
+    };
+    assert!(db.list(&filter).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_audit_show_and_export() {
+    let mut db = Database::new(&AuditLocation::Memory).await.unwrap();
+    let user = format!("query_test_show_{}", std::process::id());
+    let id = insert_audit(&mut db, &user, Mode::DECRYPT, true).await;
+
+    let record = db.show(id).await.unwrap();
+    assert_eq!(record.user, user);
+    assert_eq!(record.mode, Some("DECRYPT".to_string()));
+    assert!(db.audit_files(id).await.unwrap().is_empty());
+    assert!(db.show(-1).await.is_err());
+
+    let mut csv = Vec::new();
+    export_csv(std::slice::from_ref(&record), &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("id,created_at,user,hostname,mode,"));
+    assert_eq!(csv.lines().count(), 2);
+
+    let mut json = Vec::new();
+    export_json(&[record], &mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json[0]["id"], id);
+}
+
+#[tokio::test]
+async fn test_audit_profile() {
+    let mut db = Database::new(&AuditLocation::Memory).await.unwrap();
+    let params = Params {
+        profile: Some(Profile::HipaaSafeHarbor),
+        ..Default::default()
+    };
+    let summary = Summary {
+        user: "query_test_profile".to_owned(),
+        runtime_conf: serde_json::to_string(&params).unwrap(),
+        ..Default::default()
+    };
+    let id = db.insert(&summary).await.unwrap();
+    // the profile is recorded by name
+    let record = db.show(id).await.unwrap();
+    assert_eq!(record.profile, Some("hipaa-safe-harbor".to_string()));
+
+    let id = insert_audit(&mut db, "query_test_profile", Mode::MASK, true).await;
+    assert_eq!(db.show(id).await.unwrap().profile, None);
+}
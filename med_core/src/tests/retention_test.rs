@@ -0,0 +1,210 @@
+use sqlx::Row;
+
+use crate::audit::{
+    app::Summary,
+    chain::ChainIssueKind,
+    db::{AuditFile, AuditLocation, Database},
+    retention::RetentionPolicy,
+};
+use crate::models::{
+    enums::RecordErrorKind,
+    metrics::{Metadata, Metrics, RecordError},
+};
+use crate::utils::error::{MedError, MedErrorType};
+
+async fn memory_db() -> Database {
+    let mut db = Database::new(&AuditLocation::Memory).await.unwrap();
+    // not the key of the environment
+    db.chain_key = None;
+    db
+}
+
+/// Returns the id of a sealed run, with a completed file
+async fn sealed_run(db: &mut Database) -> i64 {
+    let id = db.insert(&Summary::default()).await.unwrap();
+    let file = AuditFile {
+        input_path: format!("{}.csv", id),
+        input_hash: "input".to_string(),
+        output_path: format!("output/{}.csv", id),
+        ..Default::default()
+    };
+    db.insert_file(id, "config", &file).await.unwrap();
+    db.update(id, &Summary::default()).await.unwrap();
+    id
+}
+
+#[tokio::test]
+async fn test_purge_max_rows() {
+    let mut db = memory_db().await;
+    for _ in 0..5 {
+        sealed_run(&mut db).await;
+    }
+
+    let policy = RetentionPolicy {
+        max_rows: Some(2),
+        ..Default::default()
+    };
+    let report = db.purge(&policy).await.unwrap();
+    assert_eq!(report.purged_records, 3);
+    assert_eq!(report.purged_files, 3);
+    assert_eq!(report.kept_records, 0);
+    assert_eq!(report.anchor_index, Some(3));
+    assert!(db.audit_files(1).await.unwrap().is_empty());
+    assert_eq!(db.audit_files(5).await.unwrap().len(), 1);
+
+    // the chain is verified from the anchor
+    let chain = db.verify().await.unwrap();
+    assert!(chain.is_valid());
+    assert_eq!(chain.anchor_index, Some(3));
+    assert_eq!(chain.sealed, 2);
+
+    // a deletion after the anchor is still detected
+    sqlx::query("DELETE FROM audit WHERE id = 4")
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    assert!(!db.verify().await.unwrap().is_valid());
+}
+
+#[tokio::test]
+async fn test_purge_anchor_is_sealed() {
+    let mut db = memory_db().await;
+    db.chain_key = Some("secret".to_string());
+    for _ in 0..5 {
+        sealed_run(&mut db).await;
+    }
+    let policy = RetentionPolicy {
+        max_rows: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(db.purge(&policy).await.unwrap().anchor_index, Some(2));
+    assert!(db.verify().await.unwrap().is_valid());
+
+    // the next record deleted and the anchor moved over it, without the key
+    let next = sqlx::query("SELECT row_hash FROM audit WHERE id = 3")
+        .fetch_one(&db.pool)
+        .await
+        .unwrap()
+        .get::<String, &str>("row_hash");
+    sqlx::query("DELETE FROM audit WHERE id = 3")
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE audit_chain_anchor SET chain_index = 3, row_hash = ?1 WHERE id = 1")
+        .bind(next)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+    let report = db.verify().await.unwrap();
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].kind, ChainIssueKind::Anchor);
+    assert_eq!(report.issues[0].chain_index, 3);
+}
+
+#[tokio::test]
+async fn test_purge_keeps_unfinished_runs() {
+    let mut db = memory_db().await;
+    sealed_run(&mut db).await;
+    // a run in progress, its record is updated at its end
+    let running = db.insert(&Summary::default()).await.unwrap();
+
+    let policy = RetentionPolicy {
+        max_rows: Some(0),
+        ..Default::default()
+    };
+    let report = db.purge(&policy).await.unwrap();
+    assert_eq!(report.purged_records, 1);
+
+    let summary = Summary {
+        elapsed_time: "1s".to_string(),
+        ..Default::default()
+    };
+    db.update(running, &summary).await.unwrap();
+    assert!(db.verify().await.unwrap().is_valid());
+}
+
+#[tokio::test]
+async fn test_purge_all_continues_chain() {
+    let mut db = memory_db().await;
+    for _ in 0..3 {
+        sealed_run(&mut db).await;
+    }
+    let policy = RetentionPolicy {
+        max_rows: Some(0),
+        ..Default::default()
+    };
+    assert_eq!(db.purge(&policy).await.unwrap().purged_records, 3);
+    db.vacuum().await.unwrap();
+
+    // the next run is sealed after the anchor
+    sealed_run(&mut db).await;
+    let chain = db.verify().await.unwrap();
+    assert!(chain.is_valid());
+    assert_eq!(chain.head_index, Some(4));
+}
+
+#[tokio::test]
+async fn test_purge_keeps_chain_prefix() {
+    let mut db = memory_db().await;
+    // the first run is sealed after the second one
+    let first = db.insert(&Summary::default()).await.unwrap();
+    let second = db.insert(&Summary::default()).await.unwrap();
+    db.update(second, &Summary::default()).await.unwrap();
+    db.update(first, &Summary::default()).await.unwrap();
+
+    let policy = RetentionPolicy {
+        max_rows: Some(1),
+        ..Default::default()
+    };
+    let report = db.purge(&policy).await.unwrap();
+    assert_eq!(report.purged_records, 0);
+    assert_eq!(report.kept_records, 1);
+    assert!(db.verify().await.unwrap().is_valid());
+
+    // the records of the last day are kept
+    let policy = RetentionPolicy {
+        max_age_days: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(db.purge(&policy).await.unwrap().purged_records, 0);
+    assert!(db.purge(&RetentionPolicy::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_max_failure_reasons() {
+    let mut db = memory_db().await;
+    db.max_failure_reasons = Some(1);
+    let reasons: Vec<RecordError> = (0..3)
+        .map(|_| {
+            RecordError::new(
+                "a.csv",
+                RecordErrorKind::Encrypt,
+                MedError {
+                    message: None,
+                    cause: None,
+                    error_type: MedErrorType::CryptoError,
+                },
+            )
+        })
+        .collect();
+    let summary = Summary {
+        metrics: Metrics {
+            metadata: Metadata {
+                total_records: 3,
+                failed_records: 3,
+                record_failed_reason: reasons,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let id = db.insert(&summary).await.unwrap();
+    db.update(id, &summary).await.unwrap();
+
+    let record = db.show(id).await.unwrap();
+    assert_eq!(record.failed_records, 3);
+    let stored: Vec<serde_json::Value> =
+        serde_json::from_str(&record.record_failed_reason.unwrap()).unwrap();
+    assert_eq!(stored.len(), 1);
+}
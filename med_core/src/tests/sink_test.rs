@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::audit::{
+    app::{Audit, Summary},
+    db::AuditLocation,
+    sink::{AuditEvent, AuditSink, SinkConfig, SyslogSink, WebhookSink},
+};
+
+const SINK_DIR: &str = "../demo/data/output/audit/sink";
+
+fn summary() -> Summary {
+    Summary {
+        user: "sink_test".to_string(),
+        hostname: "localhost".to_string(),
+        runtime_conf: r#"{"mode":"MASK"}"#.to_string(),
+        successed: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_sink_config_from_str() {
+    assert_eq!(
+        SinkConfig::from_str("jsonl:/var/log/med/audit.jsonl"),
+        Ok(SinkConfig::JsonLines(PathBuf::from(
+            "/var/log/med/audit.jsonl"
+        )))
+    );
+    assert_eq!(
+        SinkConfig::from_str("syslog"),
+        Ok(SinkConfig::Syslog(PathBuf::from("/dev/log")))
+    );
+    assert_eq!(
+        SinkConfig::from_str("webhook:http://localhost:8080/audit"),
+        Ok(SinkConfig::Webhook(
+            "http://localhost:8080/audit".to_string()
+        ))
+    );
+    assert!(SinkConfig::from_str("jsonl").is_err());
+    assert_eq!(
+        SinkConfig::from_str("webhook:https://siem.local/med"),
+        Ok(SinkConfig::Webhook("https://siem.local/med".to_string()))
+    );
+    assert!(SinkConfig::from_str("webhook:ftp://siem").is_err());
+    assert!(SinkConfig::from_str("kafka:topic").is_err());
+    // the cli sinks win over the job config
+    let sinks = SinkConfig::resolve(&["syslog".to_string()], &["jsonl:a".to_string()]).unwrap();
+    assert_eq!(sinks, vec![SinkConfig::Syslog(PathBuf::from("/dev/log"))]);
+}
+
+#[tokio::test]
+async fn test_jsonl_sink() {
+    let path = PathBuf::from(format!("{}/audit.jsonl", SINK_DIR));
+    if path.exists() {
+        std::fs::remove_file(&path).unwrap();
+    }
+    let mut audit = Audit::new(&AuditLocation::Memory)
+        .await
+        .unwrap()
+        .with_sinks(vec![SinkConfig::JsonLines(path.clone()).build()]);
+    audit.summary = summary();
+    let first = audit.insert().await.unwrap();
+    audit.id = None;
+    let second = audit.insert().await.unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let events: Vec<serde_json::Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["audit_id"], first);
+    assert_eq!(events[1]["audit_id"], second);
+    assert_eq!(events[1]["user"], "sink_test");
+    assert_eq!(events[1]["runtime_conf"]["mode"], "MASK");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_syslog_sink() {
+    std::fs::create_dir_all(SINK_DIR).unwrap();
+    let socket = PathBuf::from(format!("{}/syslog.sock", SINK_DIR));
+    if socket.exists() {
+        std::fs::remove_file(&socket).unwrap();
+    }
+    let listener = std::os::unix::net::UnixDatagram::bind(&socket).unwrap();
+
+    let event = AuditEvent::new(7, &summary());
+    SinkConfig::Syslog(socket.clone())
+        .build()
+        .emit(&event)
+        .await
+        .unwrap();
+
+    let mut buf = [0u8; 4096];
+    let n = listener.recv(&mut buf).unwrap();
+    let message = String::from_utf8_lossy(&buf[..n]).to_string();
+    assert_eq!(message, SyslogSink::message(&event).unwrap());
+    // the user facility, informational
+    assert!(message.starts_with("<14>med["));
+    assert!(message.contains(r#""audit_id":7"#));
+}
+
+/// A local http stand-in, returns the received request
+async fn webhook_stand_in(status: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/audit", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // read the headers and the body of the content length
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+                let length = headers
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(": ")?;
+                        name.eq_ignore_ascii_case("content-length").then_some(value)
+                    })
+                    .and_then(|length| length.parse::<usize>().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    break;
+                }
+            }
+            if n == 0 {
+                break;
+            }
+        }
+        let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+        stream.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8(request).unwrap()
+    });
+    (url, handle)
+}
+
+#[tokio::test]
+async fn test_webhook_sink() {
+    let (url, handle) = webhook_stand_in("200 OK").await;
+    let sink = WebhookSink::new(&url);
+    sink.emit(&AuditEvent::new(9, &summary())).await.unwrap();
+
+    let request = handle.await.unwrap();
+    assert!(request.starts_with("POST /audit HTTP/1.1\r\n"));
+    assert!(request
+        .to_lowercase()
+        .contains("content-type: application/json\r\n"));
+    let body = request.split_once("\r\n\r\n").unwrap().1;
+    let event: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(event["audit_id"], 9);
+}
+
+#[tokio::test]
+async fn test_webhook_sink_failed() {
+    let (url, handle) = webhook_stand_in("500 Internal Server Error").await;
+    let sink = WebhookSink::new(&url);
+    let err = sink
+        .emit(&AuditEvent::new(9, &summary()))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("500"));
+    // the url is not in the error
+    assert!(!err.to_string().contains(&url));
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_webhook_sink_timeout() {
+    // the stand-in never responds
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/audit", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        drop(stream);
+    });
+    let sink = WebhookSink {
+        timeout: std::time::Duration::from_millis(200),
+        ..WebhookSink::new(&url)
+    };
+    let err = sink
+        .emit(&AuditEvent::new(9, &summary()))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("timed out"));
+    handle.abort();
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use crate::{
+    strategy::subset::{sampled, Subset},
+    utils::error::MedErrorType,
+};
+
+fn record(fields: &[(&str, &str)]) -> HashMap<String, String> {
+    fields
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_sampled() {
+    let kept = (0..10000)
+        .filter(|i| sampled(42, &i.to_string(), 10.0))
+        .count();
+    assert!((800..1200).contains(&kept), "{}", kept);
+    assert!((0..100).all(|i| sampled(42, &i.to_string(), 100.0)));
+    assert!((0..100).all(|i| !sampled(42, &i.to_string(), 0.0)));
+    // the same with the seed
+    assert_eq!(sampled(7, "C001", 50.0), sampled(7, "C001", 50.0));
+}
+
+#[test]
+fn test_subset_keeps() {
+    let subset: Subset =
+        serde_yaml::from_str("drop_when: country == 'FR'\nsample: 50\nsample_key: customer_id")
+            .unwrap();
+    assert!(!subset.is_empty());
+    assert!(subset.filters_records());
+    assert!(!subset.keeps(&record(&[("country", "FR")]), 0, 42));
+
+    // the records of the same key are kept or dropped together
+    let customer = |id: &str| record(&[("country", "DE"), ("customer_id", id)]);
+    for id in ["C001", "C002", "C003", "C004"] {
+        assert_eq!(
+            subset.keeps(&customer(id), 0, 42),
+            subset.keeps(&customer(id), 99, 42)
+        );
+    }
+
+    let subset: Subset = serde_yaml::from_str("limit: 2\ndrop_fields: [notes]").unwrap();
+    assert!(subset.keeps(&(), 0, 42));
+    assert!(!subset.limit_reached(1));
+    assert!(subset.limit_reached(2));
+
+    let subset: Subset = serde_yaml::from_str("drop_fields: [notes]").unwrap();
+    assert!(!subset.filters_records());
+    assert!(Subset::default().is_empty());
+}
+
+#[test]
+fn test_subset_validate() {
+    let subset: Subset = serde_yaml::from_str("sample: 120").unwrap();
+    assert_eq!(
+        subset.validate().unwrap_err().error_type,
+        MedErrorType::ConfigError
+    );
+    assert!(Subset::default().validate().is_ok());
+}
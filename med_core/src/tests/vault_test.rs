@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    app::{csv::csv_processor, processor::ProcessRuntime},
+    models::enums::{Mode, RecordErrorKind},
+    utils::error::MedErrorType,
+    vault::token::{vault_path, TokenVault, TOKEN_PREFIX},
+};
+
+const VAULT_DIR: &str = "../demo/data/output/vault";
+
+/// Returns a new vault file path of the test
+fn new_vault(name: &str) -> PathBuf {
+    let path = Path::new(VAULT_DIR).join(format!("{}.db", name));
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+    path
+}
+
+#[test]
+fn test_vault_path() {
+    assert_eq!(
+        vault_path(Some("cli.db"), Some("conf.db")).unwrap(),
+        PathBuf::from("cli.db")
+    );
+    assert_eq!(
+        vault_path(Some(" "), None).unwrap_err().error_type,
+        MedErrorType::ConfigError
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_vault_tokenize() {
+    let path = new_vault("tokenize");
+    let vault = TokenVault::open(&path, "vault_key").await.unwrap();
+
+    let token = vault.tokenize("Antony Brandt").unwrap();
+    assert!(token.starts_with(TOKEN_PREFIX));
+    assert_eq!(token.len(), TOKEN_PREFIX.len() + 24);
+    // the same value gets the same token, another value another token
+    assert_eq!(vault.tokenize("Antony Brandt").unwrap(), token);
+    assert_ne!(vault.tokenize("Bob").unwrap(), token);
+    assert_eq!(vault.tokenize("").unwrap(), "");
+    assert_eq!(vault.detokenize(&token).unwrap(), "Antony Brandt");
+
+    // the tokens are kept in the vault file
+    let reopened = TokenVault::open(&path, "vault_key").await.unwrap();
+    assert_eq!(reopened.tokenize("Antony Brandt").unwrap(), token);
+    assert_eq!(reopened.detokenize(&token).unwrap(), "Antony Brandt");
+    assert_eq!(
+        reopened.detokenize("tok_unknown").unwrap_err().error_type,
+        MedErrorType::CryptoError
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_vault_wrong_key() {
+    let path = new_vault("wrong_key");
+    TokenVault::open(&path, "vault_key").await.unwrap();
+
+    let err = TokenVault::open(&path, "other_key").await.unwrap_err();
+    assert_eq!(err.error_type, MedErrorType::CryptoError);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_vault_csv_round_trip() {
+    let path = new_vault("csv");
+    let vault = TokenVault::open(&path, "vault_key").await.unwrap();
+    let files_path = "../demo/data/input/csv/random_data.csv";
+    let tokenized_path = "../demo/data/output/vault/tokenized.csv";
+    let detokenized_path = "../demo/data/output/vault/detokenized.csv";
+
+    for (mode, input, output) in [
+        (Mode::TOKENIZE, files_path, tokenized_path),
+        (Mode::DETOKENIZE, tokenized_path, detokenized_path),
+    ] {
+        let (tx_metadata, rx_metadata) = flume::unbounded();
+        let process_runtime = ProcessRuntime {
+            fields: vec!["name".to_string()],
+            mode,
+            vault: Some(vault.clone()),
+            ..Default::default()
+        };
+        csv_processor(tx_metadata.clone(), input, output, process_runtime).unwrap();
+        drop(tx_metadata);
+        rx_metadata
+            .iter()
+            .for_each(|item| assert_eq!(item.metadata.failed_records, 0));
+    }
+
+    let tokenized = std::fs::read_to_string(tokenized_path).unwrap();
+    assert!(!tokenized.contains("Antony Brandt"));
+    assert!(tokenized.contains(TOKEN_PREFIX));
+    assert_eq!(
+        std::fs::read_to_string(detokenized_path).unwrap(),
+        std::fs::read_to_string(files_path).unwrap()
+    );
+
+    // the unknown tokens are reported, the field is emptied
+    let other = TokenVault::open(&new_vault("csv_other"), "vault_key")
+        .await
+        .unwrap();
+    let (tx_metadata, rx_metadata) = flume::unbounded();
+    let process_runtime = ProcessRuntime {
+        fields: vec!["name".to_string()],
+        mode: Mode::DETOKENIZE,
+        vault: Some(other),
+        ..Default::default()
+    };
+    csv_processor(
+        tx_metadata.clone(),
+        tokenized_path,
+        "../demo/data/output/vault/unknown.csv",
+        process_runtime,
+    )
+    .unwrap();
+    drop(tx_metadata);
+    rx_metadata.iter().for_each(|item| {
+        assert!(item.metadata.failed_records > 0);
+        assert_eq!(
+            item.metadata.record_failed_reason[0].kind,
+            RecordErrorKind::Detokenize
+        );
+    });
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::app::worker::Worker;
+
+#[tokio::test]
+async fn test_worker_per_instance_size() {
+    let small = Worker::new(2).await.unwrap();
+    let large = Worker::new(3).await.unwrap();
+    assert_eq!(small.pool.current_num_threads(), 2);
+    assert_eq!(large.pool.current_num_threads(), 3);
+}
+
+#[tokio::test]
+async fn test_worker_execute() {
+    let worker = Worker::new(2).await.unwrap();
+    let (tx, rx) = flume::unbounded();
+    let done = Arc::new(AtomicUsize::new(0));
+
+    // more jobs than the queue capacity, the producer waits for the free slots
+    for i in 0..20 {
+        let tx = tx.clone();
+        let done = done.clone();
+        worker
+            .execute(move || {
+                done.fetch_add(1, Ordering::SeqCst);
+                tx.send(i).unwrap();
+            })
+            .await;
+    }
+    drop(tx);
+
+    assert_eq!(rx.iter().count(), 20);
+    assert_eq!(done.load(Ordering::SeqCst), 20);
+}
+
+#[tokio::test]
+async fn test_worker_job_panic() {
+    let worker = Worker::new(2).await.unwrap();
+    let (tx, rx) = flume::unbounded::<usize>();
+
+    // a panicked job releases its queue slot
+    for _ in 0..10 {
+        let tx = tx.clone();
+        worker
+            .execute(move || {
+                let _tx = tx;
+                panic!("job panicked");
+            })
+            .await;
+    }
+    drop(tx);
+
+    assert_eq!(rx.iter().count(), 0);
+}
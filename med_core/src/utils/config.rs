@@ -1,12 +1,48 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
 
+use crate::strategy::anonymity::KAnonymityConfig;
+use crate::strategy::profile::Profile;
+use crate::strategy::rule::{FieldRule, Strategy};
+use crate::strategy::subset::Subset;
 use crate::utils::error::{MedError, MedErrorType};
 
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct JobConfig {
     pub mask_symbols: String,
+    #[serde(default)]
     pub fields: Vec<String>,
+    /// the built-in profile of the fields found by their name, see [Profile]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    /// the masking strategy of the fields, by field name, the fields of a rule are masked too
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, FieldRule>,
+    /// the records and fields kept in the output
+    #[serde(default, skip_serializing_if = "Subset::is_empty")]
+    pub subset: Subset,
+    /// the quasi identifiers of the k-anonymity mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k_anonymity: Option<KAnonymityConfig>,
+    /// the seed of the strategies, random per run by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// the audit database location, see [AuditLocation](crate::audit::db::AuditLocation)
+    #[serde(default, skip_serializing)]
+    pub audit_db: Option<String>,
+    /// the audit sinks, see [SinkConfig](crate::audit::sink::SinkConfig)
+    #[serde(default, skip_serializing)]
+    pub audit_sinks: Vec<String>,
+    /// the maximum number of failure reasons stored per run in the audit database
+    #[serde(default, skip_serializing)]
+    pub audit_max_failure_reasons: Option<usize>,
+    /// the token vault path, see [vault_path](crate::vault::token::vault_path)
+    #[serde(default, skip_serializing)]
+    pub vault: Option<String>,
+    /// the key map path, see [KeyMap](crate::strategy::key::KeyMap)
+    #[serde(default, skip_serializing)]
+    pub key_map: Option<String>,
 }
 
 impl JobConfig {
@@ -22,8 +58,34 @@ impl JobConfig {
             }
         };
         let config: JobConfig = serde_yaml::from_reader(f)?;
+        config.subset.validate()?;
+        if let Some(k_anonymity) = &config.k_anonymity {
+            k_anonymity.validate()?;
+        }
         Ok(config)
     }
+
+    /// Returns the fields to process, the configured fields and the fields of the rules
+    pub fn masked_fields(&self) -> Vec<String> {
+        let mut fields = self.fields.clone();
+        self.rules.keys().for_each(|field| {
+            if !fields.contains(field) {
+                fields.push(field.to_owned());
+            }
+        });
+        fields
+    }
+
+    /// Returns true if a strategy of the rules, or of the profile, matches the predicate
+    pub fn any_strategy(&self, profile: Option<Profile>, predicate: fn(&Strategy) -> bool) -> bool {
+        self.rules.values().any(|rule| predicate(&rule.strategy))
+            || profile.is_some_and(|profile| {
+                profile
+                    .patterns()
+                    .iter()
+                    .any(|(_, strategy)| predicate(strategy))
+            })
+    }
 }
 
 #[cfg(test)]
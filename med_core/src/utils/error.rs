@@ -12,6 +12,7 @@ pub enum MedErrorType {
     SerdeJsonError,
     DatabaseError,
     CsvError,
+    CancelledError,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize)]
@@ -84,15 +85,15 @@ impl From<magic_crypt::MagicCryptError> for MedError {
     }
 }
 
-// impl From<rayon::ThreadPoolBuildError> for MedError {
-//     fn from(error: rayon::ThreadPoolBuildError) -> MedError {
-//         MedError {
-//             message: Some(error.to_string()),
-//             cause: Some("rayon worker error".to_string()),
-//             error_type: MedErrorType::WorkerError,
-//         }
-//     }
-// }
+impl From<rayon::ThreadPoolBuildError> for MedError {
+    fn from(error: rayon::ThreadPoolBuildError) -> MedError {
+        MedError {
+            message: Some(error.to_string()),
+            cause: Some("rayon worker error".to_string()),
+            error_type: MedErrorType::WorkerError,
+        }
+    }
+}
 #[cfg(not(tarpaulin_include))]
 impl From<Error> for MedError {
     fn from(error: Error) -> MedError {
@@ -1,18 +1,155 @@
-use crate::utils::error::MedError;
-use std::fs;
-use walkdir::{DirEntry, WalkDir};
-
-#[cfg(not(tarpaulin_include))]
-pub async fn create_output_dir(output_dir: &str, file_dir: &str) -> Result<(), MedError> {
-    WalkDir::new(file_dir)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_dir())
-        .for_each(|e| {
-            let output_path = format!("{}/{}", output_dir, e.path().display());
-            fs::create_dir_all(output_path).unwrap();
-        });
+use crate::models::enums::OutputPolicy;
+use crate::utils::error::{MedError, MedErrorType};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use walkdir::DirEntry;
+
+/// Create the directory, and its parents, for the output file
+pub fn create_output_dir(output_file: &Path) -> Result<(), MedError> {
+    if let Some(parent) = output_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
+/// Returns the output location of `file`, keeping its layout relative to the `input_root`, a
+/// single file input is written under the `output_root` by its name
+///
+/// # Arguments
+///
+/// * `input_root` - the root directory passed as the input
+/// * `file` - the file (or directory) found under the `input_root`
+/// * `output_root` - the root directory for the output
+pub fn output_file_path(input_root: &Path, file: &Path, output_root: &Path) -> PathBuf {
+    match file.strip_prefix(input_root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => output_root.join(relative),
+        _ => match file.file_name() {
+            Some(name) => output_root.join(name),
+            None => output_root.to_path_buf(),
+        },
+    }
+}
+
+/// Returns the path of `file` relative to the `input_root`, as a string
+pub fn relative_path(input_root: &Path, file: &Path) -> String {
+    match file.strip_prefix(input_root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative.display().to_string(),
+        _ => file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Returns the path to write to according to the [OutputPolicy], None if the file should be skipped
+pub fn resolve_output_path(path: PathBuf, policy: OutputPolicy) -> Option<PathBuf> {
+    if !path.exists() {
+        return Some(path);
+    }
+    match policy {
+        OutputPolicy::Overwrite => Some(path),
+        OutputPolicy::NoClobber => None,
+        OutputPolicy::Suffix => {
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let extension = path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            (1..)
+                .map(|n| path.with_file_name(format!("{}_{}{}", stem, n, extension)))
+                .find(|candidate| !candidate.exists())
+        }
+    }
+}
+
+/// Returns the absolute path, resolving symlinks for the part of the path which already exists
+pub fn absolute_path(path: &Path) -> Result<PathBuf, MedError> {
+    if path.exists() {
+        return Ok(fs::canonicalize(path)?);
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => absolute_path(parent)?,
+        _ => std::env::current_dir()?,
+    };
+    match path.file_name() {
+        Some(name) => Ok(parent.join(name)),
+        None => Ok(parent),
+    }
+}
+
+/// Returns true if the output directory is the input directory or one of its sub directories
+pub fn is_nested_output(file_dir: &str, output_dir: &str) -> Result<bool, MedError> {
+    let input = absolute_path(Path::new(file_dir))?;
+    let output = absolute_path(Path::new(output_dir))?;
+    Ok(output.starts_with(input))
+}
+
+/// Return an error if the output directory is inside the input directory
+pub fn ensure_output_outside_input(file_dir: &str, output_dir: &str) -> Result<(), MedError> {
+    match is_nested_output(file_dir, output_dir)? {
+        true => Err(MedError {
+            message: Some(format!(
+                "output directory {} is inside the input directory {}",
+                output_dir, file_dir
+            )),
+            cause: Some(
+                "use --allow-nested-output to write inside the input directory".to_string(),
+            ),
+            error_type: MedErrorType::ConfigError,
+        }),
+        false => Ok(()),
+    }
+}
+
+/// Returns the hidden temporary file, next to the output file, the output is written to before
+/// it is renamed to its final name
+pub fn temp_output_path(output_file: &Path) -> PathBuf {
+    let name = output_file
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    output_file.with_file_name(format!(".{}.med-tmp", name))
+}
+
+/// Flush the temporary file to disk and atomically rename it to the output file
+///
+/// # Arguments
+///
+/// * `file` - the written temporary file
+/// * `temp_file` - the path of the temporary file
+/// * `output_file` - the final path of the output file
+pub fn commit_output(file: File, temp_file: &Path, output_file: &Path) -> Result<(), MedError> {
+    file.sync_all()?;
+    drop(file);
+    fs::rename(temp_file, output_file)?;
+    sync_parent_dir(output_file)
+}
+
+/// Remove the temporary file of an output which failed
+pub fn discard_output(temp_file: &Path) {
+    if temp_file.exists() {
+        if let Err(err) = fs::remove_file(temp_file) {
+            tracing::warn!("failed to remove {}: {}", temp_file.display(), err);
+        }
+    }
+}
+
+/// Sync the directory entry of the renamed file, so the rename survives a crash
+#[cfg(unix)]
+fn sync_parent_dir(file: &Path) -> Result<(), MedError> {
+    let dir = match file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_file: &Path) -> Result<(), MedError> {
     Ok(())
 }
 
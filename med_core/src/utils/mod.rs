@@ -1,5 +1,7 @@
+pub mod cancel;
 pub mod config;
 pub mod crypto;
+pub mod digest;
 pub mod error;
 pub mod helpers;
 pub mod logger;
@@ -1,5 +1,6 @@
 use std::path::Path;
 
+use crate::strategy::{fake::FakeKind, profile::Profile, rule::Strategy};
 use crate::utils::{config::JobConfig, error::MedErrorType};
 
 #[tokio::test]
@@ -15,3 +16,32 @@ async fn test_new_config_failed_load() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_config_rules() {
+    let conf = JobConfig::new(Path::new("../demo/conf/conf_fake.yaml"))
+        .await
+        .unwrap();
+    assert_eq!(
+        conf.rules.get("email"),
+        Some(
+            &Strategy::Fake {
+                kind: FakeKind::Email
+            }
+            .into()
+        )
+    );
+    assert_eq!(conf.masked_fields(), vec!["name", "email", "phone"]);
+}
+
+#[tokio::test]
+async fn test_config_profile() {
+    let conf = JobConfig::new(Path::new("../demo/conf/conf_profile.yaml"))
+        .await
+        .unwrap();
+    assert_eq!(conf.profile, Some(Profile::GdprBasic));
+    // the fields are optional with a profile
+    let conf: JobConfig = serde_yaml::from_str("mask_symbols: '#'\nprofile: pci").unwrap();
+    assert!(conf.fields.is_empty());
+    assert!(serde_yaml::from_str::<JobConfig>("mask_symbols: '#'\nprofile: sox").is_err());
+}
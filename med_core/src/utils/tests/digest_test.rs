@@ -0,0 +1,67 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::utils::digest::{
+    hmac_sha256_hex, sha256_file, sha256_hex, HashingReader, HashingWriter,
+};
+use crate::utils::error::MedErrorType;
+
+#[tokio::test]
+async fn test_sha256_hex() {
+    assert_eq!(
+        sha256_hex(b"med"),
+        "6c6dab33994f8b98bb9db20ef4ed62d66b5f267cbcfc575b582e317fe2d58c62"
+    );
+}
+
+#[tokio::test]
+async fn test_hashing_reader() {
+    let data = std::fs::read("../demo/data/input/csv/random_data.csv").unwrap();
+    let mut reader = HashingReader::new(data.as_slice());
+    let mut content = String::new();
+    reader.read_to_string(&mut content).unwrap();
+
+    assert_eq!(content.as_bytes(), data.as_slice());
+    assert_eq!(reader.finalize(), sha256_hex(&data));
+    assert_eq!(
+        sha256_file(Path::new("../demo/data/input/csv/random_data.csv")).unwrap(),
+        sha256_hex(&data)
+    );
+}
+
+#[test]
+fn test_hashing_writer() {
+    let mut writer = HashingWriter::new(Vec::new());
+    writer.write_all(b"med").unwrap();
+    writer.flush().unwrap();
+    let (data, hash) = writer.into_parts();
+    assert_eq!(data, b"med");
+    assert_eq!(hash, sha256_hex(b"med"));
+}
+
+#[tokio::test]
+async fn test_sha256_file_failed() {
+    match sha256_file(Path::new("")) {
+        Ok(_) => unimplemented!(),
+        Err(err) => {
+            assert_eq!(err.error_type, MedErrorType::IoError);
+        }
+    }
+}
+
+#[test]
+fn test_hmac_sha256_hex() {
+    // RFC 4231 test case 2
+    assert_eq!(
+        hmac_sha256_hex(b"Jefe", b"what do ya want for nothing?"),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    // RFC 4231 test case 6, a key longer than the block size
+    assert_eq!(
+        hmac_sha256_hex(
+            &[0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First"
+        ),
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+    );
+}
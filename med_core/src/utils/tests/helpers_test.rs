@@ -1,6 +1,10 @@
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-use crate::utils::helpers::is_not_hidden;
+use crate::models::enums::OutputPolicy;
+use crate::utils::helpers::{
+    is_nested_output, is_not_hidden, output_file_path, resolve_output_path,
+};
 
 #[tokio::test]
 async fn test_is_not_hidden() {
@@ -16,3 +20,51 @@ async fn test_is_not_hidden() {
     let is_not_ignored = WalkDir::new(path).follow_links(false).into_iter().count();
     assert_eq!(is_not_ignored, 5);
 }
+
+#[tokio::test]
+async fn test_output_file_path() {
+    let output = output_file_path(
+        Path::new("../demo/data/input/csv"),
+        Path::new("../demo/data/input/csv/level/random_data.csv"),
+        Path::new("output"),
+    );
+    assert_eq!(output, PathBuf::from("output/level/random_data.csv"));
+
+    let output = output_file_path(
+        Path::new("../demo/data/input/csv/random_data.csv"),
+        Path::new("../demo/data/input/csv/random_data.csv"),
+        Path::new("output"),
+    );
+    assert_eq!(output, PathBuf::from("output/random_data.csv"));
+}
+
+#[tokio::test]
+async fn test_resolve_output_path() {
+    let exist = PathBuf::from("../demo/data/input/csv/random_data.csv");
+    let not_exist = PathBuf::from("../demo/data/input/csv/not_exist.csv");
+
+    assert_eq!(
+        resolve_output_path(exist.clone(), OutputPolicy::Overwrite),
+        Some(exist.clone())
+    );
+    assert_eq!(
+        resolve_output_path(exist.clone(), OutputPolicy::NoClobber),
+        None
+    );
+    assert_eq!(
+        resolve_output_path(exist, OutputPolicy::Suffix),
+        Some(PathBuf::from("../demo/data/input/csv/random_data_1.csv"))
+    );
+    assert_eq!(
+        resolve_output_path(not_exist.clone(), OutputPolicy::NoClobber),
+        Some(not_exist)
+    );
+}
+
+#[tokio::test]
+async fn test_is_nested_output() {
+    assert!(is_nested_output("../demo/data/input", "../demo/data/input/csv/output").unwrap());
+    assert!(is_nested_output("../demo/data/input", "../demo/data/../data/input").unwrap());
+    assert!(!is_nested_output("../demo/data/input", "../demo/data/output").unwrap());
+    assert!(!is_nested_output("../demo/data/input", "../demo/data/input_masked").unwrap());
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use colored::Colorize;
+use rand::RngCore;
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    Pool, Row, Sqlite,
+};
+use tokio::runtime::Handle;
+use tracing::info;
+
+use crate::models::enums::Standard;
+use crate::utils::crypto::Cypher;
+use crate::utils::digest::hmac_sha256_hex;
+use crate::utils::error::{MedError, MedErrorType};
+
+/// The environment variable of the token vault path
+pub const VAULT_ENV: &str = "MED_VAULT";
+
+/// The migrations of the token vault, from `db/vault`
+pub static VAULT_MIGRATOR: Migrator = sqlx::migrate!("../db/vault");
+
+/// The prefix of the tokens
+pub const TOKEN_PREFIX: &str = "tok_";
+
+/// The random bytes of a token, the tokens have the same length whatever the value
+const TOKEN_BYTES: usize = 12;
+
+/// The value whose keyed hash identifies the key of the vault
+const KEY_CHECK: &[u8] = b"med-token-vault";
+
+/// The local token vault, the values are stored encrypted with the key, and looked up by their
+/// keyed hash, the same value always gets the same token
+///
+/// The vault is used from the worker threads, every new token is stored before it is returned,
+/// so no output holds a token missing from the vault
+#[derive(Clone)]
+pub struct TokenVault {
+    pool: Pool<Sqlite>,
+    handle: Handle,
+    key: String,
+    cypher: Cypher,
+    /// the tokens of the values, by their keyed hash, and the values of the tokens
+    tokens: Arc<RwLock<HashMap<String, String>>>,
+    values: Arc<RwLock<HashMap<String, String>>>,
+}
+
+/// The key is a secret, never printed
+impl std::fmt::Debug for TokenVault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenVault")
+            .field("pool", &self.pool)
+            .field("key", &"***")
+            .finish()
+    }
+}
+
+/// Returns the vault path from, in order, the cli, the `MED_VAULT` environment variable, the
+/// job configuration, or `med_vault.db` in the config directory by default
+pub fn vault_path(cli: Option<&str>, conf: Option<&str>) -> Result<PathBuf, MedError> {
+    let env = std::env::var(VAULT_ENV).ok();
+    match cli.or(env.as_deref()).or(conf) {
+        Some(path) if !path.trim().is_empty() => Ok(PathBuf::from(path.trim())),
+        Some(_) => Err(MedError {
+            message: Some("the token vault path is empty".to_string()),
+            cause: Some(format!("check --vault or {}", VAULT_ENV)),
+            error_type: MedErrorType::ConfigError,
+        }),
+        None => match dirs::config_dir() {
+            Some(dir) => Ok(dir.join("med_vault.db")),
+            None => Err(MedError {
+                message: Some("no config directory found for the token vault".to_string()),
+                cause: Some(format!("set the vault path with --vault or {}", VAULT_ENV)),
+                error_type: MedErrorType::ConfigError,
+            }),
+        },
+    }
+}
+
+impl TokenVault {
+    /// Returns the token vault of the file, created and migrated if needed, the key must be the
+    /// key the vault was created with
+    pub async fn open(path: &Path, key: &str) -> Result<Self, MedError> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        info!("token vault {}", path.display().to_string().bold().green());
+        let connection_options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(Duration::from_secs(30));
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(connection_options)
+            .await?;
+        VAULT_MIGRATOR.run(&pool).await?;
+
+        let key_check = hmac_sha256_hex(key.as_bytes(), KEY_CHECK);
+        sqlx::query("INSERT OR IGNORE INTO vault ( id, key_check ) VALUES ( 1, ?1 );")
+            .bind(&key_check)
+            .execute(&pool)
+            .await?;
+        let stored: String = sqlx::query("SELECT key_check FROM vault WHERE id = 1;")
+            .fetch_one(&pool)
+            .await?
+            .get("key_check");
+        if stored != key_check {
+            return Err(MedError {
+                message: Some(format!(
+                    "the key does not open the token vault {}",
+                    path.display()
+                )),
+                cause: Some("use the key the vault was created with".to_string()),
+                error_type: MedErrorType::CryptoError,
+            });
+        }
+
+        Ok(TokenVault {
+            pool,
+            handle: Handle::current(),
+            key: key.to_string(),
+            cypher: Cypher::new(key),
+            tokens: Arc::new(RwLock::new(HashMap::new())),
+            values: Arc::new(RwLock::new(HashMap::new())),
+        })
+    }
+
+    /// Returns the token of the value, a new random token if the value was never tokenized, the
+    /// empty values are kept empty
+    pub fn tokenize(&self, value: &str) -> Result<String, MedError> {
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+        let lookup = hmac_sha256_hex(self.key.as_bytes(), value.as_bytes());
+        if let Some(token) = self.tokens.read().unwrap().get(&lookup) {
+            return Ok(token.to_owned());
+        }
+        let token = self.block_on(self.store(&lookup, value))?;
+        self.tokens
+            .write()
+            .unwrap()
+            .insert(lookup, token.to_owned());
+        Ok(token)
+    }
+
+    /// Returns the value of the token
+    pub fn detokenize(&self, token: &str) -> Result<String, MedError> {
+        if token.is_empty() {
+            return Ok(String::new());
+        }
+        if let Some(value) = self.values.read().unwrap().get(token) {
+            return Ok(value.to_owned());
+        }
+        let value = self.block_on(self.load(token))?;
+        self.values
+            .write()
+            .unwrap()
+            .insert(token.to_owned(), value.to_owned());
+        Ok(value)
+    }
+
+    /// Privite function store the value with a new token, unless the value was stored, by
+    /// another worker or process, in the meantime, returns the token of the value
+    async fn store(&self, lookup: &str, value: &str) -> Result<String, MedError> {
+        let encrypted = self.cypher.encrypt(value, &Standard::AES256)?;
+        loop {
+            let mut bytes = [0u8; TOKEN_BYTES];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let token = format!("{}{}", TOKEN_PREFIX, hex::encode(bytes));
+            sqlx::query(
+                "INSERT OR IGNORE INTO token ( token, lookup, value ) VALUES ( ?1, ?2, ?3 );",
+            )
+            .bind(&token)
+            .bind(lookup)
+            .bind(&encrypted)
+            .execute(&self.pool)
+            .await?;
+            // a token collision is retried with a new token
+            if let Some(row) = sqlx::query("SELECT token FROM token WHERE lookup = ?1;")
+                .bind(lookup)
+                .fetch_optional(&self.pool)
+                .await?
+            {
+                return Ok(row.get("token"));
+            }
+        }
+    }
+
+    /// Privite function returns the decrypted value of the token
+    async fn load(&self, token: &str) -> Result<String, MedError> {
+        let row = sqlx::query("SELECT value FROM token WHERE token = ?1;")
+            .bind(token)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => self
+                .cypher
+                .decrypt(&row.get::<String, &str>("value"), &Standard::AES256),
+            None => Err(MedError {
+                message: Some(format!("token {} not found in the vault", token)),
+                cause: Some("the value was not tokenized with this vault".to_string()),
+                error_type: MedErrorType::CryptoError,
+            }),
+        }
+    }
+
+    /// Privite function run the vault query from a worker thread, or from a runtime thread
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match Handle::try_current() {
+            Ok(_) => tokio::task::block_in_place(|| self.handle.block_on(future)),
+            Err(_) => self.handle.block_on(future),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/vault_test.rs"]
+mod vault_test;